@@ -1,7 +1,12 @@
-use clap::Parser;
+use clap::{error::ErrorKind, CommandFactory, Parser};
+use delta_operator_crd::{jobs, maintenance::Action, policy, telemetry::ENV_OTLP_ENDPOINT};
+use std::path::PathBuf;
 use std::time::Duration;
 use std::{error::Error, str::FromStr};
+
+use crate::controller::executor::ExecutorKind;
 const DEFAULT_LISTEN_ADDRES: &str = "0.0.0.0:8080";
+const DEFAULT_WEBHOOK_LISTEN_ADDRESS: &str = "0.0.0.0:8443";
 
 #[derive(Parser, Debug)]
 #[clap(author = "Author Name", version, about)]
@@ -9,24 +14,145 @@ const DEFAULT_LISTEN_ADDRES: &str = "0.0.0.0:8080";
 pub(crate) struct Arguments {
     #[clap(long, env, required = false, default_value = DEFAULT_LISTEN_ADDRES)]
     pub listen_address: String,
-    #[clap(long, env, required = true)]
-    pub image: String,
+    #[clap(long, env)]
+    pub image: Option<String>,
     #[clap(long, env, value_parser = parse_key_val::<String, String>)]
     pub worker_labels: Vec<(String, String)>,
     #[clap(long, env, value_parser = parse_key_val::<String, String>)]
     pub worker_annotations: Vec<(String, String)>,
     #[clap(long, env, default_value = "delta-operator-system")]
     pub worker_namespace: String,
-    #[clap(long, env, required = true)]
-    pub worker_service_account: String,
+    #[clap(long, env)]
+    pub worker_service_account: Option<String>,
     #[clap(long, env)]
     pub worker_max_cpu: Option<String>,
     #[clap(long, env)]
     pub worker_max_ram: Option<String>,
+    /// Prometheus Pushgateway that workers push their metrics to before they exit
+    #[clap(long, env)]
+    pub worker_pushgateway_url: Option<String>,
+    /// Retries of a failed worker before its job fails
+    #[clap(long, env, default_value_t = jobs::DEFAULT_BACKOFF_LIMIT)]
+    pub worker_backoff_limit: i32,
+    /// How long a worker job may run before it is stopped and fails. `0s` lets jobs run
+    /// until they finish.
+    #[clap(long, env, value_parser = parse_duration, default_value = "6h")]
+    pub worker_active_deadline: Duration,
+    /// How long Kubernetes keeps finished worker jobs. `0s` keeps them until the
+    /// history limits delete them.
+    #[clap(long, env, value_parser = parse_duration, default_value = "24h")]
+    pub worker_ttl_after_finished: Duration,
+    /// Successful worker jobs kept per table
+    #[clap(long, env, default_value_t = jobs::DEFAULT_SUCCESSFUL_JOBS_HISTORY_LIMIT)]
+    pub worker_successful_jobs_history_limit: i32,
+    /// Failed worker jobs kept per table
+    #[clap(long, env, default_value_t = jobs::DEFAULT_FAILED_JOBS_HISTORY_LIMIT)]
+    pub worker_failed_jobs_history_limit: i32,
     #[clap(long, env)]
     pub label_selector: Option<String>,
+    /// Namespaces to watch, each with its own watch. Watches all namespaces when empty.
+    #[clap(long, env, value_delimiter = ',')]
+    pub watch_namespaces: Vec<String>,
+    /// Label selector of the namespaces to watch, within `--watch-namespaces` when set
+    #[clap(long, env)]
+    pub namespace_selector: Option<String>,
     #[clap(long, env, value_parser = parse_duration, default_value = "1h")]
     pub resync_interval: Duration,
+    #[clap(long, env, value_parser = parse_duration, default_value = "24h")]
+    pub default_checkpoint_interval: Duration,
+    #[clap(long, env, default_value_t = policy::DEFAULT_CHECKPOINT_COMMIT_INTERVAL)]
+    pub default_checkpoint_commit_interval: i64,
+    #[clap(long, env, value_parser = parse_duration, default_value = "24h")]
+    pub default_optimize_interval: Duration,
+    #[clap(long, env, default_value_t = policy::DEFAULT_OPTIMIZE_COMMIT_INTERVAL)]
+    pub default_optimize_commit_interval: i64,
+    #[clap(long, env, value_parser = parse_duration, default_value = "720h")]
+    pub default_vacuum_interval: Duration,
+    #[clap(long, env, default_value_t = policy::DEFAULT_VACUUM_COMMIT_INTERVAL)]
+    pub default_vacuum_commit_interval: i64,
+    #[arg(value_enum)]
+    #[clap(long, env, default_value = "job")]
+    pub executor: ExecutorKind,
+    /// Maximum number of maintenance actions run at once by the `inprocess` executor
+    #[clap(long, env, default_value_t = 2)]
+    pub max_concurrent_maintenance: usize,
+    /// Maximum number of maintenance actions running across all tables
+    #[clap(long, env)]
+    pub maintenance_limit: Option<usize>,
+    /// Maximum number of maintenance actions running for tables in one namespace
+    #[clap(long, env)]
+    pub maintenance_limit_per_namespace: Option<usize>,
+    /// Maximum number of maintenance actions of the same kind running at once
+    #[clap(long, env)]
+    pub maintenance_limit_per_action: Option<usize>,
+    /// Upper bound of the random delay before queued maintenance starts
+    #[clap(long, env, value_parser = parse_duration, default_value = "0s")]
+    pub maintenance_jitter: Duration,
+    /// How often queued maintenance is checked against the limits
+    #[clap(long, env, value_parser = parse_duration, default_value = "10s")]
+    pub maintenance_queue_interval: Duration,
+    /// Order in which due actions run within a table's maintenance pipeline. Actions not
+    /// listed run last.
+    #[arg(value_enum)]
+    #[clap(
+        long,
+        env,
+        value_delimiter = ',',
+        default_value = "optimize,checkpoint,vacuum"
+    )]
+    pub maintenance_order: Vec<Action>,
+    /// Report status without creating tables, updating schemas or running maintenance
+    #[clap(long, env)]
+    pub dry_run: bool,
+    /// ConfigMap whose `pause-selector` entry selects tables to pause
+    #[clap(long, env)]
+    pub pause_config_map: Option<String>,
+    #[clap(long, env, default_value = "delta-operator-system")]
+    pub pause_config_map_namespace: String,
+    /// Shard group to join. Tables are split between the replicas of the group.
+    #[clap(long, env, requires = "shard_identity")]
+    pub shard_group: Option<String>,
+    /// Unique name of this replica in the shard group, usually its pod name
+    #[clap(long, env)]
+    pub shard_identity: Option<String>,
+    /// Namespace of the shard leases
+    #[clap(long, env, default_value = "delta-operator-system")]
+    pub shard_lease_namespace: String,
+    /// How long a replica stays in the shard group without renewing its lease
+    #[clap(long, env, value_parser = parse_duration, default_value = "30s")]
+    pub shard_lease_duration: Duration,
+    /// OTLP gRPC endpoint that the controller and workers export spans to. Spans are
+    /// logged to stdout when it is not set.
+    #[clap(long, env = ENV_OTLP_ENDPOINT)]
+    pub otlp_endpoint: Option<String>,
+    /// How long a reconcile may run before `/livez` fails
+    #[clap(long, env, value_parser = parse_duration, default_value = "15m")]
+    pub liveness_timeout: Duration,
+    /// Address the conversion webhook listens on when a certificate is configured
+    #[clap(long, env, default_value = DEFAULT_WEBHOOK_LISTEN_ADDRESS)]
+    pub webhook_listen_address: String,
+    /// PEM certificate chain served by the conversion webhook
+    #[clap(long, env, requires = "webhook_key")]
+    pub webhook_cert: Option<PathBuf>,
+    /// PEM private key of the conversion webhook certificate
+    #[clap(long, env, requires = "webhook_cert")]
+    pub webhook_key: Option<PathBuf>,
+}
+
+impl Arguments {
+    /// Checks arguments that are only required by some executors
+    pub(crate) fn validate(&self) -> Result<(), clap::Error> {
+        if self.executor == ExecutorKind::Job
+            && (self.image.is_none() || self.worker_service_account.is_none())
+        {
+            return Err(Self::command().error(
+                ErrorKind::MissingRequiredArgument,
+                "--image and --worker-service-account are required by the job executor",
+            ));
+        }
+
+        Ok(())
+    }
 }
 
 /// Parse a single key-value pair
@@ -55,10 +181,15 @@ fn parse_duration(s: &str) -> Result<Duration, Box<dyn Error + Send + Sync + 'st
 
 #[cfg(test)]
 mod tests {
-    use crate::app::DEFAULT_LISTEN_ADDRES;
+    use crate::app::{DEFAULT_LISTEN_ADDRES, DEFAULT_WEBHOOK_LISTEN_ADDRESS};
 
     use super::Arguments;
+    use crate::controller::executor::ExecutorKind;
     use clap::Parser;
+    use delta_operator_crd::{
+        maintenance::{Action, DEFAULT_MAINTENANCE_ORDER},
+        policy,
+    };
 
     #[test]
     fn test_all_arguments() {
@@ -80,18 +211,140 @@ mod tests {
             "500m",
             "--worker-max-ram",
             "1Gi",
+            "--worker-pushgateway-url",
+            "http://pushgateway.monitoring:9091",
+            "--worker-backoff-limit",
+            "2",
+            "--worker-active-deadline",
+            "2h",
+            "--worker-ttl-after-finished",
+            "1h",
+            "--worker-successful-jobs-history-limit",
+            "5",
+            "--worker-failed-jobs-history-limit",
+            "3",
+            "--watch-namespaces",
+            "data,analytics",
+            "--namespace-selector",
+            "delta-operator.rs/enabled=true",
             "--resync-interval",
             "5m",
+            "--default-checkpoint-interval",
+            "1h",
+            "--default-checkpoint-commit-interval",
+            "5",
+            "--default-optimize-interval",
+            "2h",
+            "--default-optimize-commit-interval",
+            "25",
+            "--default-vacuum-interval",
+            "48h",
+            "--default-vacuum-commit-interval",
+            "200",
+            "--executor",
+            "job",
+            "--max-concurrent-maintenance",
+            "4",
+            "--maintenance-limit",
+            "20",
+            "--maintenance-limit-per-namespace",
+            "5",
+            "--maintenance-limit-per-action",
+            "10",
+            "--maintenance-jitter",
+            "2m",
+            "--maintenance-queue-interval",
+            "30s",
+            "--maintenance-order",
+            "vacuum,optimize",
+            "--dry-run",
+            "--pause-config-map",
+            "delta-operator-pause",
+            "--pause-config-map-namespace",
+            "default",
+            "--shard-group",
+            "tables",
+            "--shard-identity",
+            "delta-operator-0",
+            "--shard-lease-namespace",
+            "default",
+            "--shard-lease-duration",
+            "1m",
+            "--liveness-timeout",
+            "30m",
+            "--otlp-endpoint",
+            "http://collector:4317",
+            "--webhook-listen-address",
+            "0.0.0.0:9443",
+            "--webhook-cert",
+            "/etc/webhook/tls.crt",
+            "--webhook-key",
+            "/etc/webhook/tls.key",
         ]);
         assert_eq!("0.0.0.0:443", arguments.listen_address);
-        assert_eq!("test-image:vtest", arguments.image);
+        assert_eq!("test-image:vtest", arguments.image.unwrap());
         assert!(arguments.worker_labels.len() == 1);
         assert!(arguments.worker_annotations.len() == 1);
-        assert_eq!("test-service-account", arguments.worker_service_account);
+        assert_eq!(
+            "test-service-account",
+            arguments.worker_service_account.unwrap()
+        );
         assert_eq!("500m", arguments.worker_max_cpu.unwrap());
         assert_eq!("1Gi", arguments.worker_max_ram.unwrap());
+        assert_eq!(
+            "http://pushgateway.monitoring:9091",
+            arguments.worker_pushgateway_url.unwrap()
+        );
+        assert_eq!(2, arguments.worker_backoff_limit);
+        assert_eq!(7200, arguments.worker_active_deadline.as_secs());
+        assert_eq!(3600, arguments.worker_ttl_after_finished.as_secs());
+        assert_eq!(5, arguments.worker_successful_jobs_history_limit);
+        assert_eq!(3, arguments.worker_failed_jobs_history_limit);
         assert_eq!("default", arguments.worker_namespace);
+        assert_eq!(
+            vec!["data".to_owned(), "analytics".to_owned()],
+            arguments.watch_namespaces
+        );
+        assert_eq!(
+            "delta-operator.rs/enabled=true",
+            arguments.namespace_selector.unwrap()
+        );
         assert_eq!(300, arguments.resync_interval.as_secs());
+        assert_eq!(3600, arguments.default_checkpoint_interval.as_secs());
+        assert_eq!(5, arguments.default_checkpoint_commit_interval);
+        assert_eq!(7200, arguments.default_optimize_interval.as_secs());
+        assert_eq!(25, arguments.default_optimize_commit_interval);
+        assert_eq!(172_800, arguments.default_vacuum_interval.as_secs());
+        assert_eq!(200, arguments.default_vacuum_commit_interval);
+        assert_eq!(ExecutorKind::Job, arguments.executor);
+        assert_eq!(4, arguments.max_concurrent_maintenance);
+        assert_eq!(Some(20), arguments.maintenance_limit);
+        assert_eq!(Some(5), arguments.maintenance_limit_per_namespace);
+        assert_eq!(Some(10), arguments.maintenance_limit_per_action);
+        assert_eq!(120, arguments.maintenance_jitter.as_secs());
+        assert_eq!(30, arguments.maintenance_queue_interval.as_secs());
+        assert_eq!(
+            vec![Action::Vacuum, Action::Optimize],
+            arguments.maintenance_order
+        );
+        assert!(arguments.dry_run);
+        assert_eq!("delta-operator-pause", arguments.pause_config_map.unwrap());
+        assert_eq!("default", arguments.pause_config_map_namespace);
+        assert_eq!("tables", arguments.shard_group.unwrap());
+        assert_eq!("delta-operator-0", arguments.shard_identity.unwrap());
+        assert_eq!("default", arguments.shard_lease_namespace);
+        assert_eq!(60, arguments.shard_lease_duration.as_secs());
+        assert_eq!(1800, arguments.liveness_timeout.as_secs());
+        assert_eq!("http://collector:4317", arguments.otlp_endpoint.unwrap());
+        assert_eq!("0.0.0.0:9443", arguments.webhook_listen_address);
+        assert_eq!(
+            "/etc/webhook/tls.crt",
+            arguments.webhook_cert.unwrap().to_str().unwrap()
+        );
+        assert_eq!(
+            "/etc/webhook/tls.key",
+            arguments.webhook_key.unwrap().to_str().unwrap()
+        );
     }
 
     #[test]
@@ -104,13 +357,111 @@ mod tests {
             "test-service-account",
         ]);
         assert_eq!(DEFAULT_LISTEN_ADDRES, arguments.listen_address);
-        assert_eq!("test-image:vtest", arguments.image);
+        assert_eq!("test-image:vtest", arguments.image.unwrap());
         assert!(arguments.worker_labels.is_empty());
         assert!(arguments.worker_annotations.is_empty());
-        assert_eq!("test-service-account", arguments.worker_service_account);
+        assert_eq!(
+            "test-service-account",
+            arguments.worker_service_account.unwrap()
+        );
         assert!(arguments.worker_max_cpu.is_none());
         assert!(arguments.worker_max_ram.is_none());
+        assert!(arguments.worker_pushgateway_url.is_none());
+        assert_eq!(6, arguments.worker_backoff_limit);
+        assert_eq!(21600, arguments.worker_active_deadline.as_secs());
+        assert_eq!(86400, arguments.worker_ttl_after_finished.as_secs());
+        assert_eq!(3, arguments.worker_successful_jobs_history_limit);
+        assert_eq!(1, arguments.worker_failed_jobs_history_limit);
         assert_eq!("delta-operator-system", arguments.worker_namespace);
+        assert!(arguments.watch_namespaces.is_empty());
+        assert!(arguments.namespace_selector.is_none());
         assert_eq!(3600, arguments.resync_interval.as_secs());
+        assert_eq!(
+            policy::DEFAULT_CHECKPOINT_INTERVAL,
+            arguments.default_checkpoint_interval
+        );
+        assert_eq!(
+            policy::DEFAULT_OPTIMIZE_INTERVAL,
+            arguments.default_optimize_interval
+        );
+        assert_eq!(
+            policy::DEFAULT_VACUUM_INTERVAL,
+            arguments.default_vacuum_interval
+        );
+        assert_eq!(
+            policy::DEFAULT_CHECKPOINT_COMMIT_INTERVAL,
+            arguments.default_checkpoint_commit_interval
+        );
+        assert_eq!(ExecutorKind::Job, arguments.executor);
+        assert_eq!(2, arguments.max_concurrent_maintenance);
+        assert!(arguments.maintenance_limit.is_none());
+        assert!(arguments.maintenance_limit_per_namespace.is_none());
+        assert!(arguments.maintenance_limit_per_action.is_none());
+        assert_eq!(0, arguments.maintenance_jitter.as_secs());
+        assert_eq!(10, arguments.maintenance_queue_interval.as_secs());
+        assert_eq!(
+            DEFAULT_MAINTENANCE_ORDER.to_vec(),
+            arguments.maintenance_order
+        );
+        assert!(!arguments.dry_run);
+        assert!(arguments.pause_config_map.is_none());
+        assert_eq!(
+            "delta-operator-system",
+            arguments.pause_config_map_namespace
+        );
+        assert!(arguments.shard_group.is_none());
+        assert_eq!("delta-operator-system", arguments.shard_lease_namespace);
+        assert_eq!(30, arguments.shard_lease_duration.as_secs());
+        assert_eq!(900, arguments.liveness_timeout.as_secs());
+        assert!(arguments.otlp_endpoint.is_none());
+        assert_eq!(
+            DEFAULT_WEBHOOK_LISTEN_ADDRESS,
+            arguments.webhook_listen_address
+        );
+        assert!(arguments.webhook_cert.is_none());
+        assert!(arguments.webhook_key.is_none());
+    }
+
+    #[test]
+    fn test_inprocess_arguments() {
+        let arguments = Arguments::parse_from(vec!["app", "--executor", "inprocess"]);
+        assert!(arguments.validate().is_ok());
+        assert_eq!(ExecutorKind::InProcess, arguments.executor);
+        assert!(arguments.image.is_none());
+        assert!(arguments.worker_service_account.is_none());
+    }
+
+    #[test]
+    fn test_webhook_cert_requires_key() {
+        let result = Arguments::try_parse_from(vec![
+            "app",
+            "--executor",
+            "inprocess",
+            "--webhook-cert",
+            "/etc/webhook/tls.crt",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_shard_group_requires_identity() {
+        let result = Arguments::try_parse_from(vec![
+            "app",
+            "--executor",
+            "inprocess",
+            "--shard-group",
+            "tables",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_job_executor_requires_image() {
+        let arguments = Arguments::parse_from(vec![
+            "app",
+            "--worker-service-account",
+            "test-service-account",
+        ]);
+        assert!(arguments.validate().is_err());
     }
 }
@@ -0,0 +1,252 @@
+//! State of the controller served by the health and diagnostics endpoints
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::Arc,
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use delta_operator_crd::DeltaTable;
+use kube::{
+    runtime::events::{Recorder, Reporter},
+    Client, Resource,
+};
+use serde::{Serialize, Serializer};
+use tokio::sync::RwLock;
+
+/// Key of the watch over every namespace
+pub(crate) const ALL_NAMESPACES: &str = "*";
+/// Most reconcile errors kept for `/diagnostics`
+pub(crate) const MAX_RECENT_ERRORS: usize = 20;
+
+/// A failed reconcile
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct ReconcileError {
+    /// `namespace/name` of the table
+    pub table: String,
+    pub time: DateTime<Utc>,
+    pub error: String,
+}
+
+#[derive(Clone, Serialize)]
+pub(crate) struct Diagnostics {
+    pub last_event: DateTime<Utc>,
+    #[serde(serialize_with = "serialize_reporter")]
+    pub reporter: Reporter,
+    /// Whether listing tables succeeded, which requires the CRD
+    pub crd_installed: bool,
+    /// Whether the initial list of each watch completed, keyed by namespace or
+    /// [`ALL_NAMESPACES`]
+    pub watches: BTreeMap<String, bool>,
+    /// Namespaces matching the namespace selector, unset without a selector or before
+    /// the initial list of namespaces
+    pub selected_namespaces: Option<usize>,
+    /// Start of the reconciles in progress keyed by `namespace/name`
+    pub reconciling: BTreeMap<String, DateTime<Utc>>,
+    /// Maintenance waiting for capacity as of the last scheduler pass
+    pub queue_depth: usize,
+    /// When each action is next due by time as of the last reconcile, keyed by
+    /// `namespace/name`
+    pub next_maintenance: BTreeMap<String, BTreeMap<String, DateTime<Utc>>>,
+    pub recent_errors: VecDeque<ReconcileError>,
+}
+
+fn serialize_reporter<S: Serializer>(
+    reporter: &Reporter,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    reporter.controller.serialize(serializer)
+}
+
+impl Default for Diagnostics {
+    fn default() -> Self {
+        Self {
+            last_event: Utc::now(),
+            reporter: "forwardedservice-controller".into(),
+            crd_installed: false,
+            watches: BTreeMap::new(),
+            selected_namespaces: None,
+            reconciling: BTreeMap::new(),
+            queue_depth: 0,
+            next_maintenance: BTreeMap::new(),
+            recent_errors: VecDeque::new(),
+        }
+    }
+}
+
+impl Diagnostics {
+    pub(crate) fn recorder(&self, client: Client, doc: &DeltaTable) -> Recorder {
+        Recorder::new(client, self.reporter.clone(), doc.object_ref(&()))
+    }
+
+    /// Why the controller is not ready, or `None` when it is. It is ready once the CRD
+    /// was found and every watch finished its initial list of tables.
+    pub(crate) fn not_ready(&self) -> Option<String> {
+        // No table can be listed until a namespace is labelled, which is a valid state
+        if self.selected_namespaces == Some(0) {
+            return None;
+        }
+        if !self.crd_installed {
+            return Some("tables have not been listed yet".into());
+        }
+        if self.watches.is_empty() {
+            return Some("no namespaces are watched".into());
+        }
+        let pending: Vec<&str> = self
+            .watches
+            .iter()
+            .filter(|(_, ready)| !**ready)
+            .map(|(namespace, _)| namespace.as_str())
+            .collect();
+        match pending.is_empty() {
+            true => None,
+            false => Some(format!(
+                "waiting for the initial list of tables in {}",
+                pending.join(", ")
+            )),
+        }
+    }
+
+    /// Tables whose reconcile has been running for longer than `timeout` at `now`
+    pub(crate) fn stuck(&self, now: DateTime<Utc>, timeout: Duration) -> Vec<&str> {
+        self.reconciling
+            .iter()
+            .filter(|(_, started)| {
+                now.signed_duration_since(**started)
+                    .to_std()
+                    .is_ok_and(|elapsed| elapsed > timeout)
+            })
+            .map(|(table, _)| table.as_str())
+            .collect()
+    }
+
+    /// Keeps `error` among the [`MAX_RECENT_ERRORS`] most recent errors
+    pub(crate) fn record_error(&mut self, table: String, error: String, time: DateTime<Utc>) {
+        if self.recent_errors.len() == MAX_RECENT_ERRORS {
+            self.recent_errors.pop_front();
+        }
+        self.recent_errors
+            .push_back(ReconcileError { table, time, error });
+    }
+}
+
+/// Removes a watch from the diagnostics when its controller stops, including when the
+/// namespace is no longer selected and its task is aborted
+pub(crate) struct WatchGuard {
+    diagnostics: Arc<RwLock<Diagnostics>>,
+    key: String,
+}
+
+impl WatchGuard {
+    pub(crate) async fn register(diagnostics: Arc<RwLock<Diagnostics>>, key: &str) -> Self {
+        diagnostics
+            .write()
+            .await
+            .watches
+            .insert(key.to_owned(), false);
+        Self {
+            diagnostics,
+            key: key.to_owned(),
+        }
+    }
+
+    pub(crate) async fn ready(&self) {
+        self.diagnostics
+            .write()
+            .await
+            .watches
+            .insert(self.key.clone(), true);
+    }
+}
+
+impl Drop for WatchGuard {
+    fn drop(&mut self) {
+        let diagnostics = self.diagnostics.clone();
+        let key = std::mem::take(&mut self.key);
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            handle.spawn(async move {
+                diagnostics.write().await.watches.remove(&key);
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::{TimeZone, Utc};
+
+    use super::{Diagnostics, ALL_NAMESPACES, MAX_RECENT_ERRORS};
+
+    #[test]
+    fn test_not_ready() {
+        let mut diagnostics = Diagnostics::default();
+        assert!(diagnostics.not_ready().is_some());
+        diagnostics.crd_installed = true;
+        assert_eq!(
+            Some("no namespaces are watched".to_owned()),
+            diagnostics.not_ready()
+        );
+        diagnostics.watches.insert("data".into(), true);
+        diagnostics.watches.insert("analytics".into(), false);
+        assert_eq!(
+            Some("waiting for the initial list of tables in analytics".to_owned()),
+            diagnostics.not_ready()
+        );
+        diagnostics.watches.insert("analytics".into(), true);
+        assert_eq!(None, diagnostics.not_ready());
+
+        diagnostics.watches.clear();
+        diagnostics.watches.insert(ALL_NAMESPACES.into(), true);
+        assert_eq!(None, diagnostics.not_ready());
+    }
+
+    #[test]
+    fn test_not_ready_selector() {
+        let mut diagnostics = Diagnostics::default();
+        assert!(diagnostics.not_ready().is_some());
+        diagnostics.selected_namespaces = Some(0);
+        assert_eq!(None, diagnostics.not_ready());
+        diagnostics.selected_namespaces = Some(1);
+        assert!(diagnostics.not_ready().is_some());
+        diagnostics.crd_installed = true;
+        diagnostics.watches.insert("data".into(), true);
+        assert_eq!(None, diagnostics.not_ready());
+    }
+
+    #[test]
+    fn test_stuck() {
+        let mut diagnostics = Diagnostics::default();
+        let at = |seconds| Utc.timestamp_opt(seconds, 0).unwrap();
+        diagnostics.reconciling.insert("data/old".into(), at(0));
+        diagnostics.reconciling.insert("data/new".into(), at(590));
+        diagnostics
+            .reconciling
+            .insert("data/skewed".into(), at(700));
+        assert_eq!(
+            vec!["data/old"],
+            diagnostics.stuck(at(600), Duration::from_secs(300))
+        );
+        assert!(diagnostics
+            .stuck(at(600), Duration::from_secs(900))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_recent_errors() {
+        let mut diagnostics = Diagnostics::default();
+        for i in 0..MAX_RECENT_ERRORS + 5 {
+            diagnostics.record_error(format!("data/{}", i), "failed".into(), Utc::now());
+        }
+        assert_eq!(MAX_RECENT_ERRORS, diagnostics.recent_errors.len());
+        assert_eq!("data/5", diagnostics.recent_errors[0].table);
+
+        let json = serde_json::to_value(&diagnostics).unwrap();
+        assert_eq!("forwardedservice-controller", json["reporter"]);
+        assert_eq!(
+            MAX_RECENT_ERRORS,
+            json["recent_errors"].as_array().unwrap().len()
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use clap::ValueEnum;
+use delta_operator_crd::{
+    failure::{clear_failure, publish_failure, MaintenanceFailure},
+    maintenance::{
+        join_actions, perform_pipeline, Action, MaintenanceExecutor, RunningMaintenance,
+    },
+    DeltaTable, Error,
+};
+use kube::{
+    runtime::events::{Recorder, Reporter},
+    Api, Client, Resource, ResourceExt,
+};
+use tokio::{
+    sync::{watch, Semaphore},
+    task::JoinSet,
+};
+use tracing::Instrument;
+
+/// Where maintenance actions are executed
+#[derive(Clone, Debug, ValueEnum, PartialEq)]
+pub enum ExecutorKind {
+    /// Create a worker job per action
+    Job,
+    /// Run actions on a task pool inside the controller
+    #[value(name = "inprocess")]
+    InProcess,
+}
+
+/// [`MaintenanceExecutor`] that runs actions on a bounded pool of tokio tasks
+///
+/// At most one action runs per table at a time; actions requested for a table that is
+/// already being maintained, or while every permit is taken, are skipped and picked up by
+/// a later reconcile. The [`Scheduler`](super::scheduler::Scheduler) is limited to the
+/// same capacity so that work waits in its queue instead.
+pub(crate) struct InProcessExecutor {
+    client: Client,
+    reporter: Reporter,
+    permits: Arc<Semaphore>,
+    running: Arc<Mutex<HashMap<String, RunningMaintenance>>>,
+    tasks: tokio::sync::Mutex<JoinSet<()>>,
+    shutdown: watch::Sender<bool>,
+}
+
+impl InProcessExecutor {
+    pub(crate) fn new(client: Client, reporter: Reporter, max_concurrency: usize) -> Self {
+        let (shutdown, _) = watch::channel(false);
+        Self {
+            client,
+            reporter,
+            permits: Arc::new(Semaphore::new(max_concurrency.max(1))),
+            running: Arc::new(Mutex::new(HashMap::new())),
+            tasks: tokio::sync::Mutex::new(JoinSet::new()),
+            shutdown,
+        }
+    }
+}
+
+/// Removes the table from the running set when the task ends, however it ends
+struct RunningGuard {
+    key: String,
+    running: Arc<Mutex<HashMap<String, RunningMaintenance>>>,
+}
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        self.running.lock().unwrap().remove(&self.key);
+    }
+}
+
+#[async_trait]
+impl MaintenanceExecutor for InProcessExecutor {
+    async fn execute(
+        &self,
+        doc: &DeltaTable,
+        actions: Vec<Action>,
+        _: &HashMap<String, String>,
+    ) -> Result<(), Error> {
+        if *self.shutdown.borrow() {
+            return Ok(());
+        }
+
+        let namespace = doc.namespace().unwrap();
+        let name = doc.name_any();
+        let key = format!("{}/{}", namespace, name);
+        // Taken before spawning so that work waiting for capacity does not pile up as
+        // tasks; the scheduler holds it back instead
+        let Ok(permit) = self.permits.clone().try_acquire_owned() else {
+            tracing::info!(
+                "skipping {} on {}: maintenance capacity reached",
+                join_actions(&actions, ","),
+                key
+            );
+            return Ok(());
+        };
+        {
+            let mut running = self.running.lock().unwrap();
+            if running.contains_key(&key) {
+                tracing::info!(
+                    "skipping {} on {}: maintenance in progress",
+                    join_actions(&actions, ","),
+                    key
+                );
+                return Ok(());
+            }
+            running.insert(
+                key.clone(),
+                RunningMaintenance {
+                    namespace,
+                    table: name,
+                    actions: actions.clone(),
+                },
+            );
+        }
+
+        let guard = RunningGuard {
+            key,
+            running: self.running.clone(),
+        };
+        let client = self.client.clone();
+        let recorder = Recorder::new(client.clone(), self.reporter.clone(), doc.object_ref(&()));
+        let mut shutdown = self.shutdown.subscribe();
+        let doc = doc.clone();
+        let span = tracing::Span::current();
+        self.tasks.lock().await.spawn(
+            async move {
+                let _guard = guard;
+                let _permit = permit;
+                let work = run_pipeline(client, &recorder, &doc, &actions);
+                tokio::select! {
+                    result = work => if let Err(e) = result {
+                        tracing::error!(
+                            "failed to perform maintenance {} on delta table `{}`: {}",
+                            join_actions(&actions, ","),
+                            doc.name_any(),
+                            e
+                        );
+                    },
+                    _ = shutdown.changed() => {
+                        tracing::warn!(
+                            "cancelled maintenance {} on delta table `{}`",
+                            join_actions(&actions, ","),
+                            doc.name_any()
+                        );
+                    }
+                }
+            }
+            .instrument(span),
+        );
+
+        // Reap finished tasks so the set does not grow unbounded
+        let mut tasks = self.tasks.lock().await;
+        while tasks.try_join_next().is_some() {}
+        Ok(())
+    }
+
+    async fn running(&self) -> Result<Vec<RunningMaintenance>, Error> {
+        Ok(self.running.lock().unwrap().values().cloned().collect())
+    }
+
+    async fn shutdown(&self) {
+        self.shutdown.send_replace(true);
+        let mut tasks = self.tasks.lock().await;
+        while tasks.join_next().await.is_some() {}
+    }
+}
+
+async fn run_pipeline(
+    client: Client,
+    recorder: &Recorder,
+    doc: &DeltaTable,
+    actions: &[Action],
+) -> Result<(), Error> {
+    let api: Api<DeltaTable> = Api::namespaced(client.clone(), &doc.namespace().unwrap());
+    let result = async {
+        let delta_lake_table = doc.delta_lake_table(client).await?;
+        perform_pipeline(&api, recorder, doc, delta_lake_table, actions).await
+    }
+    .await;
+    match result {
+        Ok(_) => {
+            if doc
+                .status
+                .as_ref()
+                .is_some_and(|s| s.last_failure.is_some())
+            {
+                clear_failure(&api, &doc.name_any()).await;
+            }
+            Ok(())
+        }
+        Err(e) => {
+            let failure = MaintenanceFailure::new(&e, None, Utc::now());
+            publish_failure(&api, Some(recorder), &doc.name_any(), &failure).await;
+            Err(e)
+        }
+    }
+}
@@ -1,12 +1,19 @@
-use std::net::SocketAddr;
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 
-use axum::{routing::get, Router};
+use axum::{http::StatusCode, routing::get, Json, Router};
 use axum_prometheus::PrometheusMetricLayerBuilder;
+use chrono::Utc;
+use tokio::sync::RwLock;
 
+use super::diagnostics::Diagnostics;
 use crate::error::Error;
 
-pub(crate) async fn start_host(address: &str) -> Result<(), Error> {
-    let app = create_router();
+pub(crate) async fn start_host(
+    address: &str,
+    diagnostics: Arc<RwLock<Diagnostics>>,
+    liveness_timeout: Duration,
+) -> Result<(), Error> {
+    let app = create_router(diagnostics, liveness_timeout);
     let addr: SocketAddr = match address.parse() {
         Ok(addr) => addr,
         Err(e) => return Err(Error::Server(e.to_string())),
@@ -17,19 +24,50 @@ pub(crate) async fn start_host(address: &str) -> Result<(), Error> {
         .map_err(|e| Error::Server(e.to_string()))
 }
 
-fn create_router() -> Router {
+fn create_router(diagnostics: Arc<RwLock<Diagnostics>>, liveness_timeout: Duration) -> Router {
     let (prometheus_layer, metric_handle) = PrometheusMetricLayerBuilder::new()
-        .with_ignore_patterns(&["/metrics", "/sensitive", "/health"])
+        .with_ignore_patterns(&[
+            "/metrics",
+            "/sensitive",
+            "/health",
+            "/readyz",
+            "/livez",
+            "/diagnostics",
+        ])
         .with_default_metrics()
         .build_pair();
 
-    tracing_subscriber::fmt()
-        .with_target(false)
-        .compact()
-        .init();
-
+    let ready = diagnostics.clone();
+    let live = diagnostics.clone();
     Router::new()
         .route("/metrics", get(|| async move { metric_handle.render() }))
         .route("/health", get(|| async move {}))
+        .route(
+            "/readyz",
+            get(|| async move {
+                match ready.read().await.not_ready() {
+                    Some(reason) => (StatusCode::SERVICE_UNAVAILABLE, reason),
+                    None => (StatusCode::OK, "ok".to_owned()),
+                }
+            }),
+        )
+        .route(
+            "/livez",
+            get(move || async move {
+                let diagnostics = live.read().await;
+                let stuck = diagnostics.stuck(Utc::now(), liveness_timeout);
+                match stuck.is_empty() {
+                    true => (StatusCode::OK, "ok".to_owned()),
+                    false => (
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        format!("reconcile stuck for {}", stuck.join(", ")),
+                    ),
+                }
+            }),
+        )
+        .route(
+            "/diagnostics",
+            get(|| async move { Json(diagnostics.read().await.clone()) }),
+        )
         .layer(prometheus_layer)
 }
@@ -1,51 +1,70 @@
-use chrono::{DateTime, Utc};
-use delta_operator_crd::{maintenance::JobSettings, DeltaTable};
+use chrono::{TimeZone, Utc};
+use clap::ValueEnum;
+use delta_operator_crd::{
+    jobs,
+    maintenance::{Action as MaintenanceAction, JobSettings, MaintenanceExecutor},
+    policy::SystemClock,
+    DeltaTable,
+};
 use futures::StreamExt;
+use k8s_openapi::api::batch::v1::Job;
 use kube::{
     api::ListParams,
     runtime::{
-        controller::Action,
-        events::{Recorder, Reporter},
-        Controller,
+        controller::Action, reflector::ObjectRef, reflector::Store, watcher::Config, Controller,
     },
     Api, Client, Resource, ResourceExt,
 };
-use serde::Serialize;
 use std::{sync::Arc, time::Duration};
 use tokio::sync::RwLock;
 
 use crate::error::Error;
 
-use self::state::State;
+use self::{
+    diagnostics::{Diagnostics, WatchGuard, ALL_NAMESPACES},
+    executor::ExecutorKind,
+    namespaces::WatchScope,
+    pause::PauseToggle,
+    shard::Shard,
+    state::State,
+};
+pub mod diagnostics;
+pub mod executor;
 pub mod host;
+pub mod namespaces;
+pub mod pause;
+pub mod scheduler;
+pub mod shard;
 pub mod state;
+pub mod webhook;
 
-#[derive(Clone, Serialize)]
-pub(crate) struct Diagnostics {
-    #[serde(deserialize_with = "from_ts")]
-    pub last_event: DateTime<Utc>,
-    #[serde(skip)]
-    pub reporter: Reporter,
-}
-
-impl Default for Diagnostics {
-    fn default() -> Self {
-        Self {
-            last_event: Utc::now(),
-            reporter: "forwardedservice-controller".into(),
-        }
-    }
+/// Address and certificate of the conversion webhook
+pub struct WebhookSettings {
+    pub listen_address: String,
+    pub cert: std::path::PathBuf,
+    pub key: std::path::PathBuf,
 }
 
-impl Diagnostics {
-    fn recorder(&self, client: Client, doc: &DeltaTable) -> Recorder {
-        Recorder::new(client, self.reporter.clone(), doc.object_ref(&()))
-    }
-}
-
-pub async fn start_controller(state: State, listen_address: String) -> Result<(), Error> {
+pub async fn start_controller(
+    state: State,
+    listen_address: String,
+    webhook: Option<WebhookSettings>,
+) -> Result<(), Error> {
     let b = Box::new(listen_address);
-    let jh = tokio::spawn(host::start_host(Box::leak(b)));
+    let jh = tokio::spawn(host::start_host(
+        Box::leak(b),
+        state.diagnostics(),
+        state.liveness_timeout(),
+    ));
+    if let Some(webhook) = webhook {
+        tokio::spawn(async move {
+            if let Err(e) =
+                webhook::start_webhook(&webhook.listen_address, &webhook.cert, &webhook.key).await
+            {
+                tracing::error!("conversion webhook stopped: {}", e);
+            }
+        });
+    }
     start(state).await;
     jh.await.unwrap()
 }
@@ -59,6 +78,15 @@ pub struct Context {
 
     /// Job settings for workers
     pub(crate) settings: Arc<JobSettings>,
+
+    /// Runs the maintenance actions reconcile decides are due
+    pub(crate) executor: Arc<dyn MaintenanceExecutor>,
+
+    /// Pauses tables selected by the pause ConfigMap
+    pub(crate) pause: PauseToggle,
+
+    /// Tables of other replicas are skipped when sharding is enabled
+    pub(crate) shard: Option<Shard>,
     // Prometheus metrics
     // pub metrics: Metrics,
 }
@@ -68,19 +96,174 @@ async fn start(controller_state: State) {
         .await
         .map_err(|e| Error::KubeClient { source: e })
         .expect("failed to create kubernetes client");
-    let api = Api::<DeltaTable>::all(client.clone());
+    let (ctx, cfg) = controller_state.to_context_and_config(client.clone()).await;
+    let shard = ctx
+        .shard
+        .clone()
+        .map(|shard| tokio::spawn(shard.run(client.clone())));
+    let pause = controller_state
+        .pause_config_map()
+        .map(|(namespace, name)| {
+            tokio::spawn(
+                ctx.pause
+                    .clone()
+                    .run(client.clone(), namespace.clone(), name.clone()),
+            )
+        });
+    let orphans = (*controller_state.executor() == ExecutorKind::Job)
+        .then(|| tokio::spawn(collect_orphaned_jobs(client.clone(), ctx.settings.clone())));
+    let controller_state = Arc::new(controller_state);
+    match controller_state.watch_scope() {
+        WatchScope::All => {
+            let api = Api::<DeltaTable>::all(client.clone());
+            if let Err(e) = api.list(&ListParams::default().limit(1)).await {
+                tracing::error!("Installation: cargo run --bin crdgen | kubectl apply -f -");
+                panic!("crds are not installed: {}", Error::KubeCrd { source: e });
+            }
+            ctx.diagnostics.write().await.crd_installed = true;
+            run(api, ALL_NAMESPACES, &controller_state, ctx.clone(), cfg).await;
+        }
+        WatchScope::Namespaces(namespaces) => {
+            futures::future::join_all(namespaces.iter().map(|namespace| {
+                run_namespace(
+                    namespace,
+                    &controller_state,
+                    client.clone(),
+                    ctx.clone(),
+                    cfg.clone(),
+                )
+            }))
+            .await;
+        }
+        WatchScope::Selector {
+            selector,
+            namespaces,
+        } => {
+            namespaces::watch_selected(
+                client.clone(),
+                selector,
+                namespaces,
+                ctx.diagnostics.clone(),
+                |namespace| {
+                    let controller_state = controller_state.clone();
+                    let client = client.clone();
+                    let ctx = ctx.clone();
+                    let cfg = cfg.clone();
+                    async move {
+                        run_namespace(&namespace, &controller_state, client, ctx, cfg).await
+                    }
+                },
+                |namespace| {
+                    let executor = ctx.executor.clone();
+                    async move { executor.cancel_namespace(&namespace).await }
+                },
+            )
+            .await;
+        }
+    }
+    if let Some(shard) = shard {
+        shard.abort();
+    }
+    if let Some(pause) = pause {
+        pause.abort();
+    }
+    if let Some(orphans) = orphans {
+        orphans.abort();
+    }
+    ctx.executor.shutdown().await;
+}
+
+/// Deletes the worker jobs of deleted tables every resync interval
+async fn collect_orphaned_jobs(client: Client, settings: Arc<JobSettings>) {
+    let api: Api<Job> = Api::namespaced(client.clone(), &settings.namespace);
+    let mut interval = tokio::time::interval(
+        settings
+            .resync_interval
+            .unwrap_or(Duration::from_secs(3600)),
+    );
+    loop {
+        interval.tick().await;
+        if let Err(e) = jobs::collect_orphans(&api, client.clone()).await {
+            tracing::warn!("failed to delete jobs of deleted tables: {}", e);
+        }
+    }
+}
+
+/// Runs the controller for the tables of one namespace, unless the tables cannot be
+/// listed there
+async fn run_namespace(
+    namespace: &str,
+    controller_state: &State,
+    client: Client,
+    ctx: Arc<Context>,
+    cfg: Config,
+) {
+    let api = Api::<DeltaTable>::namespaced(client.clone(), namespace);
     if let Err(e) = api.list(&ListParams::default().limit(1)).await {
-        tracing::error!("Installation: cargo run --bin crdgen | kubectl apply -f -");
-        panic!("crds are not installed: {}", Error::KubeCrd { source: e });
+        tracing::error!(
+            "cannot list tables in namespace {}: {}",
+            namespace,
+            Error::KubeCrd { source: e }
+        );
+        return;
     }
+    ctx.diagnostics.write().await.crd_installed = true;
+    run(api, namespace, controller_state, ctx, cfg).await;
+}
 
-    let (ctx, cfg) = controller_state.to_context_and_config(client);
-    Controller::new(api, cfg.any_semantic())
+/// Runs the controller for the tables of `api`, reporting when the initial list of the
+/// watch of `namespace` completed
+async fn run(
+    api: Api<DeltaTable>,
+    namespace: &str,
+    controller_state: &State,
+    ctx: Arc<Context>,
+    cfg: Config,
+) {
+    let mut controller = Controller::new(api, cfg.any_semantic());
+    if controller_state.pause_config_map().is_some() {
+        controller = controller.reconcile_all_on(ctx.pause.changes());
+    }
+    if let Some(shard) = ctx.shard.as_ref() {
+        controller = controller.reconcile_all_on(shard.changes());
+    }
+    let watch = WatchGuard::register(ctx.diagnostics.clone(), namespace).await;
+    let store = controller.store();
+    let synced = async {
+        if store.wait_until_ready().await.is_ok() {
+            watch.ready().await;
+        }
+    };
+    let forgotten = forget_deleted(ctx.shard.clone(), store.clone(), namespace);
+    let controller = controller
         .shutdown_on_signal()
         .run(reconcile, error_policy, ctx)
         .filter_map(|x| async move { std::result::Result::ok(x) })
-        .for_each(|_| futures::future::ready(()))
-        .await;
+        .for_each(|_| futures::future::ready(()));
+    futures::future::join(
+        synced,
+        futures::future::select(Box::pin(forgotten), Box::pin(controller)),
+    )
+    .await;
+}
+
+/// Drops the tables of `namespace` that are no longer in `store` from the tables the
+/// shard owns, until the controller stops
+async fn forget_deleted(shard: Option<Shard>, store: Store<DeltaTable>, namespace: &str) {
+    let Some(shard) = shard else {
+        return futures::future::pending().await;
+    };
+    if store.wait_until_ready().await.is_err() {
+        return futures::future::pending().await;
+    }
+    let mut interval = tokio::time::interval(shard.renew_interval());
+    loop {
+        interval.tick().await;
+        shard.forget_deleted(|ns, name| {
+            (namespace == ALL_NAMESPACES || ns == namespace)
+                && store.get(&ObjectRef::new(name).within(ns)).is_none()
+        });
+    }
 }
 
 fn error_policy(_: Arc<DeltaTable>, error: &Error, _: Arc<Context>) -> Action {
@@ -90,17 +273,62 @@ fn error_policy(_: Arc<DeltaTable>, error: &Error, _: Arc<Context>) -> Action {
 }
 
 async fn reconcile(doc: Arc<DeltaTable>, ctx: Arc<Context>) -> Result<Action, Error> {
+    let ns = doc.namespace().unwrap(); // doc is namespace scoped
+    let key = format!("{}/{}", ns, doc.name_any());
     if doc.meta().deletion_timestamp.is_some() {
+        ctx.diagnostics.write().await.next_maintenance.remove(&key);
+        ctx.executor.cancel(&ns, &doc.name_any()).await;
+        doc.reset_metrics();
+        if let Some(shard) = ctx.shard.as_ref() {
+            shard.forget(&ns, &doc.name_any());
+        }
         return Ok(Action::await_change());
     }
 
-    ctx.diagnostics.write().await.last_event = Utc::now();
-    let ns = doc.namespace().unwrap(); // doc is namespace scoped
+    if let Some(shard) = ctx.shard.as_ref() {
+        if !shard.owns(&ns, &doc.name_any()) {
+            ctx.diagnostics.write().await.next_maintenance.remove(&key);
+            ctx.executor.cancel(&ns, &doc.name_any()).await;
+            doc.reset_metrics();
+            return Ok(Action::await_change());
+        }
+    }
+    {
+        let mut diagnostics = ctx.diagnostics.write().await;
+        diagnostics.last_event = Utc::now();
+        diagnostics.reconciling.insert(key.clone(), Utc::now());
+    }
     tracing::info!("Reconciling DeltaTable \"{}\" in {}", doc.name_any(), ns);
     let client = ctx.client.clone();
 
     let recorder = ctx.diagnostics.read().await.recorder(client.clone(), &doc);
-    doc.reconcile(client, recorder, &ctx.settings)
+    let paused = ctx.pause.is_paused(doc.labels());
+    let result = doc
+        .reconcile(
+            client,
+            recorder,
+            &ctx.settings,
+            ctx.executor.as_ref(),
+            paused,
+        )
         .await
-        .map_err(|e| Error::Reconcilation { source: e })
+        .map_err(|e| Error::Reconcilation { source: e });
+
+    let clock = SystemClock;
+    let next_maintenance = MaintenanceAction::value_variants()
+        .iter()
+        .filter_map(|action| {
+            let due = doc.next_maintenance(action, &ctx.settings.maintenance_defaults, &clock)?;
+            Some((action.to_string(), Utc.timestamp_opt(due, 0).single()?))
+        })
+        .collect();
+    let mut diagnostics = ctx.diagnostics.write().await;
+    diagnostics.reconciling.remove(&key);
+    diagnostics
+        .next_maintenance
+        .insert(key.clone(), next_maintenance);
+    if let Err(e) = result.as_ref() {
+        diagnostics.record_error(key, e.to_string(), Utc::now());
+    }
+    result
 }
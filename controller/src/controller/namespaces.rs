@@ -0,0 +1,193 @@
+//! Decides which namespaces the controller watches
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    future::Future,
+    sync::Arc,
+};
+
+use futures::StreamExt;
+use k8s_openapi::api::core::v1::Namespace;
+use kube::{
+    runtime::{
+        watcher::{self, Event},
+        WatchStreamExt,
+    },
+    Api, Client, ResourceExt,
+};
+use tokio::{sync::RwLock, task::JoinHandle};
+
+use super::diagnostics::Diagnostics;
+
+/// Namespaces whose tables the controller reconciles
+#[derive(Clone, Debug, PartialEq)]
+pub enum WatchScope {
+    /// Every namespace, with one cluster-wide watch
+    All,
+    /// A fixed set of namespaces, each with its own watch
+    Namespaces(Vec<String>),
+    /// Namespaces whose labels match `selector`, limited to `namespaces` unless empty
+    Selector {
+        selector: String,
+        namespaces: Vec<String>,
+    },
+}
+
+impl WatchScope {
+    pub fn new(namespaces: Vec<String>, selector: Option<String>) -> Self {
+        match selector {
+            Some(selector) => WatchScope::Selector {
+                selector,
+                namespaces,
+            },
+            None if namespaces.is_empty() => WatchScope::All,
+            None => WatchScope::Namespaces(namespaces),
+        }
+    }
+}
+
+/// Namespaces to start and stop watching so that `running` becomes the namespaces of
+/// `selected` that are `allowed`
+fn changes(
+    running: &BTreeSet<String>,
+    selected: &BTreeSet<String>,
+    allowed: &[String],
+) -> (Vec<String>, Vec<String>) {
+    let desired: BTreeSet<&String> = selected
+        .iter()
+        .filter(|ns| allowed.is_empty() || allowed.contains(ns))
+        .collect();
+    let start = desired
+        .iter()
+        .filter(|ns| !running.contains(**ns))
+        .map(|ns| ns.to_string())
+        .collect();
+    let stop = running
+        .iter()
+        .filter(|ns| !desired.contains(ns))
+        .cloned()
+        .collect();
+    (start, stop)
+}
+
+/// Runs `run` for every namespace matching `selector` until the namespace stops matching,
+/// then `stopped`, or until the controller shuts down, reporting the number of such
+/// namespaces to `diagnostics`
+pub(crate) async fn watch_selected<F, Fut, S, StopFut>(
+    client: Client,
+    selector: &str,
+    allowed: &[String],
+    diagnostics: Arc<RwLock<Diagnostics>>,
+    run: F,
+    stopped: S,
+) where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+    S: Fn(String) -> StopFut,
+    StopFut: Future<Output = ()>,
+{
+    let api = Api::<Namespace>::all(client);
+    let config = watcher::Config {
+        label_selector: Some(selector.to_owned()),
+        ..Default::default()
+    };
+    let mut events = watcher::watcher(api, config).default_backoff().boxed();
+    let mut selected = BTreeSet::new();
+    let mut running: BTreeMap<String, JoinHandle<()>> = BTreeMap::new();
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+    loop {
+        let event = tokio::select! {
+            _ = &mut shutdown => break,
+            event = events.next() => match event {
+                Some(event) => event,
+                None => break,
+            },
+        };
+        match event {
+            Ok(Event::Applied(namespace)) => {
+                selected.insert(namespace.name_any());
+            }
+            Ok(Event::Deleted(namespace)) => {
+                selected.remove(&namespace.name_any());
+            }
+            Ok(Event::Restarted(namespaces)) => {
+                selected = namespaces.iter().map(|ns| ns.name_any()).collect();
+            }
+            Err(e) => {
+                tracing::warn!("namespace watch failed: {}", e);
+                continue;
+            }
+        }
+        let (start, stop) = changes(&running.keys().cloned().collect(), &selected, allowed);
+        for namespace in stop {
+            tracing::info!("stopped watching namespace {}", namespace);
+            if let Some(handle) = running.remove(&namespace) {
+                handle.abort();
+            }
+            stopped(namespace).await;
+        }
+        for namespace in start {
+            tracing::info!("watching namespace {}", namespace);
+            let handle = tokio::spawn(run(namespace.clone()));
+            running.insert(namespace, handle);
+        }
+        diagnostics.write().await.selected_namespaces = Some(running.len());
+    }
+    // The controllers shut down on the same signal
+    for (_, handle) in running {
+        handle.await.ok();
+    }
+}
+
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut terminate = signal(SignalKind::terminate()).expect("failed to listen for SIGTERM");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {},
+            _ = terminate.recv() => {},
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await.ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::{changes, WatchScope};
+
+    fn set(namespaces: &[&str]) -> BTreeSet<String> {
+        namespaces.iter().map(|ns| ns.to_string()).collect()
+    }
+
+    #[test]
+    fn test_watch_scope() {
+        assert_eq!(WatchScope::All, WatchScope::new(vec![], None));
+        assert_eq!(
+            WatchScope::Namespaces(vec!["data".into()]),
+            WatchScope::new(vec!["data".into()], None)
+        );
+        assert_eq!(
+            WatchScope::Selector {
+                selector: "delta=enabled".into(),
+                namespaces: vec![]
+            },
+            WatchScope::new(vec![], Some("delta=enabled".into()))
+        );
+    }
+
+    #[test]
+    fn test_changes() {
+        let (start, stop) = changes(&set(&["a", "b"]), &set(&["b", "c"]), &[]);
+        assert_eq!(vec!["c".to_owned()], start);
+        assert_eq!(vec!["a".to_owned()], stop);
+
+        let allowed = vec!["b".to_owned(), "d".to_owned()];
+        let (start, stop) = changes(&set(&["a"]), &set(&["b", "c", "d"]), &allowed);
+        assert_eq!(vec!["b".to_owned(), "d".to_owned()], start);
+        assert_eq!(vec!["a".to_owned()], stop);
+    }
+}
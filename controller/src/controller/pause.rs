@@ -0,0 +1,263 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, RwLock},
+};
+
+use futures::{channel::mpsc, Stream, StreamExt};
+use k8s_openapi::api::core::v1::ConfigMap;
+use kube::{
+    runtime::{watcher, WatchStreamExt},
+    Api, Client,
+};
+
+/// Key of the [`ConfigMap`] entry holding the label selector of paused tables
+pub const PAUSE_SELECTOR_KEY: &str = "pause-selector";
+
+#[derive(Clone, Debug, PartialEq)]
+enum Requirement {
+    Equals(String, String),
+    NotEquals(String, String),
+    In(String, Vec<String>),
+    NotIn(String, Vec<String>),
+    Exists(String),
+    DoesNotExist(String),
+}
+
+impl Requirement {
+    fn matches(&self, labels: &BTreeMap<String, String>) -> bool {
+        match self {
+            Requirement::Equals(k, v) => labels.get(k) == Some(v),
+            Requirement::NotEquals(k, v) => labels.get(k) != Some(v),
+            Requirement::In(k, values) => labels.get(k).is_some_and(|v| values.contains(v)),
+            Requirement::NotIn(k, values) => !labels.get(k).is_some_and(|v| values.contains(v)),
+            Requirement::Exists(k) => labels.contains_key(k),
+            Requirement::DoesNotExist(k) => !labels.contains_key(k),
+        }
+    }
+}
+
+/// A parsed Kubernetes label selector, e.g. `team=data,tier in (gold,silver),!legacy`
+#[derive(Clone, Debug, PartialEq)]
+pub struct LabelSelector(Vec<Requirement>);
+
+impl LabelSelector {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut requirements = Vec::new();
+        for term in split_terms(s) {
+            let term = term.trim();
+            if term.is_empty() {
+                continue;
+            }
+            requirements.push(parse_term(term)?);
+        }
+        Ok(Self(requirements))
+    }
+
+    /// Whether every requirement matches `labels`. An empty selector matches everything.
+    pub fn matches(&self, labels: &BTreeMap<String, String>) -> bool {
+        self.0.iter().all(|r| r.matches(labels))
+    }
+}
+
+/// Splits on commas that are not inside a `(...)` value list
+fn split_terms(s: &str) -> Vec<&str> {
+    let mut terms = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                terms.push(&s[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    terms.push(&s[start..]);
+    terms
+}
+
+fn parse_term(term: &str) -> Result<Requirement, String> {
+    if let Some(key) = term.strip_prefix('!') {
+        return Ok(Requirement::DoesNotExist(key.trim().to_owned()));
+    }
+    if let Some((key, value)) = term.split_once("!=") {
+        return Ok(Requirement::NotEquals(
+            key.trim().into(),
+            value.trim().into(),
+        ));
+    }
+    if let Some((key, value)) = term.split_once("==").or_else(|| term.split_once('=')) {
+        return Ok(Requirement::Equals(key.trim().into(), value.trim().into()));
+    }
+    if let Some((key, values)) = term.split_once(" notin ") {
+        return Ok(Requirement::NotIn(key.trim().into(), parse_values(values)?));
+    }
+    if let Some((key, values)) = term.split_once(" in ") {
+        return Ok(Requirement::In(key.trim().into(), parse_values(values)?));
+    }
+    if term.contains(char::is_whitespace) {
+        return Err(format!("invalid label selector term `{}`", term));
+    }
+    Ok(Requirement::Exists(term.to_owned()))
+}
+
+fn parse_values(values: &str) -> Result<Vec<String>, String> {
+    let values = values.trim();
+    match values.strip_prefix('(').and_then(|v| v.strip_suffix(')')) {
+        Some(inner) => Ok(inner
+            .split(',')
+            .map(|v| v.trim().to_owned())
+            .filter(|v| !v.is_empty())
+            .collect()),
+        None => Err(format!("invalid label selector values `{}`", values)),
+    }
+}
+
+/// Tables whose labels match the selector in a watched [`ConfigMap`] are paused
+#[derive(Clone, Default)]
+pub(crate) struct PauseToggle {
+    selector: Arc<RwLock<Option<LabelSelector>>>,
+    listeners: Arc<Mutex<Vec<mpsc::UnboundedSender<()>>>>,
+}
+
+impl PauseToggle {
+    pub(crate) fn is_paused(&self, labels: &BTreeMap<String, String>) -> bool {
+        self.selector
+            .read()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|s| s.matches(labels))
+    }
+
+    fn update(&self, config_map: Option<&ConfigMap>) {
+        let value = config_map
+            .and_then(|c| c.data.as_ref())
+            .and_then(|d| d.get(PAUSE_SELECTOR_KEY))
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty());
+        let selector = match value {
+            Some(value) => match LabelSelector::parse(value) {
+                Ok(selector) => {
+                    tracing::info!("pausing tables matching `{}`", value);
+                    Some(selector)
+                }
+                Err(e) => {
+                    tracing::error!("ignoring pause toggle: {}", e);
+                    None
+                }
+            },
+            None => None,
+        };
+        *self.selector.write().unwrap() = selector;
+    }
+
+    /// Yields after every change of the selector, so that all tables can be reconciled
+    /// against it
+    ///
+    /// The controller requires trigger streams to be `Sync`, which the watcher is not, so
+    /// each controller gets a channel fed by [`PauseToggle::run`].
+    pub(crate) fn changes(&self) -> impl Stream<Item = ()> + Send + Sync + 'static {
+        let (sender, receiver) = mpsc::unbounded();
+        self.listeners.lock().unwrap().push(sender);
+        receiver
+    }
+
+    /// Watches the [`ConfigMap`] `name` in `namespace` and updates the selector until the
+    /// task is dropped
+    pub(crate) async fn run(self, client: Client, namespace: String, name: String) {
+        let api: Api<ConfigMap> = Api::namespaced(client, &namespace);
+        let mut events = watcher::watch_object(api, &name).default_backoff().boxed();
+        while let Some(event) = events.next().await {
+            match event {
+                Ok(config_map) => {
+                    self.update(config_map.as_ref());
+                    self.listeners
+                        .lock()
+                        .unwrap()
+                        .retain(|sender| sender.unbounded_send(()).is_ok());
+                }
+                Err(e) => tracing::warn!("failed to watch pause toggle: {}", e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{LabelSelector, PauseToggle, PAUSE_SELECTOR_KEY};
+    use k8s_openapi::api::core::v1::ConfigMap;
+
+    #[test]
+    fn test_selector_equality() {
+        let selector = LabelSelector::parse("team=data, env!=prod").unwrap();
+        assert!(selector.matches(&BTreeMap::from([("team".to_owned(), "data".to_owned())])));
+        assert!(selector.matches(&BTreeMap::from([
+            ("team".to_owned(), "data".to_owned()),
+            ("env".to_owned(), "dev".to_owned()),
+        ])));
+        assert!(!selector.matches(&BTreeMap::from([
+            ("team".to_owned(), "data".to_owned()),
+            ("env".to_owned(), "prod".to_owned()),
+        ])));
+        assert!(!selector.matches(&BTreeMap::from([("team".to_owned(), "web".to_owned())])));
+    }
+
+    #[test]
+    fn test_selector_sets() {
+        let selector = LabelSelector::parse("tier in (gold, silver),region notin (eu)").unwrap();
+        assert!(selector.matches(&BTreeMap::from([("tier".to_owned(), "gold".to_owned())])));
+        assert!(!selector.matches(&BTreeMap::from([("tier".to_owned(), "bronze".to_owned())])));
+        assert!(!selector.matches(&BTreeMap::from([
+            ("tier".to_owned(), "silver".to_owned()),
+            ("region".to_owned(), "eu".to_owned()),
+        ])));
+        assert!(!selector.matches(&BTreeMap::new()));
+    }
+
+    #[test]
+    fn test_selector_existence() {
+        let selector = LabelSelector::parse("frozen,!legacy").unwrap();
+        assert!(selector.matches(&BTreeMap::from([("frozen".to_owned(), "".to_owned())])));
+        assert!(!selector.matches(&BTreeMap::from([
+            ("frozen".to_owned(), "".to_owned()),
+            ("legacy".to_owned(), "yes".to_owned()),
+        ])));
+        assert!(!selector.matches(&BTreeMap::new()));
+    }
+
+    #[test]
+    fn test_selector_empty_matches_everything() {
+        assert!(LabelSelector::parse("").unwrap().matches(&BTreeMap::new()));
+    }
+
+    #[test]
+    fn test_selector_invalid() {
+        assert!(LabelSelector::parse("tier in gold").is_err());
+        assert!(LabelSelector::parse("tier gold").is_err());
+    }
+
+    #[test]
+    fn test_toggle_update() {
+        let toggle = PauseToggle::default();
+        assert!(!toggle.is_paused(&BTreeMap::from([("team".to_owned(), "data".to_owned())])));
+
+        let config_map = ConfigMap {
+            data: Some(BTreeMap::from([(
+                PAUSE_SELECTOR_KEY.to_owned(),
+                "team=data".to_owned(),
+            )])),
+            ..Default::default()
+        };
+        toggle.update(Some(&config_map));
+        assert!(toggle.is_paused(&BTreeMap::from([("team".to_owned(), "data".to_owned())])));
+        assert!(!toggle.is_paused(&BTreeMap::from([("team".to_owned(), "web".to_owned())])));
+
+        toggle.update(None);
+        assert!(!toggle.is_paused(&BTreeMap::from([("team".to_owned(), "data".to_owned())])));
+    }
+}
@@ -0,0 +1,879 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use delta_operator_crd::{
+    maintenance::{
+        join_actions, sort_actions, Action, JobSettings, MaintenanceExecutor, RunningMaintenance,
+    },
+    policy::{Clock, SystemClock},
+    DeltaTable, Error, QueuedMaintenance,
+};
+use kube::{
+    api::{Patch, PatchParams},
+    Api, Client, Resource, ResourceExt,
+};
+use rand::Rng;
+use serde_json::json;
+use tokio::{
+    sync::{watch, Mutex, RwLock},
+    task::JoinHandle,
+};
+
+use tracing::Instrument;
+
+use super::{diagnostics::Diagnostics, pause::PauseToggle};
+
+/// Caps on how much maintenance may run at once. `None` means unlimited.
+#[derive(Clone, Debug, Default)]
+pub struct SchedulerLimits {
+    pub global: Option<usize>,
+    pub per_namespace: Option<usize>,
+    pub per_action: Option<usize>,
+    /// Upper bound of the random delay added before queued work may start
+    pub jitter: Duration,
+}
+
+impl SchedulerLimits {
+    /// Caps the global limit at the `capacity` of the executor
+    pub(crate) fn with_capacity(mut self, capacity: usize) -> Self {
+        self.global = Some(self.global.map_or(capacity, |l| l.min(capacity)));
+        self
+    }
+}
+
+/// Maintenance pipeline for one table waiting for capacity
+struct Pending {
+    namespace: String,
+    table: String,
+    actions: Vec<Action>,
+    priority: i32,
+    /// Seconds since the stalest action last ran, `i64::MAX` if one never ran
+    staleness: i64,
+    sequence: u64,
+    not_before: i64,
+    /// Span of the reconcile that queued the work, so that starting it joins its trace
+    span: tracing::Span,
+}
+
+impl Pending {
+    fn is(&self, namespace: &str, table: &str) -> bool {
+        self.namespace == namespace && self.table == table
+    }
+}
+
+#[derive(Default)]
+struct Queue {
+    pending: Vec<Pending>,
+    sequence: u64,
+    /// Positions last written to each table's status, keyed by `namespace/name`
+    reported: HashMap<String, QueuedMaintenance>,
+}
+
+/// Counts of running maintenance used to check [`SchedulerLimits`]
+#[derive(Default)]
+struct Usage {
+    total: usize,
+    by_namespace: HashMap<String, usize>,
+    by_action: HashMap<Action, usize>,
+}
+
+impl Usage {
+    fn new(running: &[RunningMaintenance]) -> Self {
+        let mut usage = Self::default();
+        for r in running {
+            usage.add(&r.namespace, &r.actions);
+        }
+        usage
+    }
+
+    fn add(&mut self, namespace: &str, actions: &[Action]) {
+        self.total += 1;
+        *self.by_namespace.entry(namespace.to_owned()).or_default() += 1;
+        for action in actions {
+            *self.by_action.entry(action.clone()).or_default() += 1;
+        }
+    }
+
+    fn admits(&self, limits: &SchedulerLimits, namespace: &str, actions: &[Action]) -> bool {
+        let under = |limit: Option<usize>, count: usize| !matches!(limit, Some(l) if count >= l);
+        under(limits.global, self.total)
+            && under(
+                limits.per_namespace,
+                self.by_namespace
+                    .get(namespace)
+                    .copied()
+                    .unwrap_or_default(),
+            )
+            && actions.iter().all(|action| {
+                under(
+                    limits.per_action,
+                    self.by_action.get(action).copied().unwrap_or_default(),
+                )
+            })
+    }
+}
+
+/// Whether queued maintenance of `doc` may still start, unless the controller runs dry or
+/// the table was deleted, suspended or paused while the work waited
+fn startable(doc: &DeltaTable, pause: &PauseToggle, dry_run: bool) -> bool {
+    !dry_run
+        && doc.meta().deletion_timestamp.is_none()
+        && !doc.spec.suspend.unwrap_or_default()
+        && !pause.is_paused(doc.labels())
+}
+
+/// Sorts pending work by priority, then staleness, then arrival
+fn order(pending: &mut [Pending]) {
+    pending.sort_by(|a, b| {
+        b.priority
+            .cmp(&a.priority)
+            .then(b.staleness.cmp(&a.staleness))
+            .then(a.sequence.cmp(&b.sequence))
+    });
+}
+
+/// Seconds since the stalest of `actions` last ran on `doc`, `i64::MAX` if one never ran
+fn staleness(doc: &DeltaTable, actions: &[Action], now: i64) -> i64 {
+    actions
+        .iter()
+        .map(|action| {
+            doc.status
+                .as_ref()
+                .and_then(|s| s.last_maintenance_timestamp(action))
+                .map_or(i64::MAX, |t| now.saturating_sub(t))
+        })
+        .max()
+        .unwrap_or_default()
+}
+
+/// Removes and returns the pending work that may start now without exceeding `limits`.
+/// `pending` must already be [`order`]ed.
+///
+/// At most one pipeline runs per table. Actions that are already running for a table are
+/// dropped from its pending pipeline.
+fn take_ready(
+    pending: &mut Vec<Pending>,
+    running: &[RunningMaintenance],
+    limits: &SchedulerLimits,
+    now: i64,
+) -> Vec<Pending> {
+    let mut usage = Usage::new(running);
+    let mut ready = Vec::new();
+    let mut index = 0;
+    while index < pending.len() {
+        let p = &mut pending[index];
+        let current = running.iter().find(|r| p.is(&r.namespace, &r.table));
+        if let Some(current) = current {
+            p.actions.retain(|a| !current.actions.contains(a));
+            if p.actions.is_empty() {
+                pending.remove(index);
+            } else {
+                index += 1;
+            }
+            continue;
+        }
+
+        if p.not_before <= now && usage.admits(limits, &p.namespace, &p.actions) {
+            usage.add(&p.namespace, &p.actions);
+            ready.push(pending.remove(index));
+            continue;
+        }
+
+        index += 1;
+    }
+
+    ready
+}
+
+/// Adds `actions` to `existing` without duplicates, keeping `action_order`
+fn merge_actions(existing: &mut Vec<Action>, actions: Vec<Action>, action_order: &[Action]) {
+    for action in actions {
+        if !existing.contains(&action) {
+            existing.push(action);
+        }
+    }
+    sort_actions(existing, action_order);
+}
+
+/// Queue positions of pending work keyed by `namespace/name`
+fn positions(pending: &[Pending]) -> HashMap<String, QueuedMaintenance> {
+    pending
+        .iter()
+        .enumerate()
+        .map(|(index, p)| {
+            (
+                format!("{}/{}", p.namespace, p.table),
+                QueuedMaintenance {
+                    actions: p.actions.clone(),
+                    position: index + 1,
+                },
+            )
+        })
+        .collect()
+}
+
+/// [`MaintenanceExecutor`] that queues work and hands it to an inner executor while
+/// respecting [`SchedulerLimits`]
+pub(crate) struct Scheduler {
+    inner: Arc<dyn MaintenanceExecutor>,
+    client: Client,
+    limits: SchedulerLimits,
+    settings: Arc<JobSettings>,
+    pause: PauseToggle,
+    queue: Arc<Mutex<Queue>>,
+    diagnostics: Arc<RwLock<Diagnostics>>,
+    shutdown: watch::Sender<bool>,
+    handle: std::sync::Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Scheduler {
+    /// Creates the scheduler and starts dispatching queued work every `interval`
+    pub(crate) fn start(
+        inner: Arc<dyn MaintenanceExecutor>,
+        client: Client,
+        limits: SchedulerLimits,
+        settings: Arc<JobSettings>,
+        pause: PauseToggle,
+        interval: Duration,
+        diagnostics: Arc<RwLock<Diagnostics>>,
+    ) -> Arc<Self> {
+        let (shutdown, _) = watch::channel(false);
+        let scheduler = Arc::new(Self {
+            inner,
+            client,
+            limits,
+            settings,
+            pause,
+            queue: Arc::new(Mutex::new(Queue::default())),
+            diagnostics,
+            shutdown,
+            handle: std::sync::Mutex::new(None),
+        });
+
+        let mut stop = scheduler.shutdown.subscribe();
+        let worker = scheduler.clone();
+        let handle = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => worker.dispatch(&SystemClock).await,
+                    _ = stop.changed() => break,
+                }
+            }
+        });
+        *scheduler.handle.lock().unwrap() = Some(handle);
+        scheduler
+    }
+
+    async fn dispatch(&self, clock: &dyn Clock) {
+        let running = match self.inner.running().await {
+            Ok(running) => running,
+            Err(e) => {
+                tracing::error!("failed to list running maintenance: {}", e);
+                return;
+            }
+        };
+
+        let mut queue = self.queue.lock().await;
+        order(&mut queue.pending);
+        let ready = take_ready(
+            &mut queue.pending,
+            &running,
+            &self.limits,
+            clock.timestamp(),
+        );
+        for p in ready {
+            let actions = join_actions(&p.actions, ",");
+            tracing::info!(
+                "starting {} on {}/{} from the maintenance queue",
+                actions,
+                p.namespace,
+                p.table
+            );
+            let span =
+                tracing::info_span!(parent: &p.span, "start_maintenance", actions = %actions);
+            let (namespace, table) = (p.namespace.clone(), p.table.clone());
+            if let Err(e) = self.start_pending(p).instrument(span).await {
+                tracing::error!(
+                    "failed to start {} on {}/{}: {}",
+                    actions,
+                    namespace,
+                    table,
+                    e
+                );
+            }
+        }
+
+        let current = positions(&queue.pending);
+        let mut changed: BTreeMap<String, Option<QueuedMaintenance>> = BTreeMap::new();
+        for (key, value) in current.iter() {
+            if queue.reported.get(key) != Some(value) {
+                changed.insert(key.clone(), Some(value.clone()));
+            }
+        }
+        for key in queue.reported.keys() {
+            if !current.contains_key(key) {
+                changed.insert(key.clone(), None);
+            }
+        }
+        queue.reported = current;
+        self.diagnostics.write().await.queue_depth = queue.pending.len();
+        drop(queue);
+
+        for (key, value) in changed {
+            let (namespace, name) = key.split_once('/').unwrap();
+            self.report(namespace, name, value).await;
+        }
+    }
+
+    /// Starts the actions of `p` that are still due with the live table and freshly
+    /// resolved storage options, unless the table may no longer be maintained
+    async fn start_pending(&self, p: Pending) -> Result<(), Error> {
+        let api: Api<DeltaTable> = Api::namespaced(self.client.clone(), &p.namespace);
+        let doc = match api.get_opt(&p.table).await? {
+            Some(doc) if startable(&doc, &self.pause, self.settings.dry_run) => doc,
+            _ => {
+                tracing::info!(
+                    "dropping queued maintenance of {}/{}: deleted, suspended or paused",
+                    p.namespace,
+                    p.table
+                );
+                return Ok(());
+            }
+        };
+        let actions = doc.still_due(
+            &p.actions,
+            &self.settings.maintenance_defaults,
+            &SystemClock,
+        );
+        if actions.is_empty() {
+            tracing::info!(
+                "dropping queued maintenance of {}/{}: no longer due",
+                p.namespace,
+                p.table
+            );
+            return Ok(());
+        }
+        let storage_options = doc
+            .accumulate_storage_options(self.client.clone(), &p.namespace)
+            .await?;
+        self.inner.execute(&doc, actions, &storage_options).await
+    }
+
+    async fn report(&self, namespace: &str, name: &str, value: Option<QueuedMaintenance>) {
+        let api: Api<DeltaTable> = Api::namespaced(self.client.clone(), namespace);
+        match api
+            .patch_status(
+                name,
+                &PatchParams::default(),
+                &Patch::Merge(json!({
+                    "status": {
+                        "maintenance_queue": value,
+                    }
+                })),
+            )
+            .await
+        {
+            Ok(_) => {}
+            // The table was deleted while queued
+            Err(kube::Error::Api(r)) if r.code == 404 => {}
+            Err(e) => tracing::error!("failed to patch maintenance queue of {}: {}", name, e),
+        }
+    }
+}
+
+#[async_trait]
+impl MaintenanceExecutor for Scheduler {
+    async fn execute(
+        &self,
+        doc: &DeltaTable,
+        actions: Vec<Action>,
+        _: &HashMap<String, String>,
+    ) -> Result<(), Error> {
+        let namespace = doc.namespace().unwrap();
+        let table = doc.name_any();
+        let now = SystemClock.timestamp();
+        let priority = doc.spec.maintenance_priority.unwrap_or_default();
+
+        let mut queue = self.queue.lock().await;
+        if let Some(existing) = queue.pending.iter_mut().find(|p| p.is(&namespace, &table)) {
+            merge_actions(
+                &mut existing.actions,
+                actions,
+                &self.settings.maintenance_order,
+            );
+            existing.priority = priority;
+            existing.staleness = staleness(doc, &existing.actions, now);
+            existing.span = tracing::Span::current();
+            return Ok(());
+        }
+
+        let jitter = self.limits.jitter.as_secs();
+        let delay = match jitter {
+            0 => 0,
+            _ => rand::thread_rng().gen_range(0..=jitter) as i64,
+        };
+        let mut ordered = Vec::with_capacity(actions.len());
+        merge_actions(&mut ordered, actions, &self.settings.maintenance_order);
+        queue.sequence += 1;
+        let sequence = queue.sequence;
+        queue.pending.push(Pending {
+            namespace,
+            table,
+            staleness: staleness(doc, &ordered, now),
+            actions: ordered,
+            priority,
+            sequence,
+            not_before: now + delay,
+            span: tracing::Span::current(),
+        });
+        Ok(())
+    }
+
+    async fn running(&self) -> Result<Vec<RunningMaintenance>, Error> {
+        self.inner.running().await
+    }
+
+    async fn cancel(&self, namespace: &str, table: &str) {
+        let mut queue = self.queue.lock().await;
+        let before = queue.pending.len();
+        queue.pending.retain(|p| !p.is(namespace, table));
+        if queue.pending.len() < before {
+            tracing::info!("dropped queued maintenance of {}/{}", namespace, table);
+        }
+    }
+
+    async fn cancel_namespace(&self, namespace: &str) {
+        let mut queue = self.queue.lock().await;
+        let before = queue.pending.len();
+        queue.pending.retain(|p| p.namespace != namespace);
+        if queue.pending.len() < before {
+            tracing::info!("dropped queued maintenance of namespace {}", namespace);
+        }
+    }
+
+    async fn shutdown(&self) {
+        self.shutdown.send_replace(true);
+        let handle = self.handle.lock().unwrap().take();
+        if let Some(handle) = handle {
+            if let Err(e) = handle.await {
+                tracing::error!("maintenance scheduler failed: {}", e);
+            }
+        }
+        self.inner.shutdown().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORDER: [Action; 3] = [Action::Optimize, Action::Checkpoint, Action::Vacuum];
+
+    fn tables(items: &[Pending]) -> Vec<&str> {
+        items.iter().map(|p| p.table.as_str()).collect()
+    }
+
+    #[test]
+    fn test_limits_with_capacity() {
+        assert_eq!(Some(2), SchedulerLimits::default().with_capacity(2).global);
+        let limits = SchedulerLimits {
+            global: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(Some(1), limits.clone().with_capacity(2).global);
+        assert_eq!(
+            Some(1),
+            SchedulerLimits {
+                global: Some(4),
+                ..limits
+            }
+            .with_capacity(1)
+            .global
+        );
+    }
+
+    #[test]
+    fn test_order_priority_staleness_sequence() {
+        let mut items = vec![
+            Pending {
+                namespace: "a".into(),
+                table: "low".into(),
+                actions: vec![Action::Optimize],
+                priority: 0,
+                staleness: 0,
+                sequence: 1,
+                not_before: 0,
+                span: tracing::Span::none(),
+            },
+            Pending {
+                namespace: "a".into(),
+                table: "stale".into(),
+                actions: vec![Action::Optimize],
+                priority: 0,
+                staleness: 10,
+                sequence: 2,
+                not_before: 0,
+                span: tracing::Span::none(),
+            },
+            Pending {
+                namespace: "a".into(),
+                table: "high".into(),
+                actions: vec![Action::Optimize],
+                priority: 5,
+                staleness: 0,
+                sequence: 3,
+                not_before: 0,
+                span: tracing::Span::none(),
+            },
+            Pending {
+                namespace: "a".into(),
+                table: "first".into(),
+                actions: vec![Action::Optimize],
+                priority: 0,
+                staleness: 0,
+                sequence: 0,
+                not_before: 0,
+                span: tracing::Span::none(),
+            },
+        ];
+        order(&mut items);
+        assert_eq!(vec!["high", "stale", "first", "low"], tables(&items));
+    }
+
+    #[test]
+    fn test_take_ready_unlimited() {
+        let mut items = vec![
+            Pending {
+                namespace: "a".into(),
+                table: "one".into(),
+                actions: vec![Action::Optimize],
+                priority: 0,
+                staleness: 0,
+                sequence: 0,
+                not_before: 0,
+                span: tracing::Span::none(),
+            },
+            Pending {
+                namespace: "b".into(),
+                table: "two".into(),
+                actions: vec![Action::Vacuum],
+                priority: 0,
+                staleness: 0,
+                sequence: 0,
+                not_before: 0,
+                span: tracing::Span::none(),
+            },
+        ];
+        let ready = take_ready(&mut items, &[], &SchedulerLimits::default(), 0);
+        assert_eq!(2, ready.len());
+        assert!(items.is_empty());
+    }
+
+    #[test]
+    fn test_take_ready_global_limit() {
+        let limits = SchedulerLimits {
+            global: Some(2),
+            ..Default::default()
+        };
+        let mut items = vec![
+            Pending {
+                namespace: "a".into(),
+                table: "one".into(),
+                actions: vec![Action::Optimize],
+                priority: 0,
+                staleness: 0,
+                sequence: 0,
+                not_before: 0,
+                span: tracing::Span::none(),
+            },
+            Pending {
+                namespace: "a".into(),
+                table: "two".into(),
+                actions: vec![Action::Optimize],
+                priority: 0,
+                staleness: 0,
+                sequence: 0,
+                not_before: 0,
+                span: tracing::Span::none(),
+            },
+            Pending {
+                namespace: "a".into(),
+                table: "three".into(),
+                actions: vec![Action::Optimize],
+                priority: 0,
+                staleness: 0,
+                sequence: 0,
+                not_before: 0,
+                span: tracing::Span::none(),
+            },
+        ];
+        let ready = take_ready(
+            &mut items,
+            &[RunningMaintenance {
+                namespace: "b".into(),
+                table: "other".into(),
+                actions: vec![Action::Vacuum],
+            }],
+            &limits,
+            0,
+        );
+        assert_eq!(vec!["one"], tables(&ready));
+        assert_eq!(vec!["two", "three"], tables(&items));
+    }
+
+    #[test]
+    fn test_take_ready_namespace_and_action_limits() {
+        let limits = SchedulerLimits {
+            per_namespace: Some(1),
+            per_action: Some(1),
+            ..Default::default()
+        };
+        let mut items = vec![
+            Pending {
+                namespace: "a".into(),
+                table: "one".into(),
+                actions: vec![Action::Optimize],
+                priority: 0,
+                staleness: 0,
+                sequence: 0,
+                not_before: 0,
+                span: tracing::Span::none(),
+            },
+            Pending {
+                namespace: "a".into(),
+                table: "two".into(),
+                actions: vec![Action::Vacuum],
+                priority: 0,
+                staleness: 0,
+                sequence: 0,
+                not_before: 0,
+                span: tracing::Span::none(),
+            },
+            Pending {
+                namespace: "b".into(),
+                table: "three".into(),
+                actions: vec![Action::Optimize, Action::Vacuum],
+                priority: 0,
+                staleness: 0,
+                sequence: 0,
+                not_before: 0,
+                span: tracing::Span::none(),
+            },
+            Pending {
+                namespace: "b".into(),
+                table: "four".into(),
+                actions: vec![Action::Vacuum],
+                priority: 0,
+                staleness: 0,
+                sequence: 0,
+                not_before: 0,
+                span: tracing::Span::none(),
+            },
+        ];
+        let ready = take_ready(&mut items, &[], &limits, 0);
+        assert_eq!(vec!["one", "four"], tables(&ready));
+        assert_eq!(vec!["two", "three"], tables(&items));
+    }
+
+    #[test]
+    fn test_take_ready_respects_jitter() {
+        let mut items = vec![
+            Pending {
+                namespace: "a".into(),
+                table: "later".into(),
+                actions: vec![Action::Optimize],
+                priority: 0,
+                staleness: 0,
+                sequence: 0,
+                not_before: 100,
+                span: tracing::Span::none(),
+            },
+            Pending {
+                namespace: "a".into(),
+                table: "now".into(),
+                actions: vec![Action::Optimize],
+                priority: 0,
+                staleness: 0,
+                sequence: 0,
+                not_before: 0,
+                span: tracing::Span::none(),
+            },
+        ];
+        let ready = take_ready(&mut items, &[], &SchedulerLimits::default(), 50);
+        assert_eq!(vec!["now"], tables(&ready));
+        assert_eq!(vec!["later"], tables(&items));
+    }
+
+    #[test]
+    fn test_take_ready_one_pipeline_per_table() {
+        let mut items = vec![
+            Pending {
+                namespace: "a".into(),
+                table: "one".into(),
+                actions: vec![Action::Optimize, Action::Vacuum],
+                priority: 0,
+                staleness: 0,
+                sequence: 0,
+                not_before: 0,
+                span: tracing::Span::none(),
+            },
+            Pending {
+                namespace: "a".into(),
+                table: "two".into(),
+                actions: vec![Action::Checkpoint, Action::Vacuum],
+                priority: 0,
+                staleness: 0,
+                sequence: 0,
+                not_before: 0,
+                span: tracing::Span::none(),
+            },
+        ];
+        let ready = take_ready(
+            &mut items,
+            &[RunningMaintenance {
+                namespace: "a".into(),
+                table: "two".into(),
+                actions: vec![Action::Checkpoint],
+            }],
+            &SchedulerLimits::default(),
+            0,
+        );
+        assert_eq!(vec!["one"], tables(&ready));
+        assert_eq!(vec!["two"], tables(&items));
+        assert_eq!(vec![Action::Vacuum], items[0].actions);
+    }
+
+    #[test]
+    fn test_take_ready_drops_running_duplicates() {
+        let mut items = vec![Pending {
+            namespace: "a".into(),
+            table: "one".into(),
+            actions: vec![Action::Optimize],
+            priority: 0,
+            staleness: 0,
+            sequence: 0,
+            not_before: 0,
+            span: tracing::Span::none(),
+        }];
+        let ready = take_ready(
+            &mut items,
+            &[RunningMaintenance {
+                namespace: "a".into(),
+                table: "one".into(),
+                actions: vec![Action::Optimize, Action::Vacuum],
+            }],
+            &SchedulerLimits::default(),
+            0,
+        );
+        assert!(ready.is_empty());
+        assert!(items.is_empty());
+    }
+
+    #[test]
+    fn test_merge_actions_follows_order() {
+        let mut actions = vec![Action::Vacuum];
+        merge_actions(
+            &mut actions,
+            vec![Action::Checkpoint, Action::Vacuum, Action::Optimize],
+            &ORDER,
+        );
+        assert_eq!(
+            vec![Action::Optimize, Action::Checkpoint, Action::Vacuum],
+            actions
+        );
+    }
+
+    #[test]
+    fn test_startable() {
+        let pause = PauseToggle::default();
+        let mut doc: DeltaTable = serde_json::from_value(json!({
+            "apiVersion": "delta-operator.rs/v1alpha1",
+            "kind": "DeltaTable",
+            "metadata": { "name": "one", "namespace": "a" },
+            "spec": {
+                "name": "one",
+                "table_uri": "",
+                "schema_settings": { "value": "" },
+                "partition_columns": [],
+            },
+        }))
+        .unwrap();
+        assert!(startable(&doc, &pause, false));
+        assert!(!startable(&doc, &pause, true));
+        doc.spec.suspend = Some(true);
+        assert!(!startable(&doc, &pause, false));
+        doc.spec.suspend = None;
+        doc.metadata.deletion_timestamp = Some(
+            k8s_openapi::apimachinery::pkg::apis::meta::v1::Time(chrono::Utc::now()),
+        );
+        assert!(!startable(&doc, &pause, false));
+    }
+
+    #[test]
+    fn test_staleness() {
+        let mut doc: DeltaTable = serde_json::from_value(json!({
+            "apiVersion": "delta-operator.rs/v1alpha1",
+            "kind": "DeltaTable",
+            "metadata": { "name": "one", "namespace": "a" },
+            "spec": {
+                "name": "one",
+                "table_uri": "",
+                "schema_settings": { "value": "" },
+                "partition_columns": [],
+            },
+        }))
+        .unwrap();
+        assert_eq!(i64::MAX, staleness(&doc, &[Action::Optimize], 100));
+        doc.status = Some(delta_operator_crd::DeltaTableStatus {
+            last_optimize_timestamp: Some(90),
+            last_vacuum_timestamp: Some(40),
+            ..Default::default()
+        });
+        assert_eq!(10, staleness(&doc, &[Action::Optimize], 100));
+        assert_eq!(
+            60,
+            staleness(&doc, &[Action::Optimize, Action::Vacuum], 100)
+        );
+    }
+
+    #[test]
+    fn test_positions() {
+        let items = vec![
+            Pending {
+                namespace: "a".into(),
+                table: "one".into(),
+                actions: vec![Action::Optimize, Action::Vacuum],
+                priority: 0,
+                staleness: 0,
+                sequence: 0,
+                not_before: 0,
+                span: tracing::Span::none(),
+            },
+            Pending {
+                namespace: "a".into(),
+                table: "two".into(),
+                actions: vec![Action::Optimize],
+                priority: 0,
+                staleness: 0,
+                sequence: 0,
+                not_before: 0,
+                span: tracing::Span::none(),
+            },
+        ];
+        let result = positions(&items);
+        assert_eq!(2, result.len());
+        assert_eq!(
+            QueuedMaintenance {
+                actions: vec![Action::Optimize, Action::Vacuum],
+                position: 1
+            },
+            result["a/one"]
+        );
+        assert_eq!(2, result["a/two"].position);
+    }
+}
@@ -0,0 +1,330 @@
+//! Splits the tables between controller replicas
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use futures::{channel::mpsc, Stream};
+use k8s_openapi::{
+    api::coordination::v1::{Lease, LeaseSpec},
+    apimachinery::pkg::apis::meta::v1::MicroTime,
+};
+use kube::{
+    api::{ListParams, Patch, PatchParams},
+    core::ObjectMeta,
+    Api, Client, ResourceExt,
+};
+
+/// Label holding the shard group of a [`Lease`]
+pub const LABEL_SHARD_GROUP: &str = "delta-operator.rs/shard-group";
+const FIELD_MANAGER: &str = "delta-operator";
+
+/// Settings of the shard group a replica joins
+#[derive(Clone, Debug)]
+pub struct ShardSettings {
+    pub group: String,
+    /// Unique name of this replica, usually its pod name
+    pub identity: String,
+    /// Namespace of the leases
+    pub namespace: String,
+    /// How long a lease is valid without being renewed
+    pub lease_duration: Duration,
+}
+
+/// FNV-1a followed by the MurmurHash3 finalizer, which spreads the difference of
+/// similar names over the high bits. Unlike the std hasher it is stable across builds.
+fn hash(member: &str, key: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in member.bytes().chain([0]).chain(key.bytes()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51afd7ed558ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ceb9fe1a85ec53);
+    hash ^ (hash >> 33)
+}
+
+/// The member that reconciles the table `key`, chosen by rendezvous hashing so that a
+/// member joining or leaving only moves the tables it gains or held
+fn owner<'a>(members: &'a BTreeSet<String>, key: &str) -> Option<&'a str> {
+    members
+        .iter()
+        .max_by_key(|member| hash(member, key))
+        .map(String::as_str)
+}
+
+/// Holders of the leases in `leases` that are still valid at `now`
+fn live_members(leases: &[Lease], now: chrono::DateTime<Utc>) -> BTreeSet<String> {
+    leases
+        .iter()
+        .filter_map(|lease| {
+            let spec = lease.spec.as_ref()?;
+            let renewed = spec.renew_time.as_ref()?.0;
+            let duration = chrono::Duration::try_seconds(spec.lease_duration_seconds?.into())?;
+            (renewed + duration > now)
+                .then(|| spec.holder_identity.clone())
+                .flatten()
+        })
+        .collect()
+}
+
+/// Whether the lease of this replica, last renewed at `renewed`, is no longer valid at
+/// `now`
+fn lease_expired(renewed: Option<Instant>, now: Instant, lease_duration: Duration) -> bool {
+    match renewed {
+        Some(renewed) => now.saturating_duration_since(renewed) >= lease_duration,
+        None => true,
+    }
+}
+
+/// Membership of this replica's shard group and the tables it owns. The members are
+/// the replicas holding an unexpired [`Lease`] of the group.
+#[derive(Clone)]
+pub(crate) struct Shard {
+    settings: Arc<ShardSettings>,
+    members: Arc<RwLock<BTreeSet<String>>>,
+    owned: Arc<Mutex<BTreeSet<String>>>,
+    listeners: Arc<Mutex<Vec<mpsc::UnboundedSender<()>>>>,
+}
+
+impl Shard {
+    pub(crate) fn new(settings: ShardSettings) -> Self {
+        Self {
+            settings: Arc::new(settings),
+            members: Default::default(),
+            owned: Default::default(),
+            listeners: Default::default(),
+        }
+    }
+
+    /// Whether this replica reconciles the table `name` in `namespace`. Nothing is owned
+    /// until the membership is known.
+    pub(crate) fn owns(&self, namespace: &str, name: &str) -> bool {
+        let key = format!("{}/{}", namespace, name);
+        let owned = owner(&self.members.read().unwrap(), &key) == Some(&self.settings.identity);
+        let mut tables = self.owned.lock().unwrap();
+        let changed = match owned {
+            true => tables.insert(key),
+            false => tables.remove(&key),
+        };
+        if changed {
+            self.report_owned(&tables);
+        }
+        owned
+    }
+
+    /// Stops counting the table `name` in `namespace` as owned once it is deleted
+    pub(crate) fn forget(&self, namespace: &str, name: &str) {
+        let mut tables = self.owned.lock().unwrap();
+        if tables.remove(&format!("{}/{}", namespace, name)) {
+            self.report_owned(&tables);
+        }
+    }
+
+    /// Forgets the owned tables for which `deleted(namespace, name)` holds. Tables
+    /// deleted without a finalizer are never reconciled again, so they are found by
+    /// looking them up in the controller's store.
+    pub(crate) fn forget_deleted(&self, deleted: impl Fn(&str, &str) -> bool) {
+        let mut tables = self.owned.lock().unwrap();
+        let before = tables.len();
+        tables.retain(|key| match key.split_once('/') {
+            Some((namespace, name)) => !deleted(namespace, name),
+            None => true,
+        });
+        if tables.len() != before {
+            self.report_owned(&tables);
+        }
+    }
+
+    /// How often the lease is renewed, which is also how often deleted tables are
+    /// forgotten
+    pub(crate) fn renew_interval(&self) -> Duration {
+        self.settings.lease_duration / 3
+    }
+
+    fn report_owned(&self, tables: &BTreeSet<String>) {
+        metrics::gauge!(
+            "delta_operator_shard_tables",
+            tables.len() as f64,
+            "shard" => self.settings.identity.clone()
+        );
+    }
+
+    /// Yields whenever the membership changes, so that all tables can be reconciled
+    /// against the new assignment
+    pub(crate) fn changes(&self) -> impl Stream<Item = ()> + Send + Sync + 'static {
+        let (sender, receiver) = mpsc::unbounded();
+        self.listeners.lock().unwrap().push(sender);
+        receiver
+    }
+
+    fn set_members(&self, members: BTreeSet<String>) {
+        if *self.members.read().unwrap() == members {
+            return;
+        }
+        tracing::info!(
+            "shard group {} has members {:?}",
+            self.settings.group,
+            members
+        );
+        metrics::gauge!(
+            "delta_operator_shard_members",
+            members.len() as f64,
+            "shard" => self.settings.identity.clone()
+        );
+        *self.members.write().unwrap() = members;
+        self.listeners
+            .lock()
+            .unwrap()
+            .retain(|sender| sender.unbounded_send(()).is_ok());
+    }
+
+    /// Renews this replica's lease and refreshes the membership until the task is
+    /// dropped
+    pub(crate) async fn run(self, client: Client) {
+        let api: Api<Lease> = Api::namespaced(client, &self.settings.namespace);
+        let mut interval = tokio::time::interval(self.renew_interval());
+        let mut renewed = None;
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.renew(&api).await {
+                tracing::warn!("failed to renew shard lease: {}", e);
+                // Other members take over the tables once the lease expired
+                if lease_expired(renewed, Instant::now(), self.settings.lease_duration) {
+                    self.set_members(BTreeSet::new());
+                }
+                continue;
+            }
+            renewed = Some(Instant::now());
+            let params = ListParams::default()
+                .labels(&format!("{}={}", LABEL_SHARD_GROUP, self.settings.group));
+            match api.list(&params).await {
+                Ok(leases) => self.set_members(live_members(&leases.items, Utc::now())),
+                Err(e) => tracing::warn!("failed to list shard leases: {}", e),
+            }
+        }
+    }
+
+    async fn renew(&self, api: &Api<Lease>) -> Result<Lease, kube::Error> {
+        let settings = &self.settings;
+        let name = format!("{}-{}", settings.group, settings.identity);
+        let lease = Lease {
+            metadata: ObjectMeta {
+                name: Some(name.clone()),
+                labels: Some(BTreeMap::from([(
+                    LABEL_SHARD_GROUP.to_owned(),
+                    settings.group.clone(),
+                )])),
+                ..Default::default()
+            },
+            spec: Some(LeaseSpec {
+                holder_identity: Some(settings.identity.clone()),
+                lease_duration_seconds: Some(settings.lease_duration.as_secs() as i32),
+                renew_time: Some(MicroTime(Utc::now())),
+                ..Default::default()
+            }),
+        };
+        let lease = api
+            .patch(
+                &name,
+                &PatchParams::apply(FIELD_MANAGER).force(),
+                &Patch::Apply(&lease),
+            )
+            .await?;
+        tracing::debug!("renewed shard lease {}", lease.name_any());
+        Ok(lease)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeSet,
+        time::{Duration, Instant},
+    };
+
+    use chrono::{TimeZone, Utc};
+    use k8s_openapi::{
+        api::coordination::v1::{Lease, LeaseSpec},
+        apimachinery::pkg::apis::meta::v1::MicroTime,
+    };
+
+    use super::{lease_expired, live_members, owner, Shard, ShardSettings};
+
+    fn members(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_owner_is_stable_and_balanced() {
+        let three = members(&["a", "b", "c"]);
+        let keys: Vec<String> = (0..300).map(|i| format!("default/table-{}", i)).collect();
+        let mut counts = std::collections::BTreeMap::new();
+        for key in keys.iter() {
+            *counts.entry(owner(&three, key).unwrap()).or_insert(0) += 1;
+        }
+        assert_eq!(3, counts.len());
+        assert!(counts.values().all(|count| *count > 60), "{:?}", counts);
+
+        // Only the tables of the member that left move
+        let two = members(&["a", "c"]);
+        for key in keys.iter() {
+            let before = owner(&three, key).unwrap();
+            if before != "b" {
+                assert_eq!(before, owner(&two, key).unwrap());
+            }
+        }
+        assert_eq!(None, owner(&members(&[]), "default/table"));
+    }
+
+    #[test]
+    fn test_live_members() {
+        let lease = |holder: &str, renewed: i64| Lease {
+            spec: Some(LeaseSpec {
+                holder_identity: Some(holder.into()),
+                lease_duration_seconds: Some(30),
+                renew_time: Some(MicroTime(Utc.timestamp_opt(renewed, 0).unwrap())),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let now = Utc.timestamp_opt(100, 0).unwrap();
+        let leases = vec![lease("a", 90), lease("b", 60), lease("c", 71)];
+        assert_eq!(members(&["a", "c"]), live_members(&leases, now));
+    }
+
+    #[test]
+    fn test_lease_expired() {
+        let now = Instant::now();
+        let duration = Duration::from_secs(30);
+        assert!(lease_expired(None, now, duration));
+        assert!(!lease_expired(
+            Some(now),
+            now + Duration::from_secs(29),
+            duration
+        ));
+        assert!(lease_expired(Some(now), now + duration, duration));
+    }
+
+    #[test]
+    fn test_forget_deleted_tables() {
+        let shard = Shard::new(ShardSettings {
+            group: "tables".into(),
+            identity: "a".into(),
+            namespace: "delta-operator-system".into(),
+            lease_duration: Duration::from_secs(30),
+        });
+        *shard.members.write().unwrap() = members(&["a"]);
+        assert!(shard.owns("data", "clowns"));
+        assert!(shard.owns("data", "jugglers"));
+        assert!(shard.owns("other", "clowns"));
+
+        shard.forget("data", "jugglers");
+        shard.forget_deleted(|namespace, name| namespace == "other" && name == "clowns");
+        assert_eq!(members(&["data/clowns"]), *shard.owned.lock().unwrap());
+    }
+}
@@ -1,30 +1,68 @@
-use delta_operator_crd::maintenance::JobSettings;
-use std::sync::Arc;
+use delta_operator_crd::maintenance::{JobExecutor, JobSettings, MaintenanceExecutor};
+use std::{sync::Arc, time::Duration};
 use tokio::sync::RwLock;
 
 use kube::{runtime::watcher::Config, Client};
 
-use super::{Context, Diagnostics};
+use super::{
+    diagnostics::Diagnostics,
+    executor::{ExecutorKind, InProcessExecutor},
+    namespaces::WatchScope,
+    pause::PauseToggle,
+    scheduler::{Scheduler, SchedulerLimits},
+    shard::{Shard, ShardSettings},
+    Context,
+};
+
+/// How long a reconcile may run before the controller is reported as not live
+pub const DEFAULT_LIVENESS_TIMEOUT: Duration = Duration::from_secs(15 * 60);
 
 pub struct State {
     /// Diagnostics populated by the reconciler
     diagnostics: Arc<RwLock<Diagnostics>>,
     job_settings: Arc<JobSettings>,
     label_selector: Option<String>,
+    watch_scope: WatchScope,
+    executor: ExecutorKind,
+    max_concurrent_maintenance: usize,
+    scheduler_limits: SchedulerLimits,
+    scheduler_interval: Duration,
+    /// Namespace and name of the ConfigMap holding the pause selector
+    pause_config_map: Option<(String, String)>,
+    pause: PauseToggle,
+    shard: Option<Shard>,
+    liveness_timeout: Duration,
 }
 
 impl State {
-    pub fn new(settings: JobSettings, label_selector: Option<String>) -> Self {
+    pub fn new(
+        settings: JobSettings,
+        label_selector: Option<String>,
+        executor: ExecutorKind,
+        max_concurrent_maintenance: usize,
+        scheduler_limits: SchedulerLimits,
+        scheduler_interval: Duration,
+        pause_config_map: Option<(String, String)>,
+    ) -> Self {
         Self {
             diagnostics: Arc::new(RwLock::new(Diagnostics::default())),
             job_settings: Arc::new(settings),
             label_selector,
+            watch_scope: WatchScope::All,
+            executor,
+            max_concurrent_maintenance,
+            scheduler_limits,
+            scheduler_interval,
+            pause_config_map,
+            pause: PauseToggle::default(),
+            shard: None,
+            liveness_timeout: DEFAULT_LIVENESS_TIMEOUT,
         }
     }
 
-    pub(crate) fn to_context_and_config(&self, client: Client) -> (Arc<Context>, Config) {
+    pub(crate) async fn to_context_and_config(&self, client: Client) -> (Arc<Context>, Config) {
         (
-            self.to_context(client),
+            self.to_context(client).await,
             Config {
                 label_selector: self.label_selector.clone(),
                 ..Default::default()
@@ -32,12 +70,80 @@ impl State {
         )
     }
 
-    fn to_context(&self, client: Client) -> Arc<Context> {
+    async fn to_context(&self, client: Client) -> Arc<Context> {
+        let executor: Arc<dyn MaintenanceExecutor> = match self.executor {
+            ExecutorKind::Job => {
+                Arc::new(JobExecutor::new(client.clone(), self.job_settings.clone()))
+            }
+            ExecutorKind::InProcess => Arc::new(InProcessExecutor::new(
+                client.clone(),
+                self.diagnostics.read().await.reporter.clone(),
+                self.max_concurrent_maintenance,
+            )),
+        };
+        let limits = match self.executor {
+            ExecutorKind::Job => self.scheduler_limits.clone(),
+            ExecutorKind::InProcess => self
+                .scheduler_limits
+                .clone()
+                .with_capacity(self.max_concurrent_maintenance.max(1)),
+        };
+        let executor = Scheduler::start(
+            executor,
+            client.clone(),
+            limits,
+            self.job_settings.clone(),
+            self.pause.clone(),
+            self.scheduler_interval,
+            self.diagnostics.clone(),
+        );
         Arc::new(Context {
             client,
             // metrics: Metrics::default().register(&self.registry).unwrap(),
             diagnostics: self.diagnostics.clone(),
             settings: self.job_settings.clone(),
+            executor,
+            pause: self.pause.clone(),
+            shard: self.shard.clone(),
         })
     }
+
+    /// Watches the tables of `scope` instead of every namespace
+    pub fn with_watch_scope(mut self, scope: WatchScope) -> Self {
+        self.watch_scope = scope;
+        self
+    }
+
+    /// Reconciles only the tables assigned to this replica within the shard group
+    pub fn with_shard(mut self, settings: ShardSettings) -> Self {
+        self.shard = Some(Shard::new(settings));
+        self
+    }
+
+    /// Fails `/livez` when a reconcile runs for longer than `timeout`
+    pub fn with_liveness_timeout(mut self, timeout: Duration) -> Self {
+        self.liveness_timeout = timeout;
+        self
+    }
+
+    pub(crate) fn diagnostics(&self) -> Arc<RwLock<Diagnostics>> {
+        self.diagnostics.clone()
+    }
+
+    pub(crate) fn liveness_timeout(&self) -> Duration {
+        self.liveness_timeout
+    }
+
+    pub(crate) fn watch_scope(&self) -> &WatchScope {
+        &self.watch_scope
+    }
+
+    pub(crate) fn executor(&self) -> &ExecutorKind {
+        &self.executor
+    }
+
+    /// Namespace and name of the pause toggle's ConfigMap, if one is configured
+    pub(crate) fn pause_config_map(&self) -> Option<&(String, String)> {
+        self.pause_config_map.as_ref()
+    }
 }
@@ -0,0 +1,170 @@
+//! Serves the `DeltaTable` conversion webhook
+use std::{fs::File, io::BufReader, net::SocketAddr, path::Path, sync::Arc};
+
+use axum::{routing::post, Json, Router};
+use delta_operator_crd::conversion::{convert, WEBHOOK_PATH};
+use hyper::server::conn::Http;
+use kube::core::{
+    conversion::{ConversionRequest, ConversionResponse, ConversionReview},
+    Status,
+};
+use tokio::net::TcpListener;
+use tokio_rustls::{
+    rustls::{Certificate, PrivateKey, ServerConfig},
+    TlsAcceptor,
+};
+
+use crate::error::Error;
+
+/// Serves the webhook over TLS, which the API server requires, with the certificate
+/// mounted into the controller
+pub(crate) async fn start_webhook(address: &str, cert: &Path, key: &Path) -> Result<(), Error> {
+    let addr: SocketAddr = address
+        .parse()
+        .map_err(|e: std::net::AddrParseError| Error::Server(e.to_string()))?;
+    let acceptor = TlsAcceptor::from(Arc::new(server_config(cert, key)?));
+    let listener = TcpListener::bind(addr)
+        .await
+        .map_err(|e| Error::Server(e.to_string()))?;
+    let app = Router::new().route(WEBHOOK_PATH, post(handle_review));
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                tracing::warn!("failed to accept webhook connection: {}", e);
+                continue;
+            }
+        };
+        let acceptor = acceptor.clone();
+        let app = app.clone();
+        tokio::spawn(async move {
+            let stream = match acceptor.accept(stream).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    tracing::warn!("TLS handshake with {} failed: {}", peer, e);
+                    return;
+                }
+            };
+            if let Err(e) = Http::new().serve_connection(stream, app).await {
+                tracing::warn!("webhook connection with {} failed: {}", peer, e);
+            }
+        });
+    }
+}
+
+fn server_config(cert: &Path, key: &Path) -> Result<ServerConfig, Error> {
+    let read_error = |path: &Path, e: std::io::Error| {
+        Error::Server(format!("failed to read {}: {}", path.display(), e))
+    };
+    let mut reader = BufReader::new(File::open(cert).map_err(|e| read_error(cert, e))?);
+    let certs = rustls_pemfile::certs(&mut reader)
+        .map_err(|e| read_error(cert, e))?
+        .into_iter()
+        .map(Certificate)
+        .collect();
+    let mut reader = BufReader::new(File::open(key).map_err(|e| read_error(key, e))?);
+    let key = rustls_pemfile::read_all(&mut reader)
+        .map_err(|e| read_error(key, e))?
+        .into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::ECKey(key) => Some(PrivateKey(key)),
+            _ => None,
+        })
+        .ok_or_else(|| Error::Server(format!("no private key in {}", key.display())))?;
+    ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| Error::Server(e.to_string()))
+}
+
+async fn handle_review(Json(review): Json<ConversionReview>) -> Json<ConversionReview> {
+    Json(review_conversion(review))
+}
+
+/// Converts every object of the review, failing the whole review if one cannot be
+/// converted, as the API server expects
+fn review_conversion(review: ConversionReview) -> ConversionReview {
+    let request = match ConversionRequest::from_review(review) {
+        Ok(request) => request,
+        Err(e) => {
+            return ConversionResponse::invalid(Status::failure(&e.to_string(), "InvalidRequest"))
+                .into_review()
+        }
+    };
+    let desired_api_version = request.desired_api_version.clone();
+    let objects = request.objects.clone();
+    let response = ConversionResponse::for_request(request);
+    let converted: Result<Vec<_>, String> = objects
+        .into_iter()
+        .map(|object| convert(object, &desired_api_version))
+        .collect();
+    match converted {
+        Ok(objects) => response.success(objects),
+        Err(e) => {
+            tracing::warn!("conversion to {} failed: {}", desired_api_version, e);
+            response.failure(Status::failure(&e, "ConversionFailed"))
+        }
+    }
+    .into_review()
+}
+
+#[cfg(test)]
+mod tests {
+    use kube::core::conversion::ConversionReview;
+    use serde_json::json;
+
+    use super::review_conversion;
+
+    fn review(objects: serde_json::Value) -> ConversionReview {
+        serde_json::from_value(json!({
+            "apiVersion": "apiextensions.k8s.io/v1",
+            "kind": "ConversionReview",
+            "request": {
+                "uid": "705ab4f5-6393-11e8-b7cc-42010a800002",
+                "desiredAPIVersion": "delta-operator.rs/v1beta1",
+                "objects": objects
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_review_conversion() {
+        let response = review_conversion(review(json!([{
+            "apiVersion": "delta-operator.rs/v1alpha1",
+            "kind": "DeltaTable",
+            "metadata": {"name": "clowns"},
+            "spec": {
+                "name": "clowns",
+                "table_uri": "memory://",
+                "schema_settings": {"value": ""},
+                "partition_columns": [],
+                "maintenance_priority": 1
+            }
+        }])))
+        .response
+        .unwrap();
+        assert_eq!("705ab4f5-6393-11e8-b7cc-42010a800002", response.uid);
+        assert!(response.result.is_success());
+        assert_eq!(
+            json!("delta-operator.rs/v1beta1"),
+            response.converted_objects[0]["apiVersion"]
+        );
+        assert_eq!(
+            json!(1),
+            response.converted_objects[0]["spec"]["maintenance"]["priority"]
+        );
+    }
+
+    #[test]
+    fn test_review_conversion_failure() {
+        let response = review_conversion(review(json!([{"kind": "DeltaTable"}])))
+            .response
+            .unwrap();
+        assert!(response.result.is_failure());
+        assert!(response.converted_objects.is_empty());
+    }
+}
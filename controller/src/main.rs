@@ -2,7 +2,10 @@ use std::collections::BTreeMap;
 
 use app::Arguments;
 use clap::Parser;
+use controller::scheduler::SchedulerLimits;
+use delta_operator_crd::jobs::JobLifecycle;
 use delta_operator_crd::maintenance::JobSettings;
+use delta_operator_crd::policy::{MaintenanceDefaults, PolicyDefaults};
 use k8s_openapi::{
     api::core::v1::ResourceRequirements, apimachinery::pkg::api::resource::Quantity,
 };
@@ -12,6 +15,17 @@ mod error;
 #[tokio::main]
 async fn main() {
     delta_operator_crd::initialize_handlers();
+    let arguments = app::Arguments::parse();
+    if let Err(e) = arguments.validate() {
+        e.exit();
+    }
+    if let Err(e) =
+        delta_operator_crd::telemetry::init("delta-operator", arguments.otlp_endpoint.as_deref())
+    {
+        // Tables are still reconciled without traces, logging spans to stdout instead
+        delta_operator_crd::telemetry::init("delta-operator", None).ok();
+        tracing::warn!("failed to install the OTLP exporter: {}", e);
+    }
     let Arguments {
         listen_address,
         image,
@@ -20,24 +34,121 @@ async fn main() {
         worker_service_account,
         worker_max_cpu,
         worker_max_ram,
+        worker_pushgateway_url,
+        worker_backoff_limit,
+        worker_active_deadline,
+        worker_ttl_after_finished,
+        worker_successful_jobs_history_limit,
+        worker_failed_jobs_history_limit,
         worker_namespace,
         label_selector,
+        watch_namespaces,
+        namespace_selector,
         resync_interval,
-    } = app::Arguments::parse();
+        default_checkpoint_interval,
+        default_checkpoint_commit_interval,
+        default_optimize_interval,
+        default_optimize_commit_interval,
+        default_vacuum_interval,
+        default_vacuum_commit_interval,
+        executor,
+        max_concurrent_maintenance,
+        maintenance_limit,
+        maintenance_limit_per_namespace,
+        maintenance_limit_per_action,
+        maintenance_jitter,
+        maintenance_queue_interval,
+        maintenance_order,
+        dry_run,
+        pause_config_map,
+        pause_config_map_namespace,
+        shard_group,
+        shard_identity,
+        shard_lease_namespace,
+        shard_lease_duration,
+        liveness_timeout,
+        otlp_endpoint,
+        webhook_listen_address,
+        webhook_cert,
+        webhook_key,
+    } = arguments;
     let settings = JobSettings {
-        image,
+        image: image.unwrap_or_default(),
         labels: vec_to_map(worker_labels),
         annotations: vec_to_map(worker_annotations),
-        service_account: worker_service_account,
+        service_account: worker_service_account.unwrap_or_default(),
         resource_requirements: make_resource_requirements(worker_max_cpu, worker_max_ram),
         namespace: worker_namespace,
         resync_interval: Some(resync_interval),
+        maintenance_order,
+        dry_run,
+        pushgateway_url: worker_pushgateway_url,
+        otlp_endpoint: otlp_endpoint.clone(),
+        job_lifecycle: JobLifecycle {
+            backoff_limit: Some(worker_backoff_limit),
+            active_deadline: Some(worker_active_deadline)
+                .filter(|d| !d.is_zero())
+                .map(Into::into),
+            ttl_after_finished: Some(worker_ttl_after_finished)
+                .filter(|d| !d.is_zero())
+                .map(Into::into),
+            successful_jobs_history_limit: Some(worker_successful_jobs_history_limit),
+            failed_jobs_history_limit: Some(worker_failed_jobs_history_limit),
+        },
+        maintenance_defaults: MaintenanceDefaults {
+            checkpoint: PolicyDefaults {
+                time_interval: default_checkpoint_interval,
+                commit_interval: default_checkpoint_commit_interval,
+            },
+            optimize: PolicyDefaults {
+                time_interval: default_optimize_interval,
+                commit_interval: default_optimize_commit_interval,
+            },
+            vacuum: PolicyDefaults {
+                time_interval: default_vacuum_interval,
+                commit_interval: default_vacuum_commit_interval,
+            },
+        },
     };
 
-    let state = controller::state::State::new(settings, label_selector);
-    controller::start_controller(state, listen_address)
+    let mut state = controller::state::State::new(
+        settings,
+        label_selector,
+        executor,
+        max_concurrent_maintenance,
+        SchedulerLimits {
+            global: maintenance_limit,
+            per_namespace: maintenance_limit_per_namespace,
+            per_action: maintenance_limit_per_action,
+            jitter: maintenance_jitter,
+        },
+        maintenance_queue_interval,
+        pause_config_map.map(|name| (pause_config_map_namespace, name)),
+    )
+    .with_watch_scope(controller::namespaces::WatchScope::new(
+        watch_namespaces,
+        namespace_selector,
+    ))
+    .with_liveness_timeout(liveness_timeout);
+    if let Some((group, identity)) = shard_group.zip(shard_identity) {
+        state = state.with_shard(controller::shard::ShardSettings {
+            group,
+            identity,
+            namespace: shard_lease_namespace,
+            lease_duration: shard_lease_duration,
+        });
+    }
+    let webhook = webhook_cert
+        .zip(webhook_key)
+        .map(|(cert, key)| controller::WebhookSettings {
+            listen_address: webhook_listen_address,
+            cert,
+            key,
+        });
+    controller::start_controller(state, listen_address, webhook)
         .await
         .unwrap();
+    delta_operator_crd::telemetry::shutdown();
 }
 
 fn vec_to_map(args: Vec<(String, String)>) -> BTreeMap<String, String> {
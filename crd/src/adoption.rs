@@ -0,0 +1,213 @@
+//! Checks that an existing table matches the spec before it is adopted
+use deltalake::kernel::Schema;
+
+use crate::{DeltaLakeTable, DeltaTableSpec, DeltaTableStatus, TableCreation};
+
+/// Checks that an existing table has the name, partition columns and schema of `spec`
+///
+/// Tables without a name are accepted, as are schemas the operator would evolve into the
+/// spec's when `schema_settings.manage` is set.
+pub(crate) fn verify_adoption(spec: &DeltaTableSpec, table: &DeltaLakeTable) -> Result<(), String> {
+    let metadata = table.metadata().map_err(|e| e.to_string())?;
+    let schema = table.schema().ok_or("table has no schema")?;
+    let expected = spec.schema_settings.schema().map_err(|e| e.to_string())?;
+    verify_table_metadata(
+        spec,
+        metadata.name.as_deref(),
+        &metadata.partition_columns,
+        schema,
+        &expected,
+    )
+}
+
+/// How the table at `uri` came to be managed according to `status`, or `None` when it
+/// was not created or adopted there
+pub(crate) fn recorded_creation(status: &DeltaTableStatus, uri: &str) -> Option<TableCreation> {
+    let managed = match status.creation_uri.as_deref() {
+        Some(creation_uri) => creation_uri == uri,
+        // Managed before the creation or its URI was recorded
+        None => !status.table_uri.is_empty() && status.table_uri == uri,
+    };
+    managed.then(|| status.creation.clone().unwrap_or(TableCreation::Adopted))
+}
+
+fn verify_table_metadata(
+    spec: &DeltaTableSpec,
+    name: Option<&str>,
+    partition_columns: &[String],
+    schema: &Schema,
+    expected: &Schema,
+) -> Result<(), String> {
+    if let Some(name) = name {
+        if name != spec.name {
+            return Err(format!("name is `{}`, expected `{}`", name, spec.name));
+        }
+    }
+    if partition_columns != spec.partition_columns.as_slice() {
+        return Err(format!(
+            "partition columns are {:?}, expected {:?}",
+            partition_columns, spec.partition_columns
+        ));
+    }
+
+    let manage = spec.schema_settings.manage.unwrap_or_default();
+    for field in schema.fields.iter() {
+        match expected.fields.iter().find(|f| f.name == field.name) {
+            Some(f) if f.data_type == field.data_type && f.nullable == field.nullable => {}
+            Some(_) => return Err(format!("column `{}` differs from the spec", field.name)),
+            None => return Err(format!("column `{}` is not in the spec", field.name)),
+        }
+    }
+    if !manage {
+        if let Some(f) = expected
+            .fields
+            .iter()
+            .find(|f| !schema.fields.iter().any(|field| field.name == f.name))
+        {
+            return Err(format!("column `{}` is missing from the table", f.name));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use deltalake::kernel::Schema;
+    use serde_json::json;
+
+    use super::{recorded_creation, verify_table_metadata};
+    use crate::{DeltaTableSpec, DeltaTableStatus, SchemaSettings, TableCreation};
+
+    #[test]
+    fn test_verify_matching_table() {
+        let spec = DeltaTableSpec {
+            name: "clowns".into(),
+            partition_columns: vec!["day".into()],
+            schema_settings: SchemaSettings {
+                value: String::new(),
+                manage: Some(false),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let expected: Schema = serde_json::from_value(json!({"type": "struct", "fields": [
+            {"name": "id", "type": "long", "nullable": true, "metadata": {}},
+            {"name": "day", "type": "string", "nullable": true, "metadata": {}}
+        ]}))
+        .unwrap();
+        let table: Schema = serde_json::from_value(json!({"type": "struct", "fields": [
+            {"name": "day", "type": "string", "nullable": true, "metadata": {}},
+            {"name": "id", "type": "long", "nullable": true, "metadata": {}}
+        ]}))
+        .unwrap();
+        let partitions = vec!["day".to_owned()];
+        assert!(
+            verify_table_metadata(&spec, Some("clowns"), &partitions, &table, &expected).is_ok()
+        );
+        assert!(verify_table_metadata(&spec, None, &partitions, &table, &expected).is_ok());
+    }
+
+    #[test]
+    fn test_verify_name_and_partitions() {
+        let spec = DeltaTableSpec {
+            name: "clowns".into(),
+            partition_columns: vec!["day".into()],
+            schema_settings: SchemaSettings {
+                value: String::new(),
+                manage: Some(false),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let expected: Schema = serde_json::from_value(json!({"type": "struct", "fields": [
+            {"name": "id", "type": "long", "nullable": true, "metadata": {}},
+            {"name": "day", "type": "string", "nullable": true, "metadata": {}}
+        ]}))
+        .unwrap();
+        let partitions = vec!["day".to_owned()];
+        assert!(
+            verify_table_metadata(&spec, Some("jugglers"), &partitions, &expected, &expected)
+                .is_err()
+        );
+        assert!(verify_table_metadata(&spec, None, &[], &expected, &expected).is_err());
+    }
+
+    #[test]
+    fn test_verify_schema() {
+        let spec = DeltaTableSpec {
+            name: "clowns".into(),
+            partition_columns: vec!["day".into()],
+            schema_settings: SchemaSettings {
+                value: String::new(),
+                manage: Some(false),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let managed = DeltaTableSpec {
+            name: "clowns".into(),
+            partition_columns: vec!["day".into()],
+            schema_settings: SchemaSettings {
+                value: String::new(),
+                manage: Some(true),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let expected: Schema = serde_json::from_value(json!({"type": "struct", "fields": [
+            {"name": "id", "type": "long", "nullable": true, "metadata": {}},
+            {"name": "day", "type": "string", "nullable": true, "metadata": {}}
+        ]}))
+        .unwrap();
+        let partitions = vec!["day".to_owned()];
+        let retyped: Schema = serde_json::from_value(json!({"type": "struct", "fields": [
+            {"name": "id", "type": "string", "nullable": true, "metadata": {}},
+            {"name": "day", "type": "string", "nullable": true, "metadata": {}}
+        ]}))
+        .unwrap();
+        assert!(verify_table_metadata(&managed, None, &partitions, &retyped, &expected).is_err());
+        let extra: Schema = serde_json::from_value(json!({"type": "struct", "fields": [
+            {"name": "id", "type": "long", "nullable": true, "metadata": {}},
+            {"name": "day", "type": "string", "nullable": true, "metadata": {}},
+            {"name": "name", "type": "string", "nullable": true, "metadata": {}}
+        ]}))
+        .unwrap();
+        assert!(verify_table_metadata(&managed, None, &partitions, &extra, &expected).is_err());
+
+        // Columns missing from the table are only accepted when the schema is managed
+        let missing: Schema = serde_json::from_value(json!({"type": "struct", "fields": [
+            {"name": "day", "type": "string", "nullable": true, "metadata": {}}
+        ]}))
+        .unwrap();
+        assert!(verify_table_metadata(&spec, None, &partitions, &missing, &expected).is_err());
+        assert!(verify_table_metadata(&managed, None, &partitions, &missing, &expected).is_ok());
+    }
+
+    #[test]
+    fn test_recorded_creation() {
+        let uri = "s3://tables/clowns";
+        let created = DeltaTableStatus {
+            creation: Some(TableCreation::Created),
+            creation_uri: Some(uri.into()),
+            table_uri: uri.into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            Some(TableCreation::Created),
+            recorded_creation(&created, uri)
+        );
+        // The table must be adopted again at a new URI
+        assert_eq!(None, recorded_creation(&created, "s3://tables/jugglers"));
+
+        let legacy = DeltaTableStatus {
+            table_uri: uri.into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            Some(TableCreation::Adopted),
+            recorded_creation(&legacy, uri)
+        );
+        assert_eq!(None, recorded_creation(&legacy, "s3://tables/jugglers"));
+        assert_eq!(None, recorded_creation(&DeltaTableStatus::default(), uri));
+    }
+}
@@ -0,0 +1,358 @@
+//! Column comments, metadata and generation expressions declared in
+//! [`SchemaSettings`](crate::SchemaSettings)
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use deltalake::{
+    arrow::datatypes::{DataType as ArrowDataType, Schema as ArrowSchema},
+    kernel::{Action, ColumnMetadataKey, MetadataValue, Schema, StructField},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{commit::commit, expression, DeltaLakeTable, Error};
+
+/// Metadata key holding the comment of a column
+pub const COMMENT_KEY: &str = "comment";
+/// Name of the operation recorded in the commit info of a schema update
+pub const OPERATION_UPDATE_SCHEMA: &str = "UPDATE SCHEMA";
+
+/// Settings for a top level column of the schema
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq, Default)]
+pub struct ColumnSettings {
+    /// Name of the column in `schema_settings.value`
+    pub name: String,
+    pub comment: Option<String>,
+    /// Custom metadata, such as PII tags or owners
+    pub metadata: Option<BTreeMap<String, String>>,
+    /// SQL expression the column is generated from, e.g. `CAST(timestamp AS DATE)`
+    pub generation_expression: Option<String>,
+}
+
+fn generation_expression(field: &StructField) -> Option<&str> {
+    match field.get_config_value(&ColumnMetadataKey::GenerationExpression) {
+        Some(MetadataValue::String(expression)) => Some(expression),
+        _ => None,
+    }
+}
+
+/// Whether any column of `schema` is generated
+pub fn has_generated_columns(schema: &Schema) -> bool {
+    schema
+        .fields
+        .iter()
+        .any(|field| generation_expression(field).is_some())
+}
+
+/// Writes `columns` into the metadata of the fields of `schema`, where Delta keeps them,
+/// and validates the generation expressions of the result
+pub fn apply(schema: &mut Schema, columns: &[ColumnSettings]) -> Result<(), String> {
+    let mut seen = HashSet::new();
+    for column in columns {
+        if !seen.insert(column.name.as_str()) {
+            return Err(format!("column `{}` is configured twice", column.name));
+        }
+        let field = schema
+            .fields
+            .iter_mut()
+            .find(|f| f.name == column.name)
+            .ok_or_else(|| format!("column `{}` is not in the schema", column.name))?;
+        for (key, value) in column.metadata.iter().flatten() {
+            // `delta.` keys belong to the protocol and are set by writers
+            if key.starts_with("delta.") || key == COMMENT_KEY {
+                return Err(format!(
+                    "metadata key `{}` of column `{}` is reserved",
+                    key, column.name
+                ));
+            }
+            field
+                .metadata
+                .insert(key.clone(), MetadataValue::String(value.clone()));
+        }
+        if let Some(comment) = &column.comment {
+            field.metadata.insert(
+                COMMENT_KEY.to_owned(),
+                MetadataValue::String(comment.clone()),
+            );
+        }
+        if let Some(expression) = &column.generation_expression {
+            field.metadata.insert(
+                ColumnMetadataKey::GenerationExpression.as_ref().to_owned(),
+                MetadataValue::String(expression.clone()),
+            );
+        }
+    }
+    validate_generation_expressions(schema)
+}
+
+/// Checks that generation expressions refer only to columns that are not generated and
+/// produce exactly the column's type, casting where needed
+fn validate_generation_expressions(schema: &Schema) -> Result<(), String> {
+    if !has_generated_columns(schema) {
+        return Ok(());
+    }
+    let arrow_schema = ArrowSchema::try_from(schema).map_err(|e| e.to_string())?;
+    for field in schema.fields.iter() {
+        let expression = match generation_expression(field) {
+            Some(expression) => expression,
+            None => continue,
+        };
+        let invalid = |reason: String| {
+            format!(
+                "generation expression of column `{}` is invalid: {}",
+                field.name, reason
+            )
+        };
+        let (expr, data_type) = expression::parse(&arrow_schema, expression).map_err(invalid)?;
+        for column in expr.to_columns().map_err(|e| invalid(e.to_string()))? {
+            let generated = schema
+                .fields
+                .iter()
+                .find(|f| f.name == column.name)
+                .is_some_and(|f| generation_expression(f).is_some());
+            if generated {
+                return Err(invalid(format!(
+                    "refers to generated column `{}`",
+                    column.name
+                )));
+            }
+        }
+        let expected = ArrowDataType::try_from(&field.data_type).map_err(|e| e.to_string())?;
+        if data_type != expected {
+            return Err(invalid(format!(
+                "produces {}, expected {}",
+                data_type, expected
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// The schema `current` evolves into to match `expected`
+///
+/// Columns missing from `current` are added. Existing columns take the metadata keys the
+/// spec declares and keep the others, such as column mapping ids or tags set by other
+/// tools. Columns are never dropped, and neither their types nor their generation
+/// expressions change.
+pub fn merge(current: &Schema, expected: &Schema) -> Result<Schema, String> {
+    let mut fields = Vec::with_capacity(expected.fields.len());
+    for field in current.fields.iter() {
+        let spec = match expected.fields.iter().find(|f| f.name == field.name) {
+            Some(spec) => spec,
+            None => {
+                fields.push(field.clone());
+                continue;
+            }
+        };
+        if spec.data_type != field.data_type || spec.nullable != field.nullable {
+            return Err(format!(
+                "column `{}` cannot change its type or nullability",
+                field.name
+            ));
+        }
+        // Existing rows were written without the new expression
+        if generation_expression(spec) != generation_expression(field) {
+            return Err(format!(
+                "column `{}` cannot change its generation expression",
+                field.name
+            ));
+        }
+        let mut metadata = field.metadata.clone();
+        metadata.extend(spec.metadata.clone());
+        fields.push(StructField {
+            metadata,
+            ..field.clone()
+        });
+    }
+    for spec in expected.fields.iter() {
+        if !current.fields.iter().any(|f| f.name == spec.name) {
+            fields.push(spec.clone());
+        }
+    }
+    Ok(Schema::new(fields))
+}
+
+/// Commits `schema` as the schema of `table` and reloads it
+pub async fn set_schema(table: &mut DeltaLakeTable, schema: &Schema) -> Result<(), Error> {
+    let mut metadata = table.metadata()?.clone();
+    metadata.schema_string = serde_json::to_string(schema)?;
+    commit(
+        table,
+        vec![serde_json::to_value(Action::Metadata(metadata))?],
+        OPERATION_UPDATE_SCHEMA,
+        HashMap::new(),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use deltalake::kernel::{
+        ColumnMetadataKey, DataType, MetadataValue, PrimitiveType, Schema, StructField,
+    };
+
+    use super::{apply, has_generated_columns, merge, ColumnSettings, COMMENT_KEY};
+
+    fn schema() -> Schema {
+        Schema::new(vec![
+            StructField::new("id", DataType::Primitive(PrimitiveType::Long), false),
+            StructField::new(
+                "timestamp",
+                DataType::Primitive(PrimitiveType::Timestamp),
+                true,
+            ),
+            StructField::new("date", DataType::Primitive(PrimitiveType::Date), true),
+        ])
+    }
+
+    fn column(name: &str) -> ColumnSettings {
+        ColumnSettings {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    fn string(value: &str) -> MetadataValue {
+        MetadataValue::String(value.into())
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut schema = schema();
+        apply(
+            &mut schema,
+            &[
+                ColumnSettings {
+                    comment: Some("Unique id".into()),
+                    metadata: Some(BTreeMap::from([("pii".into(), "false".into())])),
+                    ..column("id")
+                },
+                ColumnSettings {
+                    generation_expression: Some("CAST(timestamp AS DATE)".into()),
+                    ..column("date")
+                },
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            Some(&string("Unique id")),
+            schema.fields[0].metadata.get(COMMENT_KEY)
+        );
+        assert_eq!(Some(&string("false")), schema.fields[0].metadata.get("pii"));
+        assert_eq!(
+            Some(&string("CAST(timestamp AS DATE)")),
+            schema.fields[2]
+                .metadata
+                .get(ColumnMetadataKey::GenerationExpression.as_ref())
+        );
+        assert!(has_generated_columns(&schema));
+    }
+
+    #[test]
+    fn test_apply_invalid() {
+        let invalid = [
+            vec![column("missing")],
+            vec![column("id"), column("id")],
+            vec![ColumnSettings {
+                metadata: Some(BTreeMap::from([("delta.invariants".into(), "".into())])),
+                ..column("id")
+            }],
+            vec![ColumnSettings {
+                generation_expression: Some("CAST(other AS DATE)".into()),
+                ..column("date")
+            }],
+            vec![ColumnSettings {
+                generation_expression: Some("timestamp > 0 AND".into()),
+                ..column("date")
+            }],
+            vec![ColumnSettings {
+                generation_expression: Some("CAST(date AS DATE)".into()),
+                ..column("date")
+            }],
+            vec![ColumnSettings {
+                generation_expression: Some("timestamp".into()),
+                ..column("id")
+            }],
+        ];
+        for columns in invalid {
+            assert!(apply(&mut schema(), &columns).is_err(), "{:?}", columns);
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut current = schema();
+        current.fields[0].metadata.extend([
+            (
+                "delta.columnMapping.id".to_owned(),
+                MetadataValue::Number(1),
+            ),
+            (COMMENT_KEY.to_owned(), string("old")),
+            ("team".to_owned(), string("data")),
+        ]);
+        let mut expected = schema();
+        expected.fields[0]
+            .metadata
+            .insert(COMMENT_KEY.to_owned(), string("new"));
+        expected
+            .fields
+            .push(StructField::new("owner", DataType::STRING, true));
+        current.fields.remove(1);
+
+        let merged = merge(&current, &expected).unwrap();
+        assert_eq!(
+            vec!["id", "date", "timestamp", "owner"],
+            merged
+                .fields
+                .iter()
+                .map(|f| f.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            Some(&string("new")),
+            merged.fields[0].metadata.get(COMMENT_KEY)
+        );
+        assert_eq!(
+            Some(&MetadataValue::Number(1)),
+            merged.fields[0].metadata.get("delta.columnMapping.id")
+        );
+        assert_eq!(Some(&string("data")), merged.fields[0].metadata.get("team"));
+        assert_eq!(merged, merge(&merged, &expected).unwrap());
+    }
+
+    #[test]
+    fn test_merge_generation_expression() {
+        let generated = |schema: &mut Schema, index: usize, expression: &str| {
+            schema.fields[index].metadata.insert(
+                ColumnMetadataKey::GenerationExpression.as_ref().to_owned(),
+                string(expression),
+            );
+        };
+        let mut added = schema();
+        added.fields.push(StructField::new(
+            "day",
+            DataType::Primitive(PrimitiveType::Date),
+            true,
+        ));
+        generated(&mut added, 3, "CAST(timestamp AS DATE)");
+        let merged = merge(&schema(), &added).unwrap();
+        assert!(has_generated_columns(&merged));
+        assert_eq!(merged, merge(&merged, &added).unwrap());
+
+        let mut changed = added.clone();
+        generated(&mut changed, 3, "CAST(timestamp AS DATE) + 1");
+        assert!(merge(&merged, &changed).is_err());
+        let mut set = schema();
+        generated(&mut set, 2, "CAST(timestamp AS DATE)");
+        assert!(merge(&schema(), &set).is_err());
+        assert!(merge(&set, &schema()).is_err());
+    }
+
+    #[test]
+    fn test_merge_type_change() {
+        let mut expected = schema();
+        expected.fields[0].nullable = true;
+        assert!(merge(&schema(), &expected).is_err());
+    }
+}
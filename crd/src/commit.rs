@@ -0,0 +1,57 @@
+//! Commits metadata-only actions to a table's log
+use std::collections::HashMap;
+
+use deltalake::{
+    kernel::{Action, CommitInfo},
+    DeltaTableError, Path,
+};
+
+use crate::{DeltaLakeTable, Error};
+
+/// Commits `actions` as the next version of `table` and reloads it
+///
+/// delta-rs 0.17 has no operation for changing table properties or the protocol, so the
+/// commit is written through the table's log store, which detects concurrent commits of
+/// the same version. Actions are JSON so that callers can work around actions delta-rs
+/// does not serialize as the protocol specifies.
+pub(crate) async fn commit(
+    table: &mut DeltaLakeTable,
+    mut actions: Vec<serde_json::Value>,
+    operation: &str,
+    parameters: HashMap<String, serde_json::Value>,
+) -> Result<(), Error> {
+    actions.push(serde_json::to_value(Action::CommitInfo(CommitInfo {
+        timestamp: Some(chrono::Utc::now().timestamp_millis()),
+        operation: Some(operation.into()),
+        operation_parameters: Some(parameters),
+        read_version: Some(table.version()),
+        ..Default::default()
+    }))?);
+    let entry = actions
+        .iter()
+        .map(serde_json::to_string)
+        .collect::<Result<Vec<_>, _>>()?
+        .join("\n");
+
+    let version = table.version() + 1;
+    let tmp_commit = Path::from_iter([
+        "_delta_log",
+        &format!(
+            "_commit_{}_{}.json.tmp",
+            version,
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ),
+    ]);
+    let log_store = table.log_store();
+    log_store
+        .object_store()
+        .put(&tmp_commit, entry.into())
+        .await
+        .map_err(DeltaTableError::from)?;
+    log_store
+        .write_commit_entry(version, &tmp_commit)
+        .await
+        .map_err(DeltaTableError::from)?;
+    table.update().await?;
+    Ok(())
+}
@@ -0,0 +1,219 @@
+//! Follows the commits of tables with [`CommitWatch`] set in their spec
+use kube::core::Duration;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{DeltaLakeTable, Error};
+
+/// Interval at which the log is polled when the spec does not set one
+pub const DEFAULT_WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+/// Most commits reported per poll; older ones are skipped when more were added
+pub const MAX_REPORTED_COMMITS: i64 = 20;
+/// Most commits read to tell whether a table changed only its data; the table is loaded
+/// when more were added
+pub const MAX_DATA_COMMITS: i64 = 100;
+
+/// Polls the table for new commits and reports each one
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq, Default)]
+pub struct CommitWatch {
+    /// How often the log is polled. Defaults to 30s.
+    pub interval: Option<Duration>,
+    /// URL that receives a JSON `POST` for every new commit
+    pub callback_url: Option<String>,
+}
+
+impl CommitWatch {
+    pub fn interval(&self) -> std::time::Duration {
+        self.interval
+            .map(std::time::Duration::from)
+            .unwrap_or(DEFAULT_WATCH_INTERVAL)
+    }
+}
+
+/// The commit info of a version
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Commit {
+    pub version: i64,
+    /// Seconds since the unix epoch
+    pub timestamp: Option<i64>,
+    pub operation: Option<String>,
+}
+
+impl Commit {
+    /// Reads the `commitInfo` action of a commit entry
+    fn parse(version: i64, entry: &[u8]) -> Self {
+        let info = entry
+            .split(|b| *b == b'\n')
+            .filter_map(|line| serde_json::from_slice::<serde_json::Value>(line).ok())
+            .find_map(|action| action.get("commitInfo").cloned())
+            .unwrap_or_default();
+        Self {
+            version,
+            timestamp: info["timestamp"].as_i64().map(|ms| ms / 1000),
+            operation: info["operation"].as_str().map(str::to_owned),
+        }
+    }
+}
+
+/// Whether a commit entry has a `metaData` or `protocol` action
+fn changes_metadata(entry: &[u8]) -> bool {
+    entry
+        .split(|b| *b == b'\n')
+        .filter_map(|line| serde_json::from_slice::<serde_json::Value>(line).ok())
+        .any(|action| action.get("metaData").is_some() || action.get("protocol").is_some())
+}
+
+/// Latest version of the table without loading it, from `_last_checkpoint` and the
+/// names of the newest log files
+pub async fn latest_version(table: &DeltaLakeTable) -> Result<i64, Error> {
+    Ok(table.log_store().get_latest_version(0).await?)
+}
+
+/// The commits after `previous` up to `latest`, limited to the newest
+/// [`MAX_REPORTED_COMMITS`]
+pub async fn read_commits(
+    table: &DeltaLakeTable,
+    previous: i64,
+    latest: i64,
+) -> Result<Vec<Commit>, Error> {
+    let log_store = table.log_store();
+    let mut commits = Vec::new();
+    for version in (previous + 1).max(latest - MAX_REPORTED_COMMITS + 1)..=latest {
+        // Commits may be gone after log cleanup; they are skipped
+        if let Some(entry) = log_store.read_commit_entry(version).await? {
+            commits.push(Commit::parse(version, &entry));
+        }
+    }
+    Ok(commits)
+}
+
+/// Whether the commits after `previous` up to `latest` leave the metadata and protocol
+/// of the table as they were. Commits that are gone after log cleanup, or more than
+/// [`MAX_DATA_COMMITS`] of them, count as metadata changes.
+pub async fn only_data_changed(
+    table: &DeltaLakeTable,
+    previous: i64,
+    latest: i64,
+) -> Result<bool, Error> {
+    if latest - previous > MAX_DATA_COMMITS {
+        return Ok(false);
+    }
+    let log_store = table.log_store();
+    for version in previous + 1..=latest {
+        match log_store.read_commit_entry(version).await? {
+            Some(entry) if !changes_metadata(&entry) => {}
+            _ => return Ok(false),
+        }
+    }
+    Ok(true)
+}
+
+/// Body of the callback request for a commit
+#[derive(Serialize, Debug)]
+pub struct CommitNotification<'a> {
+    pub namespace: &'a str,
+    pub name: &'a str,
+    pub table_uri: &'a str,
+    #[serde(flatten)]
+    pub commit: &'a Commit,
+}
+
+/// Sends `notification` to `url`
+pub async fn notify(url: &str, notification: &CommitNotification<'_>) -> Result<(), String> {
+    reqwest::Client::new()
+        .post(url)
+        .timeout(std::time::Duration::from_secs(10))
+        .json(notification)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        changes_metadata, Commit, CommitNotification, CommitWatch, DEFAULT_WATCH_INTERVAL,
+    };
+
+    #[test]
+    fn test_parse_commit() {
+        let entry = concat!(
+            r#"{"add":{"path":"part-0.parquet","size":1}}"#,
+            "\n",
+            r#"{"commitInfo":{"timestamp":1704067200123,"operation":"WRITE"}}"#,
+            "\n"
+        );
+        assert_eq!(
+            Commit {
+                version: 4,
+                timestamp: Some(1_704_067_200),
+                operation: Some("WRITE".into())
+            },
+            Commit::parse(4, entry.as_bytes())
+        );
+        assert_eq!(
+            Commit {
+                version: 5,
+                timestamp: None,
+                operation: None
+            },
+            Commit::parse(5, br#"{"add":{}}"#)
+        );
+    }
+
+    #[test]
+    fn test_notification() {
+        let commit = Commit {
+            version: 4,
+            timestamp: Some(1),
+            operation: Some("WRITE".into()),
+        };
+        let notification = CommitNotification {
+            namespace: "default",
+            name: "clowns",
+            table_uri: "s3://tests/clowns",
+            commit: &commit,
+        };
+        assert_eq!(
+            serde_json::json!({
+                "namespace": "default",
+                "name": "clowns",
+                "table_uri": "s3://tests/clowns",
+                "version": 4,
+                "timestamp": 1,
+                "operation": "WRITE"
+            }),
+            serde_json::to_value(notification).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_watch_interval() {
+        assert_eq!(DEFAULT_WATCH_INTERVAL, CommitWatch::default().interval());
+        let watch = CommitWatch {
+            interval: Some("5s".parse().unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(5, watch.interval().as_secs());
+    }
+
+    #[test]
+    fn test_changes_metadata() {
+        let write = concat!(
+            r#"{"add":{"path":"part-0.parquet","size":1}}"#,
+            "\n",
+            r#"{"commitInfo":{"operation":"WRITE"}}"#,
+        );
+        assert!(!changes_metadata(write.as_bytes()));
+        let set_properties = concat!(
+            r#"{"metaData":{"id":"clowns","configuration":{}}}"#,
+            "\n",
+            r#"{"commitInfo":{"operation":"SET TBLPROPERTIES"}}"#,
+        );
+        assert!(changes_metadata(set_properties.as_bytes()));
+        let upgrade = r#"{"protocol":{"minReaderVersion":1,"minWriterVersion":4}}"#;
+        assert!(changes_metadata(upgrade.as_bytes()));
+    }
+}
@@ -0,0 +1,222 @@
+//! Manages Delta CHECK constraints listed in a [`DeltaTable`](crate::DeltaTable) spec
+use std::collections::{BTreeMap, HashMap};
+
+use deltalake::{arrow::datatypes::DataType, DeltaOps};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{commit::commit, expression, DeltaLakeTable, Error};
+
+/// Prefix of the configuration keys holding constraints
+pub const CONSTRAINT_PREFIX: &str = "delta.constraints.";
+/// Name of the operation recorded in the commit info when a constraint is dropped
+pub const OPERATION_DROP_CONSTRAINT: &str = "DROP CONSTRAINT";
+
+/// A CHECK constraint every row of the table must satisfy
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq)]
+pub struct TableConstraint {
+    pub name: String,
+    /// SQL boolean expression, e.g. `amount >= 0`
+    pub expression: String,
+}
+
+/// Constraints to drop and add to bring a table in line with the spec. Only constraints
+/// the operator applied are dropped; those added by other writers are left alone.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConstraintPlan {
+    pub drop: Vec<String>,
+    pub add: Vec<TableConstraint>,
+}
+
+impl ConstraintPlan {
+    /// Compares the `desired` constraints with those the operator `applied` before and
+    /// the constraints `existing` on the table, by name
+    ///
+    /// Constraints are compared with the expression recorded when they were applied,
+    /// since the table holds a normalized form. A changed constraint is dropped and added.
+    pub fn new(
+        desired: &[TableConstraint],
+        applied: &[TableConstraint],
+        existing: &BTreeMap<String, String>,
+    ) -> Self {
+        let mut plan = Self::default();
+        for constraint in applied {
+            if existing.contains_key(&constraint.name)
+                && !desired.iter().any(|c| c.name == constraint.name)
+            {
+                plan.drop.push(constraint.name.clone());
+            }
+        }
+        for constraint in desired {
+            let exists = existing.contains_key(&constraint.name);
+            if exists && applied.contains(constraint) {
+                continue;
+            }
+            if exists {
+                plan.drop.push(constraint.name.clone());
+            }
+            plan.add.push(constraint.clone());
+        }
+        plan
+    }
+}
+
+/// The constraints on `table` by name
+pub fn existing(table: &DeltaLakeTable) -> Result<BTreeMap<String, String>, Error> {
+    Ok(table
+        .metadata()?
+        .configuration
+        .iter()
+        .filter_map(|(key, value)| {
+            let name = key.strip_prefix(CONSTRAINT_PREFIX)?;
+            Some((name.to_owned(), value.clone().unwrap_or_default()))
+        })
+        .collect())
+}
+
+/// Checks that `expression` is a boolean expression over the columns of `table`
+pub fn validate(table: &DeltaLakeTable, expression: &str) -> Result<(), String> {
+    let schema = table
+        .snapshot()
+        .and_then(|snapshot| snapshot.arrow_schema())
+        .map_err(|e| e.to_string())?;
+    match expression::parse(&schema, expression)?.1 {
+        DataType::Boolean => Ok(()),
+        data_type => Err(format!("expression is {}, not boolean", data_type)),
+    }
+}
+
+/// Why a constraint could not be added
+#[derive(Clone, Debug, PartialEq)]
+pub enum AddFailure {
+    /// Existing rows violate the constraint
+    Violated(String),
+}
+
+/// Adds `constraint` to `table`, checking existing data
+///
+/// Returns [`AddFailure::Violated`] rather than an error when existing rows do not
+/// satisfy the constraint, so the caller can report it without retrying.
+pub async fn add(
+    table: &mut DeltaLakeTable,
+    constraint: &TableConstraint,
+) -> Result<Result<(), AddFailure>, Error> {
+    match DeltaOps(table.clone())
+        .add_constraint()
+        .with_constraint(constraint.name.clone(), constraint.expression.clone())
+        .await
+    {
+        Ok(updated) => {
+            *table = updated;
+            Ok(Ok(()))
+        }
+        Err(deltalake::DeltaTableError::InvalidData { violations }) => Ok(Err(
+            AddFailure::Violated(violations.first().cloned().unwrap_or_default()),
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Removes the constraint `name` from `table`
+pub async fn drop(table: &mut DeltaLakeTable, name: &str) -> Result<(), Error> {
+    let mut metadata = table.metadata()?.clone();
+    metadata
+        .configuration
+        .remove(&format!("{}{}", CONSTRAINT_PREFIX, name));
+    commit(
+        table,
+        vec![serde_json::to_value(deltalake::kernel::Action::Metadata(
+            metadata,
+        ))?],
+        OPERATION_DROP_CONSTRAINT,
+        HashMap::from([("name".to_owned(), json!(name))]),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use deltalake::{kernel::Schema, DeltaOps};
+
+    use super::{validate, ConstraintPlan, TableConstraint};
+
+    fn constraint(name: &str, expression: &str) -> TableConstraint {
+        TableConstraint {
+            name: name.into(),
+            expression: expression.into(),
+        }
+    }
+
+    #[test]
+    fn test_plan_add_and_keep() {
+        let positive = vec![constraint("positive", "amount >= 0")];
+        let plan = ConstraintPlan::new(&positive, &[], &BTreeMap::new());
+        assert_eq!(positive, plan.add);
+        assert!(plan.drop.is_empty());
+
+        let plan = ConstraintPlan::new(
+            &positive,
+            &positive,
+            &BTreeMap::from([("positive".to_owned(), "amount >= 0".to_owned())]),
+        );
+        assert_eq!(ConstraintPlan::default(), plan);
+    }
+
+    #[test]
+    fn test_plan_replace() {
+        let plan = ConstraintPlan::new(
+            &[constraint("positive", "amount > 0")],
+            &[constraint("positive", "amount >= 0")],
+            &BTreeMap::from([("positive".to_owned(), "amount >= 0".to_owned())]),
+        );
+        assert_eq!(vec!["positive".to_owned()], plan.drop);
+        assert_eq!(vec![constraint("positive", "amount > 0")], plan.add);
+    }
+
+    #[test]
+    fn test_plan_drop_only_applied() {
+        let plan = ConstraintPlan::new(
+            &[],
+            &[constraint("positive", "amount >= 0")],
+            &BTreeMap::from([
+                ("positive".to_owned(), "amount >= 0".to_owned()),
+                ("foreign".to_owned(), "id > 0".to_owned()),
+            ]),
+        );
+        assert_eq!(vec!["positive".to_owned()], plan.drop);
+        assert!(plan.add.is_empty());
+    }
+
+    #[test]
+    fn test_validate() {
+        let schema: Schema = serde_json::from_value(serde_json::json!({
+            "type": "struct",
+            "fields": [
+                {"name": "amount", "type": "long", "nullable": true, "metadata": {}},
+                {"name": "currency", "type": "string", "nullable": true, "metadata": {}},
+            ]
+        }))
+        .unwrap();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let table = runtime
+            .block_on(async {
+                DeltaOps::new_in_memory()
+                    .create()
+                    .with_columns(schema.fields)
+                    .await
+            })
+            .unwrap();
+
+        assert!(validate(&table, "amount >= 0").is_ok());
+        assert!(validate(&table, "amount >= 0 AND currency IN ('EUR', 'USD')").is_ok());
+        assert!(validate(&table, "amount + 1").is_err());
+        assert!(validate(&table, "price >= 0").is_err());
+        assert!(validate(&table, "amount >=").is_err());
+    }
+}
@@ -0,0 +1,694 @@
+//! Converts [`DeltaTable`] objects between the `v1alpha1` and [`v1beta1`] versions
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::{
+    CustomResourceConversion, CustomResourceDefinition, ServiceReference, WebhookClientConfig,
+    WebhookConversion,
+};
+use kube::core::crd::merge_crds;
+use kube::CustomResourceExt;
+use serde_json::{json, Value};
+
+use crate::{
+    columns, commits, failure::MaintenanceFailure, freshness, jobs::JobLifecycle, protocol,
+    v1beta1, DeltaTable, DeltaTableCondition, DeltaTableConfiguration,
+    DeltaTableMaintenceConfiguration, DeltaTableSpec, DeltaTableStatus,
+    DeltaTableVacuumConfiguration, SchemaSettings, TableStatistics,
+};
+
+/// Holds the original text of a `v1alpha1` schema string that is not exactly how its
+/// structured fields serialize, so that it is restored as long as the fields still match
+pub const ANNOTATION_SCHEMA_VALUE: &str = "delta-operator.rs/v1alpha1-schema-value";
+/// Namespace of the service serving the conversion webhook
+pub const WEBHOOK_SERVICE_NAMESPACE: &str = "delta-operator-system";
+/// Name of the service serving the conversion webhook
+pub const WEBHOOK_SERVICE_NAME: &str = "delta-operator";
+/// Path of the conversion webhook
+pub const WEBHOOK_PATH: &str = "/convert";
+/// Port of the service serving the conversion webhook
+pub const WEBHOOK_SERVICE_PORT: i32 = 443;
+/// cert-manager certificate whose CA is injected into the webhook configuration
+const WEBHOOK_CERTIFICATE: &str = "delta-operator-system/delta-operator-webhook";
+
+/// The definition of the `DeltaTable` resource with both versions, storing `v1beta1`
+/// and converting through the controller's webhook
+pub fn custom_resource_definition() -> CustomResourceDefinition {
+    let mut crd = merge_crds(
+        vec![v1beta1::DeltaTable::crd(), DeltaTable::crd()],
+        "v1beta1",
+    )
+    .expect("versions of the same resource");
+    crd.metadata.annotations = Some(
+        [(
+            "cert-manager.io/inject-ca-from".to_owned(),
+            WEBHOOK_CERTIFICATE.to_owned(),
+        )]
+        .into(),
+    );
+    for version in crd.spec.versions.iter_mut() {
+        if version.name == "v1alpha1" {
+            version.deprecated = Some(true);
+            version.deprecation_warning =
+                Some("delta-operator.rs/v1alpha1 DeltaTable is deprecated; use v1beta1".to_owned());
+        }
+    }
+    crd.spec.conversion = Some(CustomResourceConversion {
+        strategy: "Webhook".to_owned(),
+        webhook: Some(WebhookConversion {
+            client_config: Some(WebhookClientConfig {
+                service: Some(ServiceReference {
+                    name: WEBHOOK_SERVICE_NAME.to_owned(),
+                    namespace: WEBHOOK_SERVICE_NAMESPACE.to_owned(),
+                    path: Some(WEBHOOK_PATH.to_owned()),
+                    port: Some(WEBHOOK_SERVICE_PORT),
+                }),
+                ..Default::default()
+            }),
+            conversion_review_versions: vec!["v1".to_owned()],
+        }),
+    });
+    crd
+}
+
+/// Converts `object` to `desired_api_version`, e.g. `delta-operator.rs/v1beta1`
+pub fn convert(object: Value, desired_api_version: &str) -> Result<Value, String> {
+    let api_version = object
+        .get("apiVersion")
+        .and_then(Value::as_str)
+        .ok_or("object has no apiVersion")?
+        .to_owned();
+    if api_version == desired_api_version {
+        return Ok(object);
+    }
+    let alpha = api_version_of::<DeltaTable>();
+    let beta = api_version_of::<v1beta1::DeltaTable>();
+    let converted = if api_version == alpha && desired_api_version == beta {
+        let table: DeltaTable = serde_json::from_value(object).map_err(|e| e.to_string())?;
+        serde_json::to_value(to_v1beta1(table))
+    } else if api_version == beta && desired_api_version == alpha {
+        let table: v1beta1::DeltaTable =
+            serde_json::from_value(object).map_err(|e| e.to_string())?;
+        serde_json::to_value(to_v1alpha1(table))
+    } else {
+        return Err(format!(
+            "cannot convert {} to {}",
+            api_version, desired_api_version
+        ));
+    };
+    converted.map_err(|e| e.to_string())
+}
+
+fn api_version_of<K: kube::Resource<DynamicType = ()>>() -> String {
+    K::api_version(&()).into_owned()
+}
+
+/// Converts a `v1alpha1` object to `v1beta1`
+pub fn to_v1beta1(table: DeltaTable) -> v1beta1::DeltaTable {
+    let DeltaTable {
+        mut metadata,
+        spec,
+        status,
+    } = table;
+    let value = spec.schema_settings.value.clone();
+    let fields = schema_fields(&value);
+    let annotations = metadata.annotations.get_or_insert_with(Default::default);
+    let serialized = fields.as_deref().map(|f| schema_value(f).to_string());
+    if value.is_empty() || serialized.as_ref() == Some(&value) {
+        annotations.remove(ANNOTATION_SCHEMA_VALUE);
+    } else {
+        annotations.insert(ANNOTATION_SCHEMA_VALUE.to_owned(), value);
+    }
+    if annotations.is_empty() {
+        metadata.annotations = None;
+    }
+
+    let mut converted = v1beta1::DeltaTable::new("", spec_to_v1beta1(spec, fields));
+    converted.metadata = metadata;
+    converted.status = status.map(status_to_v1beta1);
+    converted
+}
+
+/// Converts a `v1beta1` object to `v1alpha1`
+pub fn to_v1alpha1(table: v1beta1::DeltaTable) -> DeltaTable {
+    let v1beta1::DeltaTable {
+        mut metadata,
+        spec,
+        status,
+    } = table;
+    let stored = metadata
+        .annotations
+        .as_mut()
+        .and_then(|a| a.remove(ANNOTATION_SCHEMA_VALUE));
+    if metadata.annotations.as_ref().is_some_and(|a| a.is_empty()) {
+        metadata.annotations = None;
+    }
+    // The original text is only used while it still describes the fields
+    let value = match (&spec.schema.fields, stored) {
+        (Some(fields), Some(stored))
+            if serde_json::from_str::<Value>(&stored).ok() == Some(schema_value(fields)) =>
+        {
+            stored
+        }
+        (Some(fields), _) => schema_value(fields).to_string(),
+        (None, Some(stored)) if schema_fields(&stored).is_none() => stored,
+        (None, _) => String::new(),
+    };
+
+    let mut converted = DeltaTable::new("", spec_to_v1alpha1(spec, value));
+    converted.metadata = metadata;
+    converted.status = status.map(status_to_v1alpha1);
+    converted
+}
+
+/// The fields of a `v1alpha1` schema string, or `None` when the string does not
+/// serialize back to the same schema
+fn schema_fields(value: &str) -> Option<Vec<v1beta1::SchemaField>> {
+    let parsed: Value = serde_json::from_str(value).ok()?;
+    let fields: Vec<v1beta1::SchemaField> =
+        serde_json::from_value(parsed.get("fields")?.clone()).ok()?;
+    (schema_value(&fields) == parsed).then_some(fields)
+}
+
+fn schema_value(fields: &[v1beta1::SchemaField]) -> Value {
+    json!({ "type": "struct", "fields": fields })
+}
+
+fn spec_to_v1beta1(
+    spec: DeltaTableSpec,
+    fields: Option<Vec<v1beta1::SchemaField>>,
+) -> v1beta1::DeltaTableSpec {
+    let maintenance = v1beta1::MaintenanceSettings {
+        priority: spec.maintenance_priority,
+        checkpoint: spec.checkpoint_configuration.map(action_to_v1beta1),
+        optimize: spec.optimize_configuration.map(action_to_v1beta1),
+        vacuum: spec.vacuum_configuration.map(|c| v1beta1::VacuumSettings {
+            action: v1beta1::MaintenanceActionSettings {
+                time_interval: c.time_interval,
+                commit_interval: c.commit_interval,
+                disable: c.disable,
+                criteria: c.criteria,
+                job: c.job.map(job_to_v1beta1),
+            },
+            retention_period: c.retention_period,
+        }),
+    };
+    let has_maintenance = maintenance.priority.is_some()
+        || maintenance.checkpoint.is_some()
+        || maintenance.optimize.is_some()
+        || maintenance.vacuum.is_some();
+    v1beta1::DeltaTableSpec {
+        name: spec.name,
+        table_uri: spec.table_uri,
+        allow_http: spec.allow_http,
+        schema: v1beta1::TableSchema {
+            manage: spec.schema_settings.manage,
+            fields,
+            columns: spec.schema_settings.columns.map(|columns| {
+                columns
+                    .into_iter()
+                    .map(|c| v1beta1::ColumnSettings {
+                        name: c.name,
+                        comment: c.comment,
+                        metadata: c.metadata,
+                        generation_expression: c.generation_expression,
+                    })
+                    .collect()
+            }),
+        },
+        partition_columns: spec.partition_columns,
+        maintenance: has_maintenance.then_some(maintenance),
+        storage_options: spec.storage_options,
+        storage_options_from: spec.storage_options_from,
+        configuration: spec.configuration.map(|c| v1beta1::TableConfiguration {
+            enable_change_feed: c.enable_change_feed,
+            append_only: c.append_only,
+            log_retention_duration: c.log_retention_duration,
+            deleted_file_retention_duration: c.deleted_file_retention_duration,
+            checkpoint_interval: c.checkpoint_interval,
+            data_skipping_num_indexed_cols: c.data_skipping_num_indexed_cols,
+            data_skipping_stats_columns: c.data_skipping_stats_columns,
+            target_file_size: c.target_file_size,
+            tune_file_sizes_for_rewrites: c.tune_file_sizes_for_rewrites,
+            isolation_level: c.isolation_level,
+            column_mapping_mode: c.column_mapping_mode,
+            additional_settings: c.additional_settings,
+        }),
+        constraints: spec.constraints,
+        protocol: spec.protocol.map(|p| v1beta1::TableProtocol {
+            min_reader_version: p.min_reader_version,
+            min_writer_version: p.min_writer_version,
+            features: p.features,
+            allow_upgrade: p.allow_upgrade,
+            readers: p.readers.map(|readers| {
+                readers
+                    .into_iter()
+                    .map(|r| v1beta1::Reader {
+                        name: r.name,
+                        reader_version: r.reader_version,
+                        reader_features: r.reader_features,
+                    })
+                    .collect()
+            }),
+        }),
+        creation_policy: spec.creation_policy,
+        suspend: spec.suspend,
+        statistics: spec.statistics,
+        watch_commits: spec.watch_commits.map(|w| v1beta1::CommitWatch {
+            interval: w.interval,
+            callback_url: w.callback_url,
+        }),
+        freshness: spec.freshness.map(|f| v1beta1::Freshness {
+            max_age: f.max_age,
+            severity: f.severity,
+        }),
+    }
+}
+
+fn action_to_v1beta1(c: DeltaTableMaintenceConfiguration) -> v1beta1::MaintenanceActionSettings {
+    v1beta1::MaintenanceActionSettings {
+        time_interval: c.time_interval,
+        commit_interval: c.commit_interval,
+        disable: c.disable,
+        criteria: c.criteria,
+        job: c.job.map(job_to_v1beta1),
+    }
+}
+
+fn job_to_v1beta1(j: JobLifecycle) -> v1beta1::JobLifecycle {
+    v1beta1::JobLifecycle {
+        backoff_limit: j.backoff_limit,
+        active_deadline: j.active_deadline,
+        ttl_after_finished: j.ttl_after_finished,
+        successful_jobs_history_limit: j.successful_jobs_history_limit,
+        failed_jobs_history_limit: j.failed_jobs_history_limit,
+    }
+}
+
+fn spec_to_v1alpha1(spec: v1beta1::DeltaTableSpec, value: String) -> DeltaTableSpec {
+    let maintenance = spec.maintenance.unwrap_or_default();
+    DeltaTableSpec {
+        name: spec.name,
+        table_uri: spec.table_uri,
+        allow_http: spec.allow_http,
+        schema_settings: SchemaSettings {
+            value,
+            manage: spec.schema.manage,
+            columns: spec.schema.columns.map(|columns| {
+                columns
+                    .into_iter()
+                    .map(|c| columns::ColumnSettings {
+                        name: c.name,
+                        comment: c.comment,
+                        metadata: c.metadata,
+                        generation_expression: c.generation_expression,
+                    })
+                    .collect()
+            }),
+        },
+        partition_columns: spec.partition_columns,
+        checkpoint_configuration: maintenance.checkpoint.map(action_to_v1alpha1),
+        optimize_configuration: maintenance.optimize.map(action_to_v1alpha1),
+        vacuum_configuration: maintenance.vacuum.map(|c| DeltaTableVacuumConfiguration {
+            time_interval: c.action.time_interval,
+            commit_interval: c.action.commit_interval,
+            disable: c.action.disable,
+            retention_period: c.retention_period,
+            criteria: c.action.criteria,
+            job: c.action.job.map(job_to_v1alpha1),
+        }),
+        storage_options: spec.storage_options,
+        storage_options_from: spec.storage_options_from,
+        configuration: spec.configuration.map(|c| DeltaTableConfiguration {
+            enable_change_feed: c.enable_change_feed,
+            append_only: c.append_only,
+            log_retention_duration: c.log_retention_duration,
+            deleted_file_retention_duration: c.deleted_file_retention_duration,
+            checkpoint_interval: c.checkpoint_interval,
+            data_skipping_num_indexed_cols: c.data_skipping_num_indexed_cols,
+            data_skipping_stats_columns: c.data_skipping_stats_columns,
+            target_file_size: c.target_file_size,
+            tune_file_sizes_for_rewrites: c.tune_file_sizes_for_rewrites,
+            isolation_level: c.isolation_level,
+            column_mapping_mode: c.column_mapping_mode,
+            additional_settings: c.additional_settings,
+        }),
+        constraints: spec.constraints,
+        protocol: spec.protocol.map(|p| protocol::DeltaTableProtocol {
+            min_reader_version: p.min_reader_version,
+            min_writer_version: p.min_writer_version,
+            features: p.features,
+            allow_upgrade: p.allow_upgrade,
+            readers: p.readers.map(|readers| {
+                readers
+                    .into_iter()
+                    .map(|r| protocol::DeltaReader {
+                        name: r.name,
+                        reader_version: r.reader_version,
+                        reader_features: r.reader_features,
+                    })
+                    .collect()
+            }),
+        }),
+        creation_policy: spec.creation_policy,
+        suspend: spec.suspend,
+        statistics: spec.statistics,
+        maintenance_priority: maintenance.priority,
+        watch_commits: spec.watch_commits.map(|w| commits::CommitWatch {
+            interval: w.interval,
+            callback_url: w.callback_url,
+        }),
+        freshness: spec.freshness.map(|f| freshness::Freshness {
+            max_age: f.max_age,
+            severity: f.severity,
+        }),
+    }
+}
+
+fn action_to_v1alpha1(c: v1beta1::MaintenanceActionSettings) -> DeltaTableMaintenceConfiguration {
+    DeltaTableMaintenceConfiguration {
+        time_interval: c.time_interval,
+        commit_interval: c.commit_interval,
+        disable: c.disable,
+        criteria: c.criteria,
+        job: c.job.map(job_to_v1alpha1),
+    }
+}
+
+fn job_to_v1alpha1(j: v1beta1::JobLifecycle) -> JobLifecycle {
+    JobLifecycle {
+        backoff_limit: j.backoff_limit,
+        active_deadline: j.active_deadline,
+        ttl_after_finished: j.ttl_after_finished,
+        successful_jobs_history_limit: j.successful_jobs_history_limit,
+        failed_jobs_history_limit: j.failed_jobs_history_limit,
+    }
+}
+
+fn status_to_v1beta1(status: DeltaTableStatus) -> v1beta1::DeltaTableStatus {
+    v1beta1::DeltaTableStatus {
+        table_uri: status.table_uri,
+        schema: status.schema,
+        last_checkpoint_commit: status.last_checkpoint_commit,
+        last_checkpoint_timestamp: status.last_checkpoint_timestamp,
+        last_vacuum_commit: status.last_vacuum_commit,
+        last_vacuum_timestamp: status.last_vacuum_timestamp,
+        last_optimize_commit: status.last_optimize_commit,
+        last_optimize_timestamp: status.last_optimize_timestamp,
+        version: status.version,
+        is_healthy: status.is_healthy,
+        maintenance_queue: status.maintenance_queue,
+        last_run_request: status.last_run_request,
+        suspended: status.suspended,
+        creation: status.creation,
+        creation_uri: status.creation_uri,
+        conditions: status.conditions.map(|conditions| {
+            conditions
+                .into_iter()
+                .map(|c| v1beta1::Condition {
+                    type_: c.type_,
+                    status: c.status,
+                    reason: c.reason,
+                    message: c.message,
+                    last_transition_time: c.last_transition_time,
+                })
+                .collect()
+        }),
+        protocol: status.protocol.map(|p| v1beta1::ProtocolStatus {
+            min_reader_version: p.min_reader_version,
+            min_writer_version: p.min_writer_version,
+            reader_features: p.reader_features,
+            writer_features: p.writer_features,
+        }),
+        constraints: status.constraints,
+        constraint_violations: status.constraint_violations,
+        last_optimize_time: status.last_optimize_time,
+        last_vacuum_time: status.last_vacuum_time,
+        statistics: status.statistics.map(|s| v1beta1::Statistics {
+            num_files: s.num_files,
+            size_bytes: s.size_bytes,
+            num_records: s.num_records,
+            timestamp: s.timestamp,
+        }),
+        observed_generation: status.observed_generation,
+        last_commit_timestamp: status.last_commit_timestamp,
+        last_operation: status.last_operation,
+        last_failure: status.last_failure.map(|f| v1beta1::Failure {
+            action: f.action,
+            reason: f.reason,
+            message: f.message,
+            exit_code: f.exit_code,
+            time: f.time,
+            pod: f.pod,
+        }),
+    }
+}
+
+fn status_to_v1alpha1(status: v1beta1::DeltaTableStatus) -> DeltaTableStatus {
+    DeltaTableStatus {
+        table_uri: status.table_uri,
+        schema: status.schema,
+        last_checkpoint_commit: status.last_checkpoint_commit,
+        last_checkpoint_timestamp: status.last_checkpoint_timestamp,
+        last_vacuum_commit: status.last_vacuum_commit,
+        last_vacuum_timestamp: status.last_vacuum_timestamp,
+        last_optimize_commit: status.last_optimize_commit,
+        last_optimize_timestamp: status.last_optimize_timestamp,
+        version: status.version,
+        is_healthy: status.is_healthy,
+        maintenance_queue: status.maintenance_queue,
+        last_run_request: status.last_run_request,
+        suspended: status.suspended,
+        creation: status.creation,
+        creation_uri: status.creation_uri,
+        conditions: status.conditions.map(|conditions| {
+            conditions
+                .into_iter()
+                .map(|c| DeltaTableCondition {
+                    type_: c.type_,
+                    status: c.status,
+                    reason: c.reason,
+                    message: c.message,
+                    last_transition_time: c.last_transition_time,
+                })
+                .collect()
+        }),
+        protocol: status.protocol.map(|p| protocol::ProtocolStatus {
+            min_reader_version: p.min_reader_version,
+            min_writer_version: p.min_writer_version,
+            reader_features: p.reader_features,
+            writer_features: p.writer_features,
+        }),
+        constraints: status.constraints,
+        constraint_violations: status.constraint_violations,
+        last_optimize_time: status.last_optimize_time,
+        last_vacuum_time: status.last_vacuum_time,
+        statistics: status.statistics.map(|s| TableStatistics {
+            num_files: s.num_files,
+            size_bytes: s.size_bytes,
+            num_records: s.num_records,
+            timestamp: s.timestamp,
+        }),
+        observed_generation: status.observed_generation,
+        last_commit_timestamp: status.last_commit_timestamp,
+        last_operation: status.last_operation,
+        last_failure: status.last_failure.map(|f| MaintenanceFailure {
+            action: f.action,
+            reason: f.reason,
+            message: f.message,
+            exit_code: f.exit_code,
+            time: f.time,
+            pod: f.pod,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::{convert, custom_resource_definition, ANNOTATION_SCHEMA_VALUE};
+
+    const ALPHA: &str = "delta-operator.rs/v1alpha1";
+    const BETA: &str = "delta-operator.rs/v1beta1";
+
+    fn alpha(value: &str) -> Value {
+        json!({
+            "apiVersion": ALPHA,
+            "kind": "DeltaTable",
+            "metadata": {"name": "clowns", "namespace": "default"},
+            "spec": {
+                "name": "clowns",
+                "table_uri": "s3://tables/clowns",
+                "allow_http": null,
+                "schema_settings": {"value": value, "manage": true, "columns": [
+                    {"name": "day", "comment": "Day", "metadata": null,
+                     "generation_expression": "CAST(ts AS DATE)"}
+                ]},
+                "partition_columns": ["day"],
+                "checkpoint_configuration": {"time_interval": "3600s", "commit_interval": 10,
+                    "disable": null, "criteria": "Commit", "job": null},
+                "optimize_configuration": null,
+                "vacuum_configuration": {"time_interval": null, "commit_interval": null,
+                    "disable": true, "retention_period": "604800s", "criteria": null,
+                    "job": {"backoff_limit": 0, "active_deadline": "7200s",
+                        "ttl_after_finished": null, "successful_jobs_history_limit": 5,
+                        "failed_jobs_history_limit": null}},
+                "storage_options": {"aws_region": "us-east-1"},
+                "storage_options_from": [{"kind": "Secret", "name": "s3", "optional": null}],
+                "configuration": {"enable_change_feed": null, "append_only": true,
+                    "log_retention_duration": null, "deleted_file_retention_duration": null,
+                    "checkpoint_interval": 10, "data_skipping_num_indexed_cols": null,
+                    "data_skipping_stats_columns": null, "target_file_size": null,
+                    "tune_file_sizes_for_rewrites": null, "isolation_level": null,
+                    "column_mapping_mode": "name", "additional_settings": {"owner": "data"}},
+                "constraints": [{"name": "positive", "expression": "id > 0"}],
+                "protocol": {"min_reader_version": null, "min_writer_version": 3,
+                    "features": ["checkConstraints"], "allow_upgrade": true,
+                    "readers": [{"name": "spark", "reader_version": 1, "reader_features": null}]},
+                "creation_policy": "AdoptOnly",
+                "suspend": false,
+                "statistics": true,
+                "maintenance_priority": 5,
+                "watch_commits": {"interval": "60s", "callback_url": "http://hooks/tables"},
+                "freshness": {"max_age": "7200s", "severity": "Critical"}
+            },
+            "status": {
+                "table_uri": "s3://tables/clowns", "schema": "{}",
+                "last_checkpoint_commit": 4, "last_checkpoint_timestamp": null,
+                "last_vacuum_commit": null, "last_vacuum_timestamp": null,
+                "last_optimize_commit": null, "last_optimize_timestamp": null,
+                "version": 12, "is_healthy": true, "maintenance_queue": null,
+                "last_run_request": null, "suspended": false, "creation": "Adopted",
+                "creation_uri": "s3://tables/clowns",
+                "conditions": [{"type": "PropertiesApplied", "status": "True",
+                    "reason": "Applied", "message": null,
+                    "last_transition_time": "2024-01-01T00:00:00Z"}],
+                "protocol": {"min_reader_version": 1, "min_writer_version": 3,
+                    "reader_features": null, "writer_features": null},
+                "constraints": [{"name": "positive", "expression": "id > 0"}],
+                "constraint_violations": null,
+                "last_optimize_time": null, "last_vacuum_time": "2024-01-01T00:00:00+00:00",
+                "statistics": {"num_files": 3, "size_bytes": 4096, "num_records": null,
+                    "timestamp": 1704067200},
+                "observed_generation": 2,
+                "last_commit_timestamp": 1704067200, "last_operation": "WRITE",
+                "last_failure": {"action": "Vacuum", "reason": "Storage",
+                    "message": "Failed to vacuum: timeout", "exit_code": 11,
+                    "time": "2024-01-01T00:00:00+00:00", "pod": "clowns-vacuum-abc"}
+            }
+        })
+    }
+
+    const SCHEMA: &str = r#"{"type":"struct","fields":[{"name":"ts","type":"timestamp","nullable":true,"metadata":{}},{"name":"day","type":"date","nullable":false,"metadata":{"comment":"Day"}}]}"#;
+
+    #[test]
+    fn test_convert_to_v1beta1() {
+        let beta = convert(alpha(SCHEMA), BETA).unwrap();
+        assert_eq!(BETA, beta["apiVersion"]);
+        let spec = &beta["spec"];
+        assert_eq!("s3://tables/clowns", spec["tableUri"]);
+        assert_eq!("date", spec["schema"]["fields"][1]["type"]);
+        assert_eq!("Day", spec["schema"]["fields"][1]["metadata"]["comment"]);
+        assert_eq!(
+            "CAST(ts AS DATE)",
+            spec["schema"]["columns"][0]["generationExpression"]
+        );
+        assert_eq!(5, spec["maintenance"]["priority"]);
+        assert_eq!(10, spec["maintenance"]["checkpoint"]["commitInterval"]);
+        assert_eq!("604800s", spec["maintenance"]["vacuum"]["retentionPeriod"]);
+        assert_eq!(true, spec["maintenance"]["vacuum"]["disable"]);
+        assert_eq!(
+            "7200s",
+            spec["maintenance"]["vacuum"]["job"]["activeDeadline"]
+        );
+        assert_eq!(3, spec["protocol"]["minWriterVersion"]);
+        assert_eq!(1, spec["protocol"]["readers"][0]["readerVersion"]);
+        assert_eq!(
+            "2024-01-01T00:00:00Z",
+            beta["status"]["conditions"][0]["lastTransitionTime"]
+        );
+        assert_eq!(3, beta["status"]["statistics"]["numFiles"]);
+        assert_eq!("http://hooks/tables", spec["watchCommits"]["callbackUrl"]);
+        assert_eq!("7200s", spec["freshness"]["maxAge"]);
+        assert_eq!(11, beta["status"]["lastFailure"]["exitCode"]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let formatted = r#"{ "fields": [], "type": "struct" }"#;
+        for value in [SCHEMA, "", "not json", formatted] {
+            let original = alpha(value);
+            let beta = convert(original.clone(), BETA).unwrap();
+            let back = convert(beta.clone(), ALPHA).unwrap();
+            assert_eq!(original, back, "{}", value);
+            assert_eq!(beta, convert(back, BETA).unwrap(), "{}", value);
+        }
+        let beta = convert(alpha(SCHEMA), BETA).unwrap();
+        assert!(beta["metadata"].get("annotations").is_none());
+    }
+
+    #[test]
+    fn test_unstructured_schema_kept_in_annotation() {
+        let beta = convert(alpha("not json"), BETA).unwrap();
+        assert_eq!(Value::Null, beta["spec"]["schema"]["fields"]);
+        assert_eq!(
+            "not json",
+            beta["metadata"]["annotations"][ANNOTATION_SCHEMA_VALUE]
+        );
+        let value = r#"{"type":"struct","fields":[],"extra":1}"#;
+        let beta = convert(alpha(value), BETA).unwrap();
+        assert_eq!(Value::Null, beta["spec"]["schema"]["fields"]);
+        assert_eq!(
+            value,
+            beta["metadata"]["annotations"][ANNOTATION_SCHEMA_VALUE]
+        );
+    }
+
+    #[test]
+    fn test_stale_schema_text_ignored() {
+        let formatted = SCHEMA.replace(',', ", ");
+        let mut beta = convert(alpha(&formatted), BETA).unwrap();
+        assert_eq!(
+            formatted.as_str(),
+            beta["metadata"]["annotations"][ANNOTATION_SCHEMA_VALUE]
+        );
+        beta["spec"]["schema"]["fields"][0]["nullable"] = json!(false);
+        let back = convert(beta, ALPHA).unwrap();
+        let value: Value =
+            serde_json::from_str(back["spec"]["schema_settings"]["value"].as_str().unwrap())
+                .unwrap();
+        assert_eq!(false, value["fields"][0]["nullable"]);
+        assert!(back["metadata"].get("annotations").is_none());
+    }
+
+    #[test]
+    fn test_convert_same_and_unknown_version() {
+        let original = alpha(SCHEMA);
+        assert_eq!(original, convert(original.clone(), ALPHA).unwrap());
+        assert!(convert(original, "delta-operator.rs/v2").is_err());
+        assert!(convert(json!({}), BETA).is_err());
+    }
+
+    #[test]
+    fn test_custom_resource_definition() {
+        let crd = custom_resource_definition();
+        let versions: Vec<(&str, bool)> = crd
+            .spec
+            .versions
+            .iter()
+            .map(|v| (v.name.as_str(), v.storage))
+            .collect();
+        assert_eq!(vec![("v1beta1", true), ("v1alpha1", false)], versions);
+        assert_eq!(
+            "Webhook",
+            crd.spec.conversion.as_ref().unwrap().strategy.as_str()
+        );
+        assert_eq!(Some(vec!["delta".to_owned()]), crd.spec.names.categories);
+    }
+
+    #[test]
+    fn test_manifest_up_to_date() {
+        // Regenerate with `cargo run --bin crdgen > manifests/kubernetes/production/crdv1.deltatable.yaml`
+        let manifest = include_str!("../../manifests/kubernetes/production/crdv1.deltatable.yaml");
+        let generated = serde_yaml::to_string(&custom_resource_definition()).unwrap();
+        assert_eq!(manifest, generated);
+    }
+}
@@ -1,7 +1,7 @@
-use kube::CustomResourceExt;
 fn main() {
     print!(
         "{}",
-        serde_yaml::to_string(&delta_operator_crd::DeltaTable::crd()).unwrap()
+        serde_yaml::to_string(&delta_operator_crd::conversion::custom_resource_definition())
+            .unwrap()
     )
 }
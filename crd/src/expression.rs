@@ -0,0 +1,97 @@
+//! Parses SQL expressions found in a [`DeltaTable`](crate::DeltaTable) spec against a
+//! schema, without a table or session to run them in
+use std::sync::Arc;
+
+use deltalake::{
+    arrow::datatypes::{DataType, Schema as ArrowSchema},
+    datafusion::{
+        common::{config::ConfigOptions, DFSchema, TableReference},
+        execution::context::SessionState,
+        logical_expr::{AggregateUDF, Expr, ExprSchemable, ScalarUDF, TableSource, WindowUDF},
+        prelude::SessionContext,
+        sql::{
+            planner::{ContextProvider, SqlToRel},
+            sqlparser::{dialect::GenericDialect, parser::Parser},
+        },
+    },
+};
+
+/// Resolves functions only; expressions cannot refer to other tables
+struct FunctionProvider {
+    state: SessionState,
+}
+
+impl ContextProvider for FunctionProvider {
+    fn get_table_source(
+        &self,
+        name: TableReference,
+    ) -> deltalake::datafusion::error::Result<Arc<dyn TableSource>> {
+        Err(deltalake::datafusion::error::DataFusionError::Plan(
+            format!("table `{}` cannot be used in an expression", name),
+        ))
+    }
+
+    fn get_function_meta(&self, name: &str) -> Option<Arc<ScalarUDF>> {
+        self.state.scalar_functions().get(name).cloned()
+    }
+
+    fn get_aggregate_meta(&self, name: &str) -> Option<Arc<AggregateUDF>> {
+        self.state.aggregate_functions().get(name).cloned()
+    }
+
+    fn get_window_meta(&self, name: &str) -> Option<Arc<WindowUDF>> {
+        self.state.window_functions().get(name).cloned()
+    }
+
+    fn get_variable_type(&self, _variable_names: &[String]) -> Option<DataType> {
+        None
+    }
+
+    fn options(&self) -> &ConfigOptions {
+        self.state.config_options()
+    }
+}
+
+/// Parses `expression` over the columns of `schema`, returning it with its type
+pub(crate) fn parse(schema: &ArrowSchema, expression: &str) -> Result<(Expr, DataType), String> {
+    let sql = Parser::new(&GenericDialect {})
+        .try_with_sql(expression)
+        .and_then(|mut parser| parser.parse_expr())
+        .map_err(|e| e.to_string())?;
+    let df_schema = DFSchema::try_from(schema.clone()).map_err(|e| e.to_string())?;
+    let provider = FunctionProvider {
+        state: SessionContext::new().state(),
+    };
+    let expr = SqlToRel::new(&provider)
+        .sql_to_expr(sql, &df_schema, &mut Default::default())
+        .map_err(|e| e.to_string())?;
+    let data_type = expr.get_type(&df_schema).map_err(|e| e.to_string())?;
+    Ok((expr, data_type))
+}
+
+#[cfg(test)]
+mod tests {
+    use deltalake::arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+
+    use super::parse;
+
+    #[test]
+    fn test_parse() {
+        let schema = Schema::new(vec![
+            Field::new("amount", DataType::Int64, true),
+            Field::new(
+                "timestamp",
+                DataType::Timestamp(TimeUnit::Microsecond, None),
+                true,
+            ),
+        ]);
+        assert_eq!(DataType::Boolean, parse(&schema, "amount >= 0").unwrap().1);
+        assert_eq!(
+            DataType::Date32,
+            parse(&schema, "CAST(timestamp AS DATE)").unwrap().1
+        );
+        assert!(parse(&schema, "price >= 0").is_err());
+        assert!(parse(&schema, "amount >=").is_err());
+        assert!(parse(&schema, "amount IN (SELECT id FROM other)").is_err());
+    }
+}
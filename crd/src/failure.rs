@@ -0,0 +1,269 @@
+//! Classifies failed maintenance so it can be reported and retried appropriately
+use deltalake::{operations::transaction::TransactionError, DeltaTableError};
+use kube::{
+    api::{Patch, PatchParams},
+    runtime::events::{Event, EventType, Recorder},
+    Api,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{maintenance::Action, DeltaTable, Error};
+
+/// Where Kubernetes reads the termination message of a container by default
+pub const TERMINATION_MESSAGE_PATH: &str = "/dev/termination-log";
+/// Kubernetes truncates termination messages longer than this
+const MAX_TERMINATION_MESSAGE: usize = 4096;
+
+/// The kind of error that failed maintenance
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, JsonSchema, PartialEq, Eq)]
+pub enum FailureClass {
+    /// The Kubernetes API could not be reached or failed; retrying may help
+    Kubernetes,
+    /// The table's storage could not be reached or failed; retrying may help
+    Storage,
+    /// Another writer committed first; retrying should help
+    Conflict,
+    /// The spec, its references or the table prevent the action; retrying will not help
+    Configuration,
+    Unknown,
+}
+
+impl FailureClass {
+    pub fn of(error: &Error) -> Self {
+        match error {
+            Error::Kubernetes {
+                source: kube::Error::Api(response),
+            } if matches!(response.code, 403 | 404 | 422) => FailureClass::Configuration,
+            Error::Kubernetes { .. } => FailureClass::Kubernetes,
+            Error::DeltaLake { source } => Self::of_delta_lake(source),
+            Error::MissingReference { .. }
+            | Error::SchemaJson { .. }
+            | Error::InvalidColumns { .. }
+            | Error::SchemaUpdate { .. }
+            | Error::UnknownProperty { .. }
+            | Error::TableExists { .. }
+            | Error::AdoptionMismatch { .. } => FailureClass::Configuration,
+            Error::Maintenance { source, .. } => Self::of(source),
+            Error::DeltaProtocol { .. } | Error::Arrow { .. } => FailureClass::Unknown,
+        }
+    }
+
+    fn of_delta_lake(error: &DeltaTableError) -> Self {
+        match error {
+            DeltaTableError::ObjectStore { .. } | DeltaTableError::Io { .. } => {
+                FailureClass::Storage
+            }
+            DeltaTableError::VersionAlreadyExists(_) | DeltaTableError::VersionMismatch(..) => {
+                FailureClass::Conflict
+            }
+            DeltaTableError::Transaction { source } => match source {
+                TransactionError::VersionAlreadyExists(_)
+                | TransactionError::CommitConflict(_)
+                | TransactionError::MaxCommitAttempts(_) => FailureClass::Conflict,
+                TransactionError::ObjectStore { .. } => FailureClass::Storage,
+                TransactionError::DeltaTableAppendOnly
+                | TransactionError::UnsupportedReaderFeatures(_)
+                | TransactionError::UnsupportedWriterFeatures(_) => FailureClass::Configuration,
+                _ => FailureClass::Unknown,
+            },
+            DeltaTableError::NotATable(_)
+            | DeltaTableError::InvalidTableLocation(_)
+            | DeltaTableError::MissingFeature { .. } => FailureClass::Configuration,
+            _ => FailureClass::Unknown,
+        }
+    }
+
+    /// Exit code of a worker that failed with this class of error, so the Job can tell
+    /// failures that are worth retrying from those that are not
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            FailureClass::Unknown => 1,
+            FailureClass::Kubernetes => 10,
+            FailureClass::Storage => 11,
+            FailureClass::Conflict => 12,
+            FailureClass::Configuration => 20,
+        }
+    }
+}
+
+/// Failed maintenance, as reported in the status and the termination message
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq)]
+pub struct MaintenanceFailure {
+    /// The action that failed, unset when the table could not be loaded
+    pub action: Option<Action>,
+    pub reason: FailureClass,
+    pub message: String,
+    pub exit_code: i32,
+    /// RFC 3339 time of the failure
+    pub time: String,
+    /// Pod of the worker that failed
+    pub pod: Option<String>,
+}
+
+impl MaintenanceFailure {
+    pub fn new(error: &Error, pod: Option<String>, time: chrono::DateTime<chrono::Utc>) -> Self {
+        let action = match error {
+            Error::Maintenance { action, .. } => Some(action.clone()),
+            _ => None,
+        };
+        let reason = FailureClass::of(error);
+        Self {
+            action,
+            reason,
+            message: error.to_string(),
+            exit_code: reason.exit_code(),
+            time: time.to_rfc3339(),
+            pod,
+        }
+    }
+
+    /// The failure as JSON that fits in a termination message
+    pub fn termination_message(&self) -> String {
+        let mut failure = self.clone();
+        loop {
+            let message = serde_json::to_string(&failure).unwrap_or_default();
+            if message.len() <= MAX_TERMINATION_MESSAGE || failure.message.is_empty() {
+                return message;
+            }
+            let excess = message.len() - MAX_TERMINATION_MESSAGE;
+            let mut end = failure.message.len().saturating_sub(excess);
+            while !failure.message.is_char_boundary(end) {
+                end -= 1;
+            }
+            failure.message.truncate(end);
+        }
+    }
+}
+
+/// Records `failure` in the status of the [`DeltaTable`] named `name` and publishes a
+/// Warning event for it
+pub async fn publish_failure(
+    api: &Api<DeltaTable>,
+    recorder: Option<&Recorder>,
+    name: &str,
+    failure: &MaintenanceFailure,
+) {
+    let patch = json!({ "status": { "last_failure": failure } });
+    if let Err(e) = api
+        .patch_status(name, &PatchParams::default(), &Patch::Merge(patch))
+        .await
+    {
+        tracing::error!("failed to record the failure of {}: {}", name, e);
+    }
+    let event = Event {
+        type_: EventType::Warning,
+        reason: "MaintenanceFailed".into(),
+        note: Some(format!("{:?}: {}", failure.reason, failure.message)),
+        action: failure
+            .action
+            .as_ref()
+            .map_or("Load".into(), |action| action.to_string()),
+        secondary: None,
+    };
+    if let Some(recorder) = recorder {
+        if let Err(e) = recorder.publish(event).await {
+            tracing::error!("failed to record delta table changes: {}", e);
+        }
+    }
+}
+
+/// Clears the failure recorded in the status of the [`DeltaTable`] named `name`
+pub async fn clear_failure(api: &Api<DeltaTable>, name: &str) {
+    let patch = json!({ "status": { "last_failure": null } });
+    if let Err(e) = api
+        .patch_status(name, &PatchParams::default(), &Patch::Merge(patch))
+        .await
+    {
+        tracing::error!("failed to clear the failure of {}: {}", name, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use deltalake::{operations::transaction::TransactionError, DeltaTableError};
+
+    use super::{FailureClass, MaintenanceFailure, MAX_TERMINATION_MESSAGE};
+    use crate::{maintenance::Action, Error, ReferenceKind};
+
+    fn maintenance(source: Error) -> Error {
+        Error::Maintenance {
+            action: Action::Vacuum,
+            source: Box::new(source),
+        }
+    }
+
+    #[test]
+    fn test_classify() {
+        let storage = Error::DeltaLake {
+            source: DeltaTableError::ObjectStore {
+                source: deltalake::ObjectStoreError::NotImplemented,
+            },
+        };
+        assert_eq!(
+            FailureClass::Storage,
+            FailureClass::of(&maintenance(storage))
+        );
+
+        let conflict = Error::DeltaLake {
+            source: DeltaTableError::Transaction {
+                source: TransactionError::MaxCommitAttempts(3),
+            },
+        };
+        assert_eq!(FailureClass::Conflict, FailureClass::of(&conflict));
+
+        let missing = Error::MissingReference {
+            object: "clowns".into(),
+            kind: ReferenceKind::Secret,
+            name: "storage".into(),
+        };
+        assert_eq!(FailureClass::Configuration, FailureClass::of(&missing));
+        let not_a_table = Error::DeltaLake {
+            source: DeltaTableError::NotATable("s3://tables/clowns".into()),
+        };
+        assert_eq!(FailureClass::Configuration, FailureClass::of(&not_a_table));
+
+        let not_found = Error::Kubernetes {
+            source: kube::Error::Api(kube::core::ErrorResponse {
+                status: "Failure".into(),
+                message: "not found".into(),
+                reason: "NotFound".into(),
+                code: 404,
+            }),
+        };
+        assert_eq!(FailureClass::Configuration, FailureClass::of(&not_found));
+        let unavailable = Error::Kubernetes {
+            source: kube::Error::Api(kube::core::ErrorResponse {
+                status: "Failure".into(),
+                message: "unavailable".into(),
+                reason: "ServiceUnavailable".into(),
+                code: 503,
+            }),
+        };
+        assert_eq!(FailureClass::Kubernetes, FailureClass::of(&unavailable));
+    }
+
+    #[test]
+    fn test_failure() {
+        let error = maintenance(Error::SchemaUpdate {
+            reason: "x".repeat(2 * MAX_TERMINATION_MESSAGE),
+        });
+        let failure = MaintenanceFailure::new(
+            &error,
+            Some("worker-abc".into()),
+            Utc.timestamp_opt(1_704_067_200, 0).unwrap(),
+        );
+        assert_eq!(Some(Action::Vacuum), failure.action);
+        assert_eq!(FailureClass::Configuration, failure.reason);
+        assert_eq!(20, failure.exit_code);
+        assert_eq!("2024-01-01T00:00:00+00:00", failure.time);
+
+        let message = failure.termination_message();
+        assert!(message.len() <= MAX_TERMINATION_MESSAGE);
+        let parsed: MaintenanceFailure = serde_json::from_str(&message).unwrap();
+        assert_eq!(failure.reason, parsed.reason);
+        assert!(failure.message.starts_with(&parsed.message));
+    }
+}
@@ -0,0 +1,101 @@
+//! Checks that tables with [`Freshness`] set in their spec receive commits
+use std::time::Duration as StdDuration;
+
+use deltalake::{ObjectStoreError, Path};
+use kube::core::Duration;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{DeltaLakeTable, Error};
+
+/// Gauge of the seconds since the latest commit of a table
+pub const METRIC_SECONDS_SINCE_LAST_COMMIT: &str = "delta_table_seconds_since_last_commit";
+
+/// How old the latest commit of a table may be
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq)]
+pub struct Freshness {
+    pub max_age: Duration,
+    /// Severity reported when the table is stale. Defaults to `Warning`.
+    pub severity: Option<Severity>,
+}
+
+/// How urgent a stale table is, for routing alerts
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq, Default)]
+pub enum Severity {
+    Info,
+    #[default]
+    Warning,
+    Critical,
+}
+
+/// The age of the latest commit compared with the maximum age
+#[derive(Clone, Debug, PartialEq)]
+pub struct FreshnessCheck {
+    /// Seconds since the latest commit
+    pub age: i64,
+    pub fresh: bool,
+    /// Time until the table becomes stale
+    pub remaining: Option<StdDuration>,
+}
+
+impl Freshness {
+    /// Checks a table whose latest commit was at `last_commit` at `now`, both in seconds
+    /// since the unix epoch
+    pub fn check(&self, last_commit: i64, now: i64) -> FreshnessCheck {
+        let age = now.saturating_sub(last_commit).max(0);
+        let max_age = StdDuration::from(self.max_age).as_secs() as i64;
+        FreshnessCheck {
+            age,
+            fresh: age <= max_age,
+            remaining: (age <= max_age).then(|| StdDuration::from_secs((max_age - age) as u64)),
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity.clone().unwrap_or_default()
+    }
+}
+
+/// Seconds since the unix epoch at which `version` was committed
+///
+/// Uses the timestamp of the commit info, or the modification time of the commit file
+/// when the writer did not record one.
+pub async fn commit_timestamp(table: &DeltaLakeTable, version: i64) -> Result<i64, Error> {
+    let commits = crate::commits::read_commits(table, version - 1, version).await?;
+    if let Some(timestamp) = commits.first().and_then(|c| c.timestamp) {
+        return Ok(timestamp);
+    }
+    let path = Path::from_iter(["_delta_log", &format!("{:020}.json", version)]);
+    let meta = table
+        .log_store()
+        .object_store()
+        .head(&path)
+        .await
+        .map_err(|e: ObjectStoreError| Error::DeltaLake { source: e.into() })?;
+    Ok(meta.last_modified.timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Freshness, Severity};
+
+    #[test]
+    fn test_check() {
+        let freshness = Freshness {
+            max_age: "2h".parse().unwrap(),
+            severity: None,
+        };
+        let check = freshness.check(1_000, 1_000 + 3_600);
+        assert!(check.fresh);
+        assert_eq!(3_600, check.age);
+        assert_eq!(3_600, check.remaining.unwrap().as_secs());
+
+        let check = freshness.check(1_000, 1_000 + 7_201);
+        assert!(!check.fresh);
+        assert_eq!(None, check.remaining);
+
+        // Commits from a clock ahead of the controller's count as new
+        assert_eq!(0, freshness.check(2_000, 1_000).age);
+        assert_eq!(Severity::Warning, freshness.severity());
+    }
+}
@@ -0,0 +1,375 @@
+//! Retries, deadlines and retention of worker jobs
+use std::{collections::BTreeMap, time::Duration as StdDuration};
+
+use k8s_openapi::api::batch::v1::Job;
+use kube::{
+    api::{DeleteParams, ListParams, PropagationPolicy},
+    core::Duration,
+    Api, Client, ResourceExt,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    maintenance::{ANNOTATION_TABLE_NAME, ANNOTATION_TABLE_NAMESPACE, LABEL_ACTION},
+    DeltaTable, Error,
+};
+
+/// Retries of a failed worker before its job fails, as in Kubernetes
+pub const DEFAULT_BACKOFF_LIMIT: i32 = 6;
+/// Successful jobs kept per table, as for CronJobs
+pub const DEFAULT_SUCCESSFUL_JOBS_HISTORY_LIMIT: i32 = 3;
+/// Failed jobs kept per table, as for CronJobs
+pub const DEFAULT_FAILED_JOBS_HISTORY_LIMIT: i32 = 1;
+
+/// How the job running a maintenance action is retried, stopped and kept
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema, PartialEq)]
+pub struct JobLifecycle {
+    /// Retries of a failed worker before the job fails
+    #[schemars(range(min = 0))]
+    pub backoff_limit: Option<i32>,
+    /// How long the job may run before its worker is stopped and the job fails
+    pub active_deadline: Option<Duration>,
+    /// How long Kubernetes keeps the job after it finished
+    pub ttl_after_finished: Option<Duration>,
+    /// Successful jobs of the table kept by the controller
+    #[schemars(range(min = 0))]
+    pub successful_jobs_history_limit: Option<i32>,
+    /// Failed jobs of the table kept by the controller
+    #[schemars(range(min = 0))]
+    pub failed_jobs_history_limit: Option<i32>,
+}
+
+impl JobLifecycle {
+    /// The settings of `self`, falling back to `defaults` for those that are unset
+    pub fn or(&self, defaults: &JobLifecycle) -> JobLifecycle {
+        JobLifecycle {
+            backoff_limit: self.backoff_limit.or(defaults.backoff_limit),
+            active_deadline: self.active_deadline.or(defaults.active_deadline),
+            ttl_after_finished: self.ttl_after_finished.or(defaults.ttl_after_finished),
+            successful_jobs_history_limit: self
+                .successful_jobs_history_limit
+                .or(defaults.successful_jobs_history_limit),
+            failed_jobs_history_limit: self
+                .failed_jobs_history_limit
+                .or(defaults.failed_jobs_history_limit),
+        }
+    }
+
+    /// The settings of a job running the actions of `lifecycles` in one pipeline
+    ///
+    /// The job gets the most retries, TTL and history of any action. Its deadline is the
+    /// sum of the deadlines of the actions, or unset when one of them has none.
+    pub fn for_pipeline(lifecycles: &[JobLifecycle]) -> JobLifecycle {
+        let active_deadline = lifecycles
+            .iter()
+            .map(|l| l.active_deadline.map(StdDuration::from))
+            .sum::<Option<StdDuration>>()
+            .map(Duration::from);
+        JobLifecycle {
+            backoff_limit: lifecycles.iter().filter_map(|l| l.backoff_limit).max(),
+            active_deadline: active_deadline.filter(|_| !lifecycles.is_empty()),
+            ttl_after_finished: lifecycles
+                .iter()
+                .map(|l| l.ttl_after_finished)
+                .collect::<Option<Vec<Duration>>>()
+                .and_then(|ttls| ttls.into_iter().max()),
+            successful_jobs_history_limit: lifecycles
+                .iter()
+                .filter_map(|l| l.successful_jobs_history_limit)
+                .max(),
+            failed_jobs_history_limit: lifecycles
+                .iter()
+                .filter_map(|l| l.failed_jobs_history_limit)
+                .max(),
+        }
+    }
+}
+
+/// Longest `generate_name` of a job. Kubernetes appends 5 random characters and the name
+/// must fit in the 63 characters of the `job-name` label of its pods.
+const MAX_NAME_PREFIX: usize = 58;
+
+/// The `generate_name` of the job running `actions` on `table`, truncated to fit
+pub fn name_prefix(table: &str, actions: &str) -> String {
+    let name = format!("{}-{}", table, actions).to_lowercase();
+    let name = name[..name.len().min(MAX_NAME_PREFIX - 1)].trim_end_matches(['-', '.']);
+    format!("{}-", name)
+}
+
+/// `Some(true)` when `job` completed, `Some(false)` when it failed and `None` while it
+/// runs
+pub fn job_succeeded(job: &Job) -> Option<bool> {
+    let conditions = job.status.as_ref()?.conditions.as_ref()?;
+    conditions
+        .iter()
+        .filter(|c| c.status == "True")
+        .find_map(|c| match c.type_.as_str() {
+            "Complete" => Some(true),
+            "Failed" => Some(false),
+            _ => None,
+        })
+}
+
+/// Names of the finished `jobs` beyond the history limits of `lifecycle`, oldest last.
+/// Unset limits keep every job.
+pub fn jobs_to_delete(jobs: &[Job], lifecycle: &JobLifecycle) -> Vec<String> {
+    let mut finished: Vec<(&Job, bool)> = jobs
+        .iter()
+        .filter_map(|job| Some((job, job_succeeded(job)?)))
+        .collect();
+    finished.sort_by_key(|(job, _)| std::cmp::Reverse(job.creation_timestamp()));
+    let mut deleted = Vec::new();
+    for (succeeded, limit) in [
+        (true, lifecycle.successful_jobs_history_limit),
+        (false, lifecycle.failed_jobs_history_limit),
+    ] {
+        let Some(limit) = limit else {
+            continue;
+        };
+        deleted.extend(
+            finished
+                .iter()
+                .filter(|(_, s)| *s == succeeded)
+                .skip(limit.max(0) as usize)
+                .map(|(job, _)| job.name_any()),
+        );
+    }
+    deleted
+}
+
+/// Names of the worker `jobs` keyed by the namespace and name of their table
+fn jobs_by_table(jobs: &[Job]) -> BTreeMap<(String, String), Vec<String>> {
+    let mut tables: BTreeMap<(String, String), Vec<String>> = BTreeMap::new();
+    for job in jobs {
+        let annotations = job.annotations();
+        let (Some(namespace), Some(name)) = (
+            annotations.get(ANNOTATION_TABLE_NAMESPACE),
+            annotations.get(ANNOTATION_TABLE_NAME),
+        ) else {
+            continue;
+        };
+        tables
+            .entry((namespace.clone(), name.clone()))
+            .or_default()
+            .push(job.name_any());
+    }
+    tables
+}
+
+/// Deletes the job `name` along with its pods, unless it is already gone
+async fn delete_job(api: &Api<Job>, name: &str) -> Result<(), Error> {
+    let dp = DeleteParams {
+        propagation_policy: Some(PropagationPolicy::Background),
+        ..Default::default()
+    };
+    match api.delete(name, &dp).await {
+        Ok(_) => Ok(()),
+        Err(kube::Error::Api(r)) if r.code == 404 => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Deletes the finished jobs of the table `namespace/name` that are beyond the history
+/// limits of `lifecycle`, along with their pods. Jobs of every pipeline count, so that
+/// those of pipelines the table no longer runs are deleted too.
+pub async fn collect_garbage(
+    api: &Api<Job>,
+    namespace: &str,
+    name: &str,
+    lifecycle: &JobLifecycle,
+) -> Result<(), Error> {
+    let jobs: Vec<Job> = api
+        .list(&ListParams::default().labels(LABEL_ACTION))
+        .await?
+        .items
+        .into_iter()
+        .filter(|job| {
+            let annotations = job.annotations();
+            annotations
+                .get(ANNOTATION_TABLE_NAMESPACE)
+                .map(String::as_str)
+                == Some(namespace)
+                && annotations.get(ANNOTATION_TABLE_NAME).map(String::as_str) == Some(name)
+        })
+        .collect();
+    for job in jobs_to_delete(&jobs, lifecycle) {
+        tracing::info!("deleting job {} of {}/{}", job, namespace, name);
+        delete_job(api, &job).await?;
+    }
+
+    Ok(())
+}
+
+/// Deletes the jobs in `api` whose table no longer exists, along with their pods. Owner
+/// references only delete the jobs that run in the namespace of their table.
+pub async fn collect_orphans(api: &Api<Job>, client: Client) -> Result<(), Error> {
+    let jobs = api
+        .list(&ListParams::default().labels(LABEL_ACTION))
+        .await?
+        .items;
+    for ((namespace, name), jobs) in jobs_by_table(&jobs) {
+        let tables: Api<DeltaTable> = Api::namespaced(client.clone(), &namespace);
+        match tables.get_opt(&name).await {
+            Ok(Some(_)) => continue,
+            Ok(None) => {}
+            Err(e) => {
+                tracing::warn!("failed to get table {}/{}: {}", namespace, name, e);
+                continue;
+            }
+        }
+        for job in jobs {
+            tracing::info!(
+                "deleting job {} of deleted table {}/{}",
+                job,
+                namespace,
+                name
+            );
+            delete_job(api, &job).await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use chrono::{TimeZone, Utc};
+    use k8s_openapi::{
+        api::batch::v1::{Job, JobCondition, JobStatus},
+        apimachinery::pkg::apis::meta::v1::Time,
+    };
+    use kube::core::ObjectMeta;
+
+    use super::{job_succeeded, jobs_by_table, jobs_to_delete, name_prefix, JobLifecycle};
+    use crate::maintenance::{ANNOTATION_TABLE_NAME, ANNOTATION_TABLE_NAMESPACE};
+
+    fn job(name: &str, created: i64, condition: Option<&str>) -> Job {
+        Job {
+            metadata: ObjectMeta {
+                name: Some(name.into()),
+                creation_timestamp: Some(Time(Utc.timestamp_opt(created, 0).unwrap())),
+                ..Default::default()
+            },
+            status: Some(JobStatus {
+                conditions: condition.map(|type_| {
+                    vec![JobCondition {
+                        type_: type_.into(),
+                        status: "True".into(),
+                        ..Default::default()
+                    }]
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_lifecycle() {
+        let defaults = JobLifecycle {
+            backoff_limit: Some(6),
+            active_deadline: Some("6h".parse().unwrap()),
+            ttl_after_finished: None,
+            successful_jobs_history_limit: Some(3),
+            failed_jobs_history_limit: Some(1),
+        };
+        let vacuum = JobLifecycle {
+            backoff_limit: Some(0),
+            active_deadline: Some("2h".parse().unwrap()),
+            ..Default::default()
+        }
+        .or(&defaults);
+        assert_eq!(Some(0), vacuum.backoff_limit);
+        assert_eq!(Some(3), vacuum.successful_jobs_history_limit);
+
+        let optimize = JobLifecycle {
+            ttl_after_finished: Some("1h".parse().unwrap()),
+            ..Default::default()
+        }
+        .or(&defaults);
+        let pipeline = JobLifecycle::for_pipeline(&[optimize.clone(), vacuum]);
+        assert_eq!(Some(6), pipeline.backoff_limit);
+        assert_eq!(Some("8h".parse().unwrap()), pipeline.active_deadline);
+        assert_eq!(None, pipeline.ttl_after_finished);
+        assert_eq!(Some(1), pipeline.failed_jobs_history_limit);
+
+        let unlimited = JobLifecycle::default();
+        let pipeline = JobLifecycle::for_pipeline(&[optimize.clone(), unlimited]);
+        assert_eq!(None, pipeline.active_deadline);
+        let pipeline = JobLifecycle::for_pipeline(&[optimize.clone(), optimize]);
+        assert_eq!(Some("1h".parse().unwrap()), pipeline.ttl_after_finished);
+        assert_eq!(JobLifecycle::default(), JobLifecycle::for_pipeline(&[]));
+    }
+
+    #[test]
+    fn test_jobs_to_delete() {
+        let jobs = vec![
+            job("running", 50, None),
+            job("complete-1", 10, Some("Complete")),
+            job("complete-3", 30, Some("Complete")),
+            job("complete-2", 20, Some("Complete")),
+            job("failed-1", 15, Some("Failed")),
+            job("failed-2", 25, Some("Failed")),
+        ];
+        assert_eq!(Some(true), job_succeeded(&jobs[1]));
+        assert_eq!(Some(false), job_succeeded(&jobs[4]));
+        assert_eq!(None, job_succeeded(&jobs[0]));
+
+        let lifecycle = JobLifecycle {
+            successful_jobs_history_limit: Some(2),
+            failed_jobs_history_limit: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(
+            vec!["complete-1", "failed-2", "failed-1"],
+            jobs_to_delete(&jobs, &lifecycle)
+        );
+        assert!(jobs_to_delete(&jobs, &JobLifecycle::default()).is_empty());
+    }
+
+    #[test]
+    fn test_jobs_by_table() {
+        let mut jobs = vec![
+            job("clowns-1", 10, None),
+            job("clowns-2", 20, Some("Complete")),
+            job("emails-1", 30, None),
+            job("unknown", 40, None),
+        ];
+        for (job, table) in jobs.iter_mut().zip(["clowns", "clowns", "emails"]) {
+            job.metadata.annotations = Some(BTreeMap::from([
+                (ANNOTATION_TABLE_NAMESPACE.to_owned(), "data".to_owned()),
+                (ANNOTATION_TABLE_NAME.to_owned(), table.to_owned()),
+            ]));
+        }
+        assert_eq!(
+            BTreeMap::from([
+                (
+                    ("data".to_owned(), "clowns".to_owned()),
+                    vec!["clowns-1".to_owned(), "clowns-2".to_owned()]
+                ),
+                (
+                    ("data".to_owned(), "emails".to_owned()),
+                    vec!["emails-1".to_owned()]
+                ),
+            ]),
+            jobs_by_table(&jobs)
+        );
+    }
+
+    #[test]
+    fn test_name_prefix() {
+        assert_eq!(
+            "clowns-optimize-vacuum-",
+            name_prefix("Clowns", "optimize-vacuum")
+        );
+        let prefix = name_prefix(&"a".repeat(60), "checkpoint-optimize-vacuum");
+        assert_eq!(format!("{}-", "a".repeat(57)), prefix);
+        assert!(prefix.len() + 5 <= 63);
+        // A trailing separator is not doubled
+        let prefix = name_prefix(&"a".repeat(56), "optimize");
+        assert_eq!(format!("{}-", "a".repeat(56)), prefix);
+    }
+}
@@ -2,7 +2,7 @@
 //!
 //! Provides the [`maintenance`] module and [`DeltaTable`] [`CustomResource`] type
 //!
-use deltalake::datafusion::sql::sqlparser::keywords::NONE;
+use clap::ValueEnum;
 use deltalake::{DeltaConfigKey, DeltaOps, DeltaTableBuilder};
 use k8s_openapi::api::batch::v1::Job;
 use k8s_openapi::api::core::v1::{
@@ -14,16 +14,31 @@ use kube::core::{Duration, ObjectMeta};
 use kube::runtime::events::{Event, EventType, Recorder};
 use kube::{runtime::controller::Action, CustomResource};
 use kube::{Api, Client, Resource, ResourceExt};
-use maintenance::JobSettings;
+use maintenance::{JobSettings, MaintenanceExecutor};
+use policy::{criteria_policy, Clock, MaintenanceDefaults, MaintenancePolicy, SnapshotSummary};
 use schemars::JsonSchema;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::collections::{BTreeMap, HashMap};
 use std::fmt::Display;
-use std::time::Duration as StdDuration;
 
+mod adoption;
+pub mod columns;
+mod commit;
+pub mod commits;
+pub mod constraints;
+pub mod conversion;
+mod expression;
+pub mod failure;
+pub mod freshness;
+pub mod jobs;
 pub mod maintenance;
+pub mod policy;
+pub mod properties;
+pub mod protocol;
+pub mod telemetry;
+pub mod v1beta1;
 pub type DeltaLakeTable = deltalake::DeltaTable;
 
 static START: std::sync::Once = std::sync::Once::new();
@@ -36,98 +51,14 @@ pub fn initialize_handlers() {
 }
 
 const AZURITE_BLOB_STORAGE_URL: &str = "AZURITE_BLOB_STORAGE_URL";
-macro_rules! compare_optional_interval {
-    // match something(q,r,t,6,7,8) etc
-    // compiler extracts function name and arguments. It injects the values in respective varibles.
-    ($a:expr, $b:expr, $c:ty, $d:expr) => {
-        $a >= $b.map(|i| i.into()).unwrap_or($d)
-    };
-}
-
-macro_rules! match_maintenance_critiera_time {
-    ($timestamp_field:expr, $now:expr, $interval_option:expr, $default_value:expr) => {
-        match $timestamp_field {
-            Some(last_timestamp) => {
-                compare_optional_interval!(
-                    StdDuration::from_secs(($now - last_timestamp) as u64),
-                    $interval_option,
-                    StdDuration,
-                    $default_value
-                )
-            }
-            None => true,
-        }
-    };
-}
-
-macro_rules! match_maintenance_critiera_commit {
-    ($commit_field:expr, $current_commit:expr, $interval_option:expr, $default_value:expr) => {
-        match $commit_field {
-            Some(last_commit) => {
-                compare_optional_interval!(
-                    $current_commit - last_commit,
-                    $interval_option,
-                    i64,
-                    $default_value
-                )
-            }
-            None => true,
-        }
-    };
-}
-
-macro_rules! match_maintenance_criteria {
-    ($criteria_option:expr, $timestamp_field:expr, $time_interval_option:expr, $default_time:expr, $commit_field:expr, $current_commit:expr, $commit_option:expr, $default_commit:expr) => {{
-        let now = chrono::Utc::now();
-        let timestamp_now = now.timestamp();
-        match $criteria_option
-            .as_ref()
-            .unwrap_or(&MaintenanceCriteria::Time)
-        {
-            MaintenanceCriteria::Time => {
-                match_maintenance_critiera_time!(
-                    $timestamp_field,
-                    timestamp_now,
-                    $time_interval_option,
-                    $default_time
-                )
-            }
-            MaintenanceCriteria::Commit => match_maintenance_critiera_commit!(
-                $commit_field,
-                $current_commit,
-                $commit_option,
-                $default_commit
-            ),
-            MaintenanceCriteria::TimeAndCommit => {
-                let time_match = match_maintenance_critiera_time!(
-                    $timestamp_field,
-                    timestamp_now,
-                    $time_interval_option,
-                    $default_time
-                );
-                let commit_match = match_maintenance_critiera_commit!(
-                    $commit_field,
-                    $current_commit,
-                    $commit_option,
-                    $default_commit
-                );
-
-                time_match || commit_match
-            }
-        }
-    }};
-}
 
 pub const ANNOTATION_RECONCILIATION_POLICY: &str = "delta-operator.rs/reconciliation-policy";
 pub const RECONCILIATION_MANAGE: &str = "manage";
 pub const RECONCILIATION_DETACH: &str = "detach";
-
-// 30 days
-static DEFAULT_VACUUM_INTERVAL: StdDuration = StdDuration::from_secs(2_592_000);
-// 24 hours
-static DEFAULT_CHECKPOINT_INTERVAL: StdDuration = StdDuration::from_secs(86_400);
-// 24 hours
-static DEFAULT_OPTIMIZE_INTERVAL: StdDuration = StdDuration::from_secs(86_400);
+/// Comma separated maintenance actions to run on the next reconcile, regardless of criteria
+pub const ANNOTATION_RUN: &str = "delta-operator.rs/run";
+/// Identifies a request made with [`ANNOTATION_RUN`]; change it to run the same actions again
+pub const ANNOTATION_RUN_ID: &str = "delta-operator.rs/run-id";
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -162,6 +93,21 @@ pub enum Error {
         #[from]
         source: deltalake::arrow::error::ArrowError,
     },
+    #[error("Invalid column settings: {reason}")]
+    InvalidColumns { reason: String },
+    #[error("Cannot update the schema: {reason}")]
+    SchemaUpdate { reason: String },
+    #[error("Unknown table property {key}")]
+    UnknownProperty { key: String },
+    #[error("Table at {uri} already exists and the creation policy is CreateOnly")]
+    TableExists { uri: String },
+    #[error("Table at {uri} does not match the spec: {reason}")]
+    AdoptionMismatch { uri: String, reason: String },
+    #[error("Maintenance {action} failed: {source}")]
+    Maintenance {
+        action: maintenance::Action,
+        source: Box<Error>,
+    },
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
@@ -177,6 +123,25 @@ pub enum MaintenanceCriteria {
     TimeAndCommit,
 }
 
+/// What to do when `table_uri` does or does not hold a table
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema, PartialEq)]
+pub enum CreationPolicy {
+    /// Adopt an existing table or create a new one
+    #[default]
+    CreateIfMissing,
+    /// Only adopt an existing table; fail when there is none
+    AdoptOnly,
+    /// Only create a new table; fail when one already exists
+    CreateOnly,
+}
+
+/// How the operator came to manage a table
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq)]
+pub enum TableCreation {
+    Created,
+    Adopted,
+}
+
 impl Display for ReferenceKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -194,8 +159,16 @@ impl Display for ReferenceKind {
     version = "v1alpha1",
     namespaced
 )]
-#[kube(status = "DeltaTableStatus", shortname = "dt")]
-
+#[kube(status = "DeltaTableStatus", shortname = "dt", category = "delta")]
+#[kube(
+    printcolumn = r#"{"name":"URI", "type":"string", "jsonPath":".spec.table_uri"}"#,
+    printcolumn = r#"{"name":"Version", "type":"integer", "jsonPath":".status.version"}"#,
+    printcolumn = r#"{"name":"Ready", "type":"string", "jsonPath":".status.conditions[?(@.type==\"Ready\")].status"}"#,
+    printcolumn = r#"{"name":"Optimized", "type":"date", "jsonPath":".status.last_optimize_time"}"#,
+    printcolumn = r#"{"name":"Vacuumed", "type":"date", "jsonPath":".status.last_vacuum_time"}"#,
+    printcolumn = r#"{"name":"Files", "type":"integer", "jsonPath":".status.statistics.num_files"}"#,
+    printcolumn = r#"{"name":"Age", "type":"date", "jsonPath":".metadata.creationTimestamp"}"#
+)]
 pub struct DeltaTableSpec {
     /// Name of the table
     pub name: String,
@@ -226,12 +199,45 @@ pub struct DeltaTableSpec {
     pub storage_options_from: Option<Vec<StorageOptionReference>>,
     /// Delta table configuration settings
     pub configuration: Option<DeltaTableConfiguration>,
+    /// CHECK constraints every row of the table must satisfy
+    pub constraints: Option<Vec<constraints::TableConstraint>>,
+    /// Protocol versions and table features to upgrade the table to
+    pub protocol: Option<protocol::DeltaTableProtocol>,
+    /// Whether a table may be created at `table_uri` or an existing one adopted.
+    /// Defaults to `CreateIfMissing`.
+    pub creation_policy: Option<CreationPolicy>,
+    /// Stop creating the table, updating its schema and running maintenance while still
+    /// reporting its status
+    pub suspend: Option<bool>,
+    /// Count the files and rows of the table into `status.statistics`, which walks
+    /// every file of the snapshot whenever the table is loaded or the counts are older
+    /// than the resync interval
+    pub statistics: Option<bool>,
+    /// Order in which pending maintenance for this table is started relative to other
+    /// tables when the controller limits concurrent maintenance. Higher runs first.
+    pub maintenance_priority: Option<i32>,
+    /// Poll the log for new commits and report each one
+    pub watch_commits: Option<commits::CommitWatch>,
+    /// Report the table as stale when its latest commit is older than `max_age`
+    pub freshness: Option<freshness::Freshness>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, Default)]
 pub struct SchemaSettings {
     pub value: String,
     pub manage: Option<bool>,
+    /// Comments, metadata and generation expressions of the columns in `value`
+    pub columns: Option<Vec<columns::ColumnSettings>>,
+}
+
+impl SchemaSettings {
+    /// The schema in `value` with the `columns` settings applied
+    pub fn schema(&self) -> Result<deltalake::kernel::Schema, Error> {
+        let mut schema = serde_json::de::from_str(&self.value)?;
+        columns::apply(&mut schema, self.columns.as_deref().unwrap_or_default())
+            .map_err(|reason| Error::InvalidColumns { reason })?;
+        Ok(schema)
+    }
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
@@ -249,22 +255,135 @@ pub struct WorkerConfiguration {
 
 /// Represents settings for the delta table
 /// For any properties not handled by the static properties, `additional_settings` allows
-/// adding the options as key-value pairs.
+/// adding the options as key-value pairs. Typed properties take precedence over the same
+/// key in `additional_settings`, and unknown `delta.` keys are rejected.
 /// The available properties can be found at the following links.
 /// <https://docs.delta.io/latest/table-properties.html#delta-table-properties-reference>
 /// <https://learn.microsoft.com/en-us/azure/databricks/delta/table-properties>
-#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
 pub struct DeltaTableConfiguration {
     pub enable_change_feed: Option<bool>,
+    /// `delta.appendOnly`
+    pub append_only: Option<bool>,
+    /// `delta.logRetentionDuration`
+    pub log_retention_duration: Option<Duration>,
+    /// `delta.deletedFileRetentionDuration`
+    pub deleted_file_retention_duration: Option<Duration>,
+    /// `delta.checkpointInterval`
+    #[schemars(range(min = 1))]
+    pub checkpoint_interval: Option<i32>,
+    /// `delta.dataSkippingNumIndexedCols`, -1 collects statistics for all columns
+    #[schemars(range(min = -1))]
+    pub data_skipping_num_indexed_cols: Option<i32>,
+    /// `delta.dataSkippingStatsColumns`
+    pub data_skipping_stats_columns: Option<Vec<String>>,
+    /// `delta.targetFileSize` in bytes
+    #[schemars(range(min = 1))]
+    pub target_file_size: Option<i64>,
+    /// `delta.tuneFileSizesForRewrites`
+    pub tune_file_sizes_for_rewrites: Option<bool>,
+    /// `delta.isolationLevel`
+    pub isolation_level: Option<IsolationLevel>,
+    /// `delta.columnMapping.mode`
+    pub column_mapping_mode: Option<ColumnMappingMode>,
     pub additional_settings: Option<HashMap<String, String>>,
 }
 
+/// Isolation level of transactions on a table
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq)]
+pub enum IsolationLevel {
+    Serializable,
+    WriteSerializable,
+    SnapshotIsolation,
+}
+
+/// How columns are mapped to the columns of data files
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnMappingMode {
+    None,
+    Id,
+    Name,
+}
+
+impl DeltaTableConfiguration {
+    /// The table properties configured by these settings
+    pub fn properties(&self) -> Result<BTreeMap<String, String>, Error> {
+        let mut data: BTreeMap<String, String> = BTreeMap::new();
+        for (key, value) in self.additional_settings.iter().flatten() {
+            if key.starts_with("delta.") && key.parse::<DeltaConfigKey>().is_err() {
+                return Err(Error::UnknownProperty { key: key.clone() });
+            }
+            data.insert(key.clone(), value.clone());
+        }
+
+        let mut set = |key: DeltaConfigKey, value: Option<String>| {
+            if let Some(value) = value {
+                data.insert(key.as_ref().to_owned(), value);
+            }
+        };
+        set(
+            DeltaConfigKey::EnableChangeDataFeed,
+            self.enable_change_feed.map(|v| v.to_string()),
+        );
+        set(
+            DeltaConfigKey::AppendOnly,
+            self.append_only.map(|v| v.to_string()),
+        );
+        set(
+            DeltaConfigKey::LogRetentionDuration,
+            self.log_retention_duration
+                .map(|d| properties::format_interval(d.into())),
+        );
+        set(
+            DeltaConfigKey::DeletedFileRetentionDuration,
+            self.deleted_file_retention_duration
+                .map(|d| properties::format_interval(d.into())),
+        );
+        set(
+            DeltaConfigKey::CheckpointInterval,
+            self.checkpoint_interval.map(|v| v.to_string()),
+        );
+        set(
+            DeltaConfigKey::DataSkippingNumIndexedCols,
+            self.data_skipping_num_indexed_cols.map(|v| v.to_string()),
+        );
+        set(
+            DeltaConfigKey::DataSkippingStatsColumns,
+            self.data_skipping_stats_columns
+                .as_ref()
+                .map(|c| c.join(",")),
+        );
+        set(
+            DeltaConfigKey::TargetFileSize,
+            self.target_file_size.map(|v| v.to_string()),
+        );
+        set(
+            DeltaConfigKey::TuneFileSizesForRewrites,
+            self.tune_file_sizes_for_rewrites.map(|v| v.to_string()),
+        );
+        set(
+            DeltaConfigKey::IsolationLevel,
+            self.isolation_level.as_ref().map(|v| format!("{:?}", v)),
+        );
+        set(
+            DeltaConfigKey::ColumnMappingMode,
+            self.column_mapping_mode
+                .as_ref()
+                .map(|v| format!("{:?}", v).to_lowercase()),
+        );
+        Ok(data)
+    }
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
 pub struct DeltaTableMaintenceConfiguration {
     pub time_interval: Option<Duration>,
     pub commit_interval: Option<i32>,
     pub disable: Option<bool>,
     pub criteria: Option<MaintenanceCriteria>,
+    /// Retries, deadline and history of the worker jobs running the action
+    pub job: Option<jobs::JobLifecycle>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
@@ -274,6 +393,8 @@ pub struct DeltaTableVacuumConfiguration {
     pub disable: Option<bool>,
     pub retention_period: Option<Duration>,
     pub criteria: Option<MaintenanceCriteria>,
+    /// Retries, deadline and history of the worker jobs running the action
+    pub job: Option<jobs::JobLifecycle>,
 }
 
 /// The status object of `DeltaTable`
@@ -289,6 +410,216 @@ pub struct DeltaTableStatus {
     pub last_optimize_timestamp: Option<i64>,
     pub version: Option<i64>,
     pub is_healthy: Option<bool>,
+    /// Maintenance waiting for capacity in the controller's scheduler
+    pub maintenance_queue: Option<QueuedMaintenance>,
+    /// The last request made with the `delta-operator.rs/run` annotation that was handled
+    pub last_run_request: Option<RunRequestStatus>,
+    /// Whether the table is suspended by `spec.suspend` or paused by the controller
+    pub suspended: Option<bool>,
+    /// Whether the table was created by the operator or adopted
+    pub creation: Option<TableCreation>,
+    /// The `table_uri` at which the table was created or adopted
+    pub creation_uri: Option<String>,
+    /// Observations of the table's state
+    pub conditions: Option<Vec<DeltaTableCondition>>,
+    /// The protocol of the current table version
+    pub protocol: Option<protocol::ProtocolStatus>,
+    /// Constraints from the spec that are applied to the table
+    pub constraints: Option<Vec<constraints::TableConstraint>>,
+    /// Constraints that rows of the table violated when they were added
+    pub constraint_violations: Option<Vec<ConstraintViolation>>,
+    /// RFC 3339 time of `last_optimize_timestamp`
+    pub last_optimize_time: Option<String>,
+    /// RFC 3339 time of `last_vacuum_timestamp`
+    pub last_vacuum_time: Option<String>,
+    /// Files of the current table version
+    pub statistics: Option<TableStatistics>,
+    /// Generation of the spec last applied to the table
+    pub observed_generation: Option<i64>,
+    /// Timestamp of the latest commit seen by `watch_commits` or the freshness check
+    pub last_commit_timestamp: Option<i64>,
+    /// Operation of the latest commit seen by `watch_commits`
+    pub last_operation: Option<String>,
+    /// The last failed maintenance, cleared when maintenance succeeds
+    pub last_failure: Option<failure::MaintenanceFailure>,
+}
+
+/// Files of a table version, as reported in the status
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema, PartialEq)]
+pub struct TableStatistics {
+    /// Number of active data files
+    pub num_files: i64,
+    /// Total size of the active data files
+    pub size_bytes: i64,
+    /// Number of rows, when every file has statistics
+    pub num_records: Option<i64>,
+    /// Seconds since the unix epoch at which the files were counted
+    pub timestamp: Option<i64>,
+}
+
+impl TableStatistics {
+    pub fn from_table(table: &DeltaLakeTable) -> Result<Self, Error> {
+        let mut statistics = Self {
+            num_records: Some(0),
+            ..Default::default()
+        };
+        for file in table.snapshot()?.log_data() {
+            statistics.num_files += 1;
+            statistics.size_bytes += file.size();
+            statistics.num_records = statistics
+                .num_records
+                .zip(file.num_records())
+                .map(|(total, records)| total + records as i64);
+        }
+        Ok(statistics)
+    }
+}
+
+/// A constraint that could not be added because existing rows violate it
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq)]
+pub struct ConstraintViolation {
+    pub constraint: constraints::TableConstraint,
+    /// Table version that was checked; adding is retried once the table changes
+    pub version: i64,
+    pub message: String,
+}
+
+/// Condition type reporting whether the spec's schema and column settings are applied
+pub const CONDITION_SCHEMA_APPLIED: &str = "SchemaApplied";
+/// Condition type reporting whether the spec's table properties are applied
+pub const CONDITION_PROPERTIES_APPLIED: &str = "PropertiesApplied";
+/// Condition type reporting whether the spec's constraints are applied
+pub const CONDITION_CONSTRAINTS_APPLIED: &str = "ConstraintsApplied";
+/// Condition type reporting whether the table has the protocol requested in the spec
+pub const CONDITION_PROTOCOL_UP_TO_DATE: &str = "ProtocolUpToDate";
+/// Condition type reporting whether the latest commit is within the spec's freshness
+pub const CONDITION_FRESH: &str = "Fresh";
+/// Condition type reporting whether the last reconcile succeeded and every other
+/// condition is `True`
+pub const CONDITION_READY: &str = "Ready";
+
+/// An observation of a table's state, following Kubernetes condition conventions
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq)]
+pub struct DeltaTableCondition {
+    #[serde(rename = "type")]
+    pub type_: String,
+    /// One of `True`, `False` or `Unknown`
+    pub status: String,
+    pub reason: Option<String>,
+    pub message: Option<String>,
+    /// RFC 3339 time of the last change of `status`
+    pub last_transition_time: Option<String>,
+}
+
+impl DeltaTableCondition {
+    pub fn new(type_: &str, status: bool, reason: &str, message: Option<String>) -> Self {
+        Self {
+            type_: type_.to_owned(),
+            status: if status { "True" } else { "False" }.to_owned(),
+            reason: Some(reason.to_owned()),
+            message,
+            last_transition_time: None,
+        }
+    }
+}
+
+/// Replaces the condition of the same type in `conditions`, keeping its transition time
+/// when the status did not change
+fn set_condition(
+    conditions: &mut Vec<DeltaTableCondition>,
+    mut condition: DeltaTableCondition,
+    now: &str,
+) {
+    match conditions.iter_mut().find(|c| c.type_ == condition.type_) {
+        Some(existing) => {
+            condition.last_transition_time = match existing.status == condition.status {
+                true => existing.last_transition_time.clone(),
+                false => Some(now.to_owned()),
+            };
+            *existing = condition;
+        }
+        None => {
+            condition.last_transition_time = Some(now.to_owned());
+            conditions.push(condition);
+        }
+    }
+}
+
+/// The [`CONDITION_READY`] condition for `conditions`, or for a reconcile that failed
+/// with `error`
+fn ready_condition(
+    conditions: &[DeltaTableCondition],
+    error: Option<String>,
+) -> DeltaTableCondition {
+    if let Some(error) = error {
+        return DeltaTableCondition::new(CONDITION_READY, false, "ReconcileFailed", Some(error));
+    }
+    let failing: Vec<&DeltaTableCondition> = conditions
+        .iter()
+        .filter(|c| c.type_ != CONDITION_READY && c.status != "True")
+        .collect();
+    match failing.first() {
+        None => DeltaTableCondition::new(CONDITION_READY, true, "Reconciled", None),
+        Some(first) => DeltaTableCondition::new(
+            CONDITION_READY,
+            false,
+            first.reason.as_deref().unwrap_or(&first.type_),
+            Some(
+                failing
+                    .iter()
+                    .map(|c| match &c.message {
+                        Some(message) => format!("{}: {}", c.type_, message),
+                        None => c.type_.clone(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("; "),
+            ),
+        ),
+    }
+}
+
+/// RFC 3339 time of a status timestamp
+fn status_time(timestamp: Option<i64>) -> Option<String> {
+    timestamp
+        .and_then(|t| chrono::DateTime::from_timestamp(t, 0))
+        .map(|t| t.to_rfc3339())
+}
+
+/// Acknowledges a request made with the `delta-operator.rs/run` annotation
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq)]
+pub struct RunRequestStatus {
+    pub id: String,
+    /// Actions that were started. Empty when the request was rejected.
+    pub actions: Vec<maintenance::Action>,
+    pub timestamp: i64,
+    pub message: Option<String>,
+}
+
+/// Maintenance actions waiting to start and their position in the controller's queue
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq)]
+pub struct QueuedMaintenance {
+    pub actions: Vec<maintenance::Action>,
+    pub position: usize,
+}
+
+impl DeltaTableStatus {
+    /// The commit recorded by the last successful run of `action`
+    pub fn last_maintenance_commit(&self, action: &maintenance::Action) -> Option<i64> {
+        match action {
+            maintenance::Action::Checkpoint => self.last_checkpoint_commit,
+            maintenance::Action::Optimize => self.last_optimize_commit,
+            maintenance::Action::Vacuum => self.last_vacuum_commit,
+        }
+    }
+
+    /// The timestamp recorded by the last successful run of `action`
+    pub fn last_maintenance_timestamp(&self, action: &maintenance::Action) -> Option<i64> {
+        match action {
+            maintenance::Action::Checkpoint => self.last_checkpoint_timestamp,
+            maintenance::Action::Optimize => self.last_optimize_timestamp,
+            maintenance::Action::Vacuum => self.last_vacuum_timestamp,
+        }
+    }
 }
 
 impl DeltaTable {
@@ -297,67 +628,72 @@ impl DeltaTable {
         let storage_options = self
             .accumulate_storage_options(client.clone(), namespace)
             .await?;
-        self.create_delta_table(storage_options, false).await
+        Ok(self.create_delta_table(storage_options, false).await?.0)
     }
 
-    fn extract_config(&self) -> Vec<(String, Option<String>)> {
-        match self.spec.configuration.as_ref() {
-            None => Vec::new(),
-            Some(configuration) => {
-                let mut data = match configuration.additional_settings.as_ref() {
-                    None => HashMap::<String, String>::new(),
-                    Some(additional_settings) => additional_settings.clone(),
-                };
-
-                if let Some(enable_change_feed) = configuration.enable_change_feed.as_ref() {
-                    data.insert(
-                        DeltaConfigKey::EnableChangeDataFeed.as_ref().to_owned(),
-                        enable_change_feed.to_string(),
-                    );
-                }
-
-                data.into_iter()
-                    .map(|(key, value)| (key, Some(value)))
-                    .collect::<Vec<(String, Option<String>)>>()
-            }
-        }
+    fn extract_config(&self) -> Result<Vec<(String, Option<String>)>, Error> {
+        Ok(self
+            .spec
+            .configuration
+            .as_ref()
+            .map(|c| c.properties())
+            .transpose()?
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(key, value)| (key, Some(value)))
+            .collect())
     }
 
-    async fn create_delta_table(
+    /// The table at `table_uri`, without reading its log
+    fn unloaded_table(
         &self,
         storage_options: HashMap<String, String>,
-        create_if_not_found: bool,
     ) -> Result<DeltaLakeTable, Error> {
-        let mut table = DeltaTableBuilder::from_uri(&self.spec.table_uri)
+        Ok(DeltaTableBuilder::from_uri(&self.spec.table_uri)
             .with_allow_http(self.spec.allow_http.unwrap_or_default())
             .with_storage_options(storage_options)
-            .build()?;
+            .build()?)
+    }
 
+    /// Loads the table, creating it when it is not found and `create_if_not_found` is set.
+    /// Also returns whether the table was created.
+    #[tracing::instrument(name = "load_table", skip_all, fields(uri = %self.spec.table_uri))]
+    async fn create_delta_table(
+        &self,
+        storage_options: HashMap<String, String>,
+        create_if_not_found: bool,
+    ) -> Result<(DeltaLakeTable, bool), Error> {
+        let mut table = self.unloaded_table(storage_options)?;
         match table.load().await {
-            Ok(_) => Ok(table),
+            Ok(_) => Ok((table, false)),
             Err(deltalake::DeltaTableError::NotATable(_)) if create_if_not_found => {
-                let schema: deltalake::kernel::Schema =
-                    serde_json::de::from_str(&self.spec.schema_settings.value)?;
-                let columns = schema.fields;
-                Ok(DeltaOps(table)
+                let columns = self.spec.schema_settings.schema()?.fields;
+                let table = DeltaOps(table)
                     .create()
                     .with_table_name(self.spec.name.clone())
                     .with_columns(columns)
-                    .with_configuration(self.extract_config())
+                    .with_configuration(self.extract_config()?)
                     .with_partition_columns(self.spec.partition_columns.clone())
                     .with_metadata(serde_json::Map::new())
-                    .await?)
+                    .await?;
+                Ok((table, true))
             }
             Err(err) => Err(Error::DeltaLake { source: err }),
         }
     }
 
+    #[tracing::instrument(name = "reconcile", skip_all, fields(table = %self.name_any()))]
     pub async fn reconcile(
         &self,
         client: Client,
-        _: Recorder,
+        recorder: Recorder,
         settings: &JobSettings,
+        executor: &dyn MaintenanceExecutor,
+        paused: bool,
     ) -> Result<Action, Error> {
+        let suspended = paused || self.spec.suspend.unwrap_or_default();
+        // Changes to storage are skipped, but status is still reported
+        let read_only = suspended || settings.dry_run;
         let namespace = &self.namespace().unwrap();
         let api: Api<DeltaTable> = Api::namespaced(client.clone(), namespace);
         if self.status.as_ref().is_none() {
@@ -376,89 +712,835 @@ impl DeltaTable {
         let storage_options = self
             .accumulate_storage_options(client.clone(), namespace)
             .await?;
-        let table = match self.create_delta_table(storage_options.clone(), true).await {
-            Ok(table) => table,
-            Err(e) => {
-                self.update_status_ok(
-                    &api,
-                    json!({
-                    "table_uri": "",
-                    "schema": "",
-                    "is_healthy": false,
-                        }),
-                )
-                .await;
-                return Err(e);
+        let status = self.status.clone().unwrap_or_default();
+        let (latest, only_data_changed, until_stale) = match self
+            .unloaded_table(storage_options.clone())
+        {
+            Ok(table) => {
+                let latest = commits::latest_version(&table)
+                    .await
+                    .map_err(|e| {
+                        tracing::debug!("failed to read the version of {}: {}", self.name_any(), e)
+                    })
+                    .ok();
+                let only_data_changed = match (latest, status.version) {
+                    (Some(latest), Some(previous)) if latest > previous => {
+                        commits::only_data_changed(&table, previous, latest)
+                            .await
+                            .unwrap_or_default()
+                    }
+                    _ => false,
+                };
+                if let (Some(watch), Some(latest), Some(previous)) =
+                    (self.spec.watch_commits.as_ref(), latest, status.version)
+                {
+                    if latest > previous {
+                        self.track_commits(&api, &recorder, watch, &table, previous, latest)
+                            .await;
+                    }
+                }
+                let until_stale = match (self.spec.freshness.as_ref(), latest) {
+                    (Some(freshness), Some(latest)) => {
+                        self.check_freshness(&api, &recorder, freshness, &table, latest)
+                            .await
+                    }
+                    _ => None,
+                };
+                (latest, only_data_changed, until_stale)
+            }
+            Err(_) => (None, false, None),
+        };
+        let applied = read_only
+            || (status.observed_generation.is_some()
+                && status.observed_generation == self.metadata.generation);
+        // Counting the files walks the whole snapshot, so new data only leads to a load
+        // once requested counts are older than the resync interval
+        let counted = !self.spec.statistics.unwrap_or_default()
+            || status
+                .statistics
+                .as_ref()
+                .and_then(|s| s.timestamp)
+                .is_some_and(|counted| match settings.resync_interval {
+                    Some(resync) => {
+                        counted + resync.as_secs() as i64 > chrono::Utc::now().timestamp()
+                    }
+                    None => true,
+                });
+        // Violated constraints are retried once the table changes
+        let violations = status
+            .constraint_violations
+            .as_ref()
+            .is_some_and(|v| !v.is_empty());
+        let data_only = only_data_changed && counted && !violations;
+        // The table is only loaded when the spec or the table's metadata changed
+        let unchanged = match latest {
+            Some(latest) if applied && status.is_healthy == Some(true) => {
+                (status.version == Some(latest) || data_only).then_some(latest)
             }
+            _ => None,
+        };
+        let (snapshot, mut table_status) = match unchanged {
+            Some(version) => (
+                SnapshotSummary { version },
+                json!({ "version": version, "is_healthy": true }),
+            ),
+            None => match self
+                .load_and_reconcile(&api, &recorder, storage_options.clone(), read_only)
+                .await
+            {
+                Ok(loaded) => loaded,
+                Err(e) => {
+                    self.update_status_ok(
+                        &api,
+                        json!({
+                        "table_uri": "",
+                        "schema": "",
+                        "is_healthy": false,
+                            }),
+                    )
+                    .await;
+                    self.update_ready(&api, Some(e.to_string())).await;
+                    return Err(e);
+                }
+            },
+        };
+        let mut due = self.due_actions(
+            &snapshot,
+            &settings.maintenance_defaults,
+            &settings.maintenance_order,
+            &policy::SystemClock,
+        );
+        let run_request = match read_only {
+            true => None,
+            false => {
+                self.take_run_request(&recorder, &settings.maintenance_order, &mut due)
+                    .await
+            }
+        };
+        if read_only && !due.is_empty() {
+            tracing::info!(
+                "skipping maintenance {} on {}: suspended or dry run",
+                maintenance::join_actions(&due, ","),
+                self.name_any()
+            );
+        }
+        if read_only || due.is_empty() {
+            executor.cancel(namespace, &self.name_any()).await;
+        } else {
+            executor.execute(self, due, &storage_options).await?;
+        }
+        if let Some(run_request) = run_request {
+            self.update_status_ok(&api, json!({ "last_run_request": run_request }))
+                .await;
+        }
+
+        table_status["suspended"] = json!(suspended);
+        table_status["last_optimize_time"] = json!(status_time(status.last_optimize_timestamp));
+        table_status["last_vacuum_time"] = json!(status_time(status.last_vacuum_timestamp));
+        self.update_status_ok(&api, table_status).await;
+        self.update_ready(&api, None).await;
+        let requeue = match self.spec.watch_commits.as_ref() {
+            Some(watch) => Some(
+                settings
+                    .resync_interval
+                    .map_or(watch.interval(), |resync| resync.min(watch.interval())),
+            ),
+            None => settings.resync_interval,
         };
+        // Check again just after the table would become stale, so that a stalled writer
+        // is noticed without waiting for the resync
+        let requeue = match until_stale.map(|d| d + std::time::Duration::from_secs(1)) {
+            Some(until_stale) => Some(requeue.map_or(until_stale, |r| r.min(until_stale))),
+            None => requeue,
+        };
+        Ok(requeue.map_or(Action::await_change(), Action::requeue))
+    }
 
-        if let Some(true) = self.spec.schema_settings.manage.as_ref() {}
+    /// Zeroes the gauges of this table once the controller stops reconciling it, as
+    /// gauges cannot be unregistered
+    pub fn reset_metrics(&self) {
+        if self.spec.freshness.is_some() {
+            metrics::gauge!(
+                freshness::METRIC_SECONDS_SINCE_LAST_COMMIT,
+                0.0,
+                "table" => self.name_any(),
+                "namespace" => self.namespace().unwrap()
+            );
+        }
+    }
 
-        let table = match crate::maintenance::update_schema(self, table).await {
-            Ok(table) => table,
+    /// Compares the latest commit with `freshness`, reporting the outcome in the
+    /// [`CONDITION_FRESH`] condition and returning the time until the table is stale
+    ///
+    /// A Warning event is published when the table becomes stale.
+    async fn check_freshness(
+        &self,
+        api: &Api<DeltaTable>,
+        recorder: &Recorder,
+        freshness: &freshness::Freshness,
+        table: &DeltaLakeTable,
+        latest: i64,
+    ) -> Option<std::time::Duration> {
+        let last_commit = match freshness::commit_timestamp(table, latest).await {
+            Ok(timestamp) => timestamp,
             Err(e) => {
-                self.update_status_ok(
-                    &api,
-                    json!({
-                    "table_uri": "",
-                    "schema": "",
-                    "is_healthy": false,
-                        }),
+                tracing::warn!(
+                    "failed to read the last commit of {}: {}",
+                    self.name_any(),
+                    e
+                );
+                return None;
+            }
+        };
+        let check = freshness.check(last_commit, chrono::Utc::now().timestamp());
+        metrics::gauge!(
+            freshness::METRIC_SECONDS_SINCE_LAST_COMMIT,
+            check.age as f64,
+            "table" => self.name_any(),
+            "namespace" => self.namespace().unwrap()
+        );
+        let condition = if check.fresh {
+            DeltaTableCondition::new(CONDITION_FRESH, true, "Fresh", None)
+        } else {
+            let message = format!(
+                "latest commit {} is {}s old, more than the maximum age of {}s",
+                latest,
+                check.age,
+                std::time::Duration::from(freshness.max_age).as_secs()
+            );
+            let was_stale = self
+                .status
+                .as_ref()
+                .and_then(|s| s.conditions.as_ref())
+                .and_then(|c| c.iter().find(|c| c.type_ == CONDITION_FRESH))
+                .is_some_and(|c| c.status == "False");
+            if !was_stale {
+                self.publish_event(
+                    recorder,
+                    EventType::Warning,
+                    "FreshnessBreached",
+                    format!("{:?}: {}", freshness.severity(), message),
                 )
                 .await;
-                return Err(e);
             }
+            DeltaTableCondition::new(CONDITION_FRESH, false, "Stale", Some(message))
         };
-        if self.requires_vacuum(&table) {
-            self.create_job(
-                client.clone(),
-                maintenance::Action::Vacuum,
-                namespace,
-                settings,
-                &storage_options,
-            )
-            .await?;
+        self.update_condition(api, condition).await;
+        if self.status.as_ref().and_then(|s| s.last_commit_timestamp) != Some(last_commit) {
+            self.update_status_ok(api, json!({ "last_commit_timestamp": last_commit }))
+                .await;
         }
+        check.remaining
+    }
 
-        if self.requires_checkpoint(&table) {
-            self.create_job(
-                client.clone(),
-                maintenance::Action::Checkpoint,
-                namespace,
-                settings,
-                &storage_options,
-            )
-            .await?;
+    /// Publishes an event, and calls the callback of `watch`, for the commits after
+    /// `previous` up to `latest`, and records the latest commit in the status
+    async fn track_commits(
+        &self,
+        api: &Api<DeltaTable>,
+        recorder: &Recorder,
+        watch: &commits::CommitWatch,
+        table: &DeltaLakeTable,
+        previous: i64,
+        latest: i64,
+    ) {
+        let commits = match commits::read_commits(table, previous, latest).await {
+            Ok(commits) => commits,
+            Err(e) => {
+                tracing::warn!("failed to read commits of {}: {}", self.name_any(), e);
+                return;
+            }
+        };
+        let skipped = latest - previous - commits.len() as i64;
+        if skipped > 0 {
+            tracing::info!(
+                "{} commits of {} were not reported",
+                skipped,
+                self.name_any()
+            );
         }
-
-        if self.requires_optimize(&table) {
-            self.create_job(
-                client.clone(),
-                maintenance::Action::Optimize,
-                namespace,
-                settings,
-                &storage_options,
+        let namespace = self.namespace().unwrap_or_default();
+        let name = self.name_any();
+        for commit in commits.iter() {
+            let note = match &commit.operation {
+                Some(operation) => format!("Version {}: {}", commit.version, operation),
+                None => format!("Version {}", commit.version),
+            };
+            self.publish_event(recorder, EventType::Normal, "NewCommit", note)
+                .await;
+            if let Some(url) = watch.callback_url.as_deref() {
+                let notification = commits::CommitNotification {
+                    namespace: &namespace,
+                    name: &name,
+                    table_uri: &self.spec.table_uri,
+                    commit,
+                };
+                if let Err(e) = commits::notify(url, &notification).await {
+                    tracing::warn!("commit callback of {} failed: {}", name, e);
+                }
+            }
+        }
+        if let Some(commit) = commits.last() {
+            self.update_status_ok(
+                api,
+                json!({
+                    "version": latest,
+                    "last_commit_timestamp": commit.timestamp,
+                    "last_operation": commit.operation,
+                }),
             )
-            .await?;
+            .await;
         }
+    }
 
+    /// Loads the table, brings it in line with the spec unless `read_only` is set and
+    /// returns its status
+    async fn load_and_reconcile(
+        &self,
+        api: &Api<DeltaTable>,
+        recorder: &Recorder,
+        storage_options: HashMap<String, String>,
+        read_only: bool,
+    ) -> Result<(SnapshotSummary, serde_json::Value), Error> {
+        let table = self
+            .load_or_create_table(api, recorder, storage_options, read_only)
+            .await?;
+        let table = match read_only {
+            true => table,
+            false => self.reconcile_table(api, recorder, table).await?,
+        };
+        let statistics = match self.spec.statistics.unwrap_or_default() {
+            false => None,
+            true => match TableStatistics::from_table(&table) {
+                Ok(statistics) => Some(TableStatistics {
+                    timestamp: Some(chrono::Utc::now().timestamp()),
+                    ..statistics
+                }),
+                Err(e) => {
+                    tracing::warn!("failed to compute statistics of {}: {}", self.name_any(), e);
+                    None
+                }
+            },
+        };
         let schema_string = table
             .schema()
             .map(|s| serde_json::to_string(&s).unwrap())
             .unwrap_or_default();
-        self.update_status_ok(
-            &api,
-            json!({
-                        "version": table.version(),
-                        "table_uri": table.table_uri(),
-                        "schema": schema_string,
+        let mut status = json!({
+            "version": table.version(),
+            "table_uri": table.table_uri(),
+            "schema": schema_string,
             "is_healthy": true,
-                    }),
+            "protocol": table.protocol().ok().map(protocol::ProtocolStatus::from),
+            "statistics": statistics,
+        });
+        if !read_only {
+            status["observed_generation"] = json!(self.metadata.generation);
+        }
+        Ok((SnapshotSummary::from(&table), status))
+    }
+
+    /// Loads the table at `table_uri` according to the [`CreationPolicy`]
+    ///
+    /// The first time an existing table is seen at a `table_uri` it must match the spec
+    /// before it is adopted, so a mistyped or changed `table_uri` never leads to a table
+    /// being managed, or created, in the wrong place.
+    async fn load_or_create_table(
+        &self,
+        api: &Api<DeltaTable>,
+        recorder: &Recorder,
+        storage_options: HashMap<String, String>,
+        read_only: bool,
+    ) -> Result<DeltaLakeTable, Error> {
+        if let Err(e) = self.extract_config() {
+            let condition = DeltaTableCondition::new(
+                CONDITION_PROPERTIES_APPLIED,
+                false,
+                "UnknownProperty",
+                Some(e.to_string()),
+            );
+            self.update_condition(api, condition).await;
+            self.publish_event(
+                recorder,
+                EventType::Warning,
+                "InvalidProperty",
+                e.to_string(),
+            )
+            .await;
+            return Err(e);
+        }
+        let policy = self.spec.creation_policy.clone().unwrap_or_default();
+        let create = !read_only && policy != CreationPolicy::AdoptOnly;
+        let (table, created) = self.create_delta_table(storage_options, create).await?;
+        let status = self.status.clone().unwrap_or_default();
+        let uri = table.table_uri();
+        let creation = if created {
+            TableCreation::Created
+        } else if let Some(creation) = adoption::recorded_creation(&status, &uri) {
+            if status.creation_uri.is_none() {
+                self.update_status_ok(api, json!({ "creation": creation, "creation_uri": uri }))
+                    .await;
+            }
+            return Ok(table);
+        } else if policy == CreationPolicy::CreateOnly {
+            return Err(Error::TableExists {
+                uri: table.table_uri(),
+            });
+        } else if let Err(reason) = adoption::verify_adoption(&self.spec, &table) {
+            let e = Error::AdoptionMismatch {
+                uri: table.table_uri(),
+                reason,
+            };
+            self.publish_event(
+                recorder,
+                EventType::Warning,
+                "AdoptionFailed",
+                e.to_string(),
+            )
+            .await;
+            return Err(e);
+        } else {
+            TableCreation::Adopted
+        };
+
+        let note = match creation {
+            TableCreation::Created => format!("Created table at {}", table.table_uri()),
+            TableCreation::Adopted => format!("Adopted table at {}", table.table_uri()),
+        };
+        self.publish_event(
+            recorder,
+            EventType::Normal,
+            &format!("{:?}", creation),
+            note,
         )
         .await;
-        Ok(settings
-            .resync_interval
-            .map_or(Action::await_change(), Action::requeue))
+        self.update_status_ok(api, json!({ "creation": creation, "creation_uri": uri }))
+            .await;
+        Ok(table)
+    }
+
+    /// Brings the schema, protocol and properties of `table` in line with the spec
+    async fn reconcile_table(
+        &self,
+        api: &Api<DeltaTable>,
+        recorder: &Recorder,
+        table: DeltaLakeTable,
+    ) -> Result<DeltaLakeTable, Error> {
+        let table = self.reconcile_schema(api, table).await?;
+        let table = self.reconcile_protocol(api, recorder, table).await?;
+        let table = self.reconcile_constraints(api, table).await?;
+        self.reconcile_properties(api, table).await
+    }
+
+    /// Updates the schema of a managed table and reports the outcome in the
+    /// [`CONDITION_SCHEMA_APPLIED`] condition
+    async fn reconcile_schema(
+        &self,
+        api: &Api<DeltaTable>,
+        table: DeltaLakeTable,
+    ) -> Result<DeltaLakeTable, Error> {
+        if !self.spec.schema_settings.manage.unwrap_or_default() {
+            return Ok(table);
+        }
+        let table = match crate::maintenance::update_schema(self, table).await {
+            Ok(table) => table,
+            Err(e) => {
+                let reason = match e {
+                    Error::InvalidColumns { .. } | Error::SchemaJson { .. } => "InvalidSchema",
+                    Error::SchemaUpdate { .. } => "UnsupportedChange",
+                    _ => "UpdateFailed",
+                };
+                let condition = DeltaTableCondition::new(
+                    CONDITION_SCHEMA_APPLIED,
+                    false,
+                    reason,
+                    Some(e.to_string()),
+                );
+                self.update_condition(api, condition).await;
+                return Err(e);
+            }
+        };
+
+        let generated = table.schema().is_some_and(columns::has_generated_columns);
+        let condition = if generated
+            && !protocol::supports(table.protocol()?, &protocol::TableFeature::GeneratedColumns)
+        {
+            DeltaTableCondition::new(
+                CONDITION_SCHEMA_APPLIED,
+                false,
+                "ProtocolUpgradeRequired",
+                Some(
+                    "generation expressions are not enforced until the table supports \
+                     generatedColumns, see spec.protocol"
+                        .to_owned(),
+                ),
+            )
+        } else {
+            DeltaTableCondition::new(CONDITION_SCHEMA_APPLIED, true, "Applied", None)
+        };
+        self.update_condition(api, condition).await;
+        Ok(table)
+    }
+
+    /// Adds and drops constraints to match the spec and reports the outcome in the
+    /// [`CONDITION_CONSTRAINTS_APPLIED`] condition
+    async fn reconcile_constraints(
+        &self,
+        api: &Api<DeltaTable>,
+        mut table: DeltaLakeTable,
+    ) -> Result<DeltaLakeTable, Error> {
+        let status = self.status.clone().unwrap_or_default();
+        let desired = self.spec.constraints.clone().unwrap_or_default();
+        let applied = status.constraints.unwrap_or_default();
+        if desired.is_empty() && applied.is_empty() {
+            return Ok(table);
+        }
+        let known_violations = status.constraint_violations.unwrap_or_default();
+        let existing = constraints::existing(&table)?;
+        let plan = constraints::ConstraintPlan::new(&desired, &applied, &existing);
+
+        let mut failures: Vec<(&str, String)> = Vec::new();
+        let mut violations: Vec<ConstraintViolation> = Vec::new();
+        let mut adds = Vec::new();
+        for constraint in plan.add.iter() {
+            if let Some(violation) = known_violations
+                .iter()
+                .find(|v| &v.constraint == constraint && v.version == table.version())
+            {
+                failures.push(("ConstraintViolated", violation.message.clone()));
+                violations.push(violation.clone());
+            } else if let Err(e) = constraints::validate(&table, &constraint.expression) {
+                failures.push((
+                    "InvalidExpression",
+                    format!("constraint `{}`: {}", constraint.name, e),
+                ));
+            } else {
+                adds.push(constraint.clone());
+            }
+        }
+        let check_constraints = protocol::TableFeature::CheckConstraints;
+        if !adds.is_empty() && !protocol::supports(table.protocol()?, &check_constraints) {
+            for constraint in adds.drain(..) {
+                failures.push((
+                    "ProtocolUpgradeRequired",
+                    format!(
+                        "constraint `{}` needs the checkConstraints feature, see spec.protocol",
+                        constraint.name
+                    ),
+                ));
+            }
+        }
+
+        // Replaced constraints are only dropped when their replacement can be added
+        let mut now_applied: Vec<constraints::TableConstraint> = desired
+            .iter()
+            .filter(|c| !plan.add.contains(c))
+            .cloned()
+            .collect();
+        for name in plan.drop.iter() {
+            let replacement = desired.iter().find(|c| &c.name == name);
+            if replacement.is_some_and(|c| !adds.contains(c)) {
+                now_applied.extend(applied.iter().filter(|c| &c.name == name).cloned());
+                continue;
+            }
+            constraints::drop(&mut table, name).await?;
+        }
+        for constraint in adds.iter() {
+            match constraints::add(&mut table, constraint).await? {
+                Ok(()) => now_applied.push(constraint.clone()),
+                Err(constraints::AddFailure::Violated(violation)) => {
+                    let message = format!(
+                        "rows violate constraint `{}` ({}): {}",
+                        constraint.name, constraint.expression, violation
+                    );
+                    violations.push(ConstraintViolation {
+                        constraint: constraint.clone(),
+                        version: table.version(),
+                        message: message.clone(),
+                    });
+                    failures.push(("ConstraintViolated", message));
+                    // Restore the constraint that was replaced, which the rows satisfied
+                    if let Some(previous) = existing.get(&constraint.name) {
+                        let restored = constraints::TableConstraint {
+                            name: constraint.name.clone(),
+                            expression: previous.clone(),
+                        };
+                        constraints::add(&mut table, &restored).await?.ok();
+                        now_applied.extend(
+                            applied
+                                .iter()
+                                .filter(|c| c.name == constraint.name)
+                                .cloned(),
+                        );
+                    }
+                }
+            }
+        }
+
+        let condition = match failures.first() {
+            None => DeltaTableCondition::new(CONDITION_CONSTRAINTS_APPLIED, true, "Applied", None),
+            Some((reason, _)) => DeltaTableCondition::new(
+                CONDITION_CONSTRAINTS_APPLIED,
+                false,
+                reason,
+                Some(
+                    failures
+                        .iter()
+                        .map(|(_, m)| m.as_str())
+                        .collect::<Vec<_>>()
+                        .join("; "),
+                ),
+            ),
+        };
+        self.update_condition(api, condition).await;
+        if now_applied != applied || violations != known_violations {
+            self.update_status_ok(
+                api,
+                json!({
+                    "constraints": now_applied,
+                    "constraint_violations": violations,
+                }),
+            )
+            .await;
+        }
+        Ok(table)
+    }
+
+    /// Upgrades the protocol when the spec asks for a newer one and allows upgrades, and
+    /// reports the outcome in the [`CONDITION_PROTOCOL_UP_TO_DATE`] condition
+    async fn reconcile_protocol(
+        &self,
+        api: &Api<DeltaTable>,
+        recorder: &Recorder,
+        mut table: DeltaLakeTable,
+    ) -> Result<DeltaLakeTable, Error> {
+        let spec = match self.spec.protocol.as_ref() {
+            Some(spec) => spec,
+            None => return Ok(table),
+        };
+        let current = table.protocol()?.clone();
+        let target = protocol::target(&current, spec);
+        if target == current {
+            let condition =
+                DeltaTableCondition::new(CONDITION_PROTOCOL_UP_TO_DATE, true, "UpToDate", None);
+            self.update_condition(api, condition).await;
+            return Ok(table);
+        }
+
+        let status = protocol::ProtocolStatus::from(&target);
+        let mut message = format!(
+            "upgrade to reader version {} and writer version {}",
+            status.min_reader_version, status.min_writer_version
+        );
+        let locked_out = protocol::locked_out(
+            &current,
+            &target,
+            spec.readers.as_deref().unwrap_or_default(),
+        );
+        if !locked_out.is_empty() {
+            message = format!("{} locks out readers {}", message, locked_out.join(", "));
+        }
+        if !spec.allow_upgrade.unwrap_or_default() {
+            let condition = DeltaTableCondition::new(
+                CONDITION_PROTOCOL_UP_TO_DATE,
+                false,
+                "UpgradeNotAllowed",
+                Some(format!("{}; set allow_upgrade to apply it", message)),
+            );
+            self.update_condition(api, condition).await;
+            return Ok(table);
+        }
+
+        if !locked_out.is_empty() {
+            self.publish_event(
+                recorder,
+                EventType::Warning,
+                "ReadersLockedOut",
+                message.clone(),
+            )
+            .await;
+        }
+        tracing::info!("{} for {}", message, self.name_any());
+        if let Err(e) = protocol::upgrade(&mut table, target).await {
+            let condition = DeltaTableCondition::new(
+                CONDITION_PROTOCOL_UP_TO_DATE,
+                false,
+                "UpgradeFailed",
+                Some(e.to_string()),
+            );
+            self.update_condition(api, condition).await;
+            return Err(e);
+        }
+        self.publish_event(recorder, EventType::Normal, "ProtocolUpgraded", message)
+            .await;
+        let condition =
+            DeltaTableCondition::new(CONDITION_PROTOCOL_UP_TO_DATE, true, "Upgraded", None);
+        self.update_condition(api, condition).await;
+        Ok(table)
+    }
+
+    /// Applies the table properties of the spec that differ from the table's and
+    /// reports the outcome in the [`CONDITION_PROPERTIES_APPLIED`] condition
+    async fn reconcile_properties(
+        &self,
+        api: &Api<DeltaTable>,
+        mut table: DeltaLakeTable,
+    ) -> Result<DeltaLakeTable, Error> {
+        let plan = properties::PropertyPlan::new(
+            &self.extract_config()?,
+            &table.metadata()?.configuration,
+            table.protocol()?,
+        );
+        if !plan.changes.is_empty() {
+            tracing::info!(
+                "setting properties {:?} on {}",
+                plan.changes,
+                self.name_any()
+            );
+            if let Err(e) = properties::set_properties(&mut table, &plan.changes).await {
+                let message = format!("failed to set properties: {}", e);
+                let condition = DeltaTableCondition::new(
+                    CONDITION_PROPERTIES_APPLIED,
+                    false,
+                    "ApplyFailed",
+                    Some(message),
+                );
+                self.update_condition(api, condition).await;
+                return Err(e);
+            }
+        }
+
+        let condition = if !plan.unsupported.is_empty() {
+            DeltaTableCondition::new(
+                CONDITION_PROPERTIES_APPLIED,
+                false,
+                "UnsupportedProperty",
+                Some(format!(
+                    "unsupported properties: {}",
+                    plan.unsupported.join(", ")
+                )),
+            )
+        } else if !plan.requires_upgrade.is_empty() {
+            DeltaTableCondition::new(
+                CONDITION_PROPERTIES_APPLIED,
+                false,
+                "ProtocolUpgradeRequired",
+                Some(format!(
+                    "properties need a newer protocol, see spec.protocol: {}",
+                    plan.requires_upgrade.join(", ")
+                )),
+            )
+        } else {
+            DeltaTableCondition::new(CONDITION_PROPERTIES_APPLIED, true, "Applied", None)
+        };
+        self.update_condition(api, condition).await;
+        Ok(table)
+    }
+
+    /// Sets `condition` in the status, patching only when it changed
+    ///
+    /// Conditions are read back from the API, since `self` does not see conditions set
+    /// earlier in the same reconcile and the whole list is replaced by the patch.
+    async fn update_condition(&self, api: &Api<DeltaTable>, condition: DeltaTableCondition) {
+        let current = self.current_conditions(api).await;
+        let mut conditions = current.clone();
+        set_condition(&mut conditions, condition, &chrono::Utc::now().to_rfc3339());
+        if conditions != current {
+            self.update_status_ok(api, json!({ "conditions": conditions }))
+                .await;
+        }
+    }
+
+    /// Sets the [`CONDITION_READY`] condition from the other conditions, or to `False`
+    /// when the reconcile failed with `error`
+    async fn update_ready(&self, api: &Api<DeltaTable>, error: Option<String>) {
+        let current = self.current_conditions(api).await;
+        self.update_condition(api, ready_condition(&current, error))
+            .await;
+    }
+
+    async fn current_conditions(&self, api: &Api<DeltaTable>) -> Vec<DeltaTableCondition> {
+        match api.get_status(&self.name_any()).await {
+            Ok(latest) => latest.status,
+            Err(e) => {
+                tracing::error!("failed to get status: {}", e);
+                self.status.clone()
+            }
+        }
+        .and_then(|s| s.conditions)
+        .unwrap_or_default()
+    }
+
+    async fn publish_event(
+        &self,
+        recorder: &Recorder,
+        type_: EventType,
+        reason: &str,
+        note: String,
+    ) {
+        if let Err(e) = recorder
+            .publish(Event {
+                type_,
+                reason: reason.into(),
+                note: Some(note),
+                action: "Reconciling".into(),
+                secondary: None,
+            })
+            .await
+        {
+            tracing::error!("failed to publish event: {}", e);
+        }
+    }
+
+    /// Adds the actions of an unhandled [`ANNOTATION_RUN`] request to `due` and returns
+    /// the status acknowledging it
+    async fn take_run_request(
+        &self,
+        recorder: &Recorder,
+        maintenance_order: &[maintenance::Action],
+        due: &mut Vec<maintenance::Action>,
+    ) -> Option<RunRequestStatus> {
+        let request = maintenance::RunRequest::from_annotations(self.annotations())?;
+        let handled = self
+            .status
+            .as_ref()
+            .and_then(|s| s.last_run_request.as_ref())
+            .map(|r| r.id.as_str());
+        let timestamp = chrono::Utc::now().timestamp();
+        match request {
+            Ok(request) if handled == Some(request.id.as_str()) => None,
+            Ok(request) => {
+                for action in request.actions.iter() {
+                    if !due.contains(action) {
+                        due.push(action.clone());
+                    }
+                }
+                maintenance::sort_actions(due, maintenance_order);
+                Some(RunRequestStatus {
+                    id: request.id,
+                    actions: request.actions,
+                    timestamp,
+                    message: None,
+                })
+            }
+            Err((id, _)) if handled == Some(id.as_str()) => None,
+            Err((id, value)) => {
+                let message = format!("unknown maintenance action `{}`", value);
+                self.publish_event(
+                    recorder,
+                    EventType::Warning,
+                    "InvalidRunRequest",
+                    message.clone(),
+                )
+                .await;
+                Some(RunRequestStatus {
+                    id,
+                    actions: Vec::new(),
+                    timestamp,
+                    message: Some(message),
+                })
+            }
+        }
     }
 
     async fn update_status_ok(&self, api: &Api<DeltaTable>, value: serde_json::value::Value) {
@@ -522,12 +1604,14 @@ impl DeltaTable {
         Ok(Action::requeue(std::time::Duration::from_secs(3600)))
     }
 
-    async fn create_job(
+    #[tracing::instrument(name = "create_job", skip_all, fields(actions = %maintenance::join_actions(actions, ",")))]
+    pub(crate) async fn create_job(
         &self,
         client: Client,
-        action: maintenance::Action,
+        actions: &[maintenance::Action],
         namespace: &str,
         settings: &JobSettings,
+        lifecycle: &jobs::JobLifecycle,
         storage_options: &HashMap<String, String>,
     ) -> Result<(), Error> {
         let api: Api<Job> = Api::namespaced(client, &settings.namespace);
@@ -560,135 +1644,259 @@ impl DeltaTable {
                 })
             }
         };
+        if let Some(traceparent) = telemetry::current_traceparent() {
+            env.push(EnvVar {
+                name: telemetry::ENV_TRACEPARENT.into(),
+                value: Some(traceparent),
+                value_from: None,
+            });
+        }
+        if let Some(endpoint) = settings.otlp_endpoint.as_ref() {
+            env.push(EnvVar {
+                name: telemetry::ENV_OTLP_ENDPOINT.into(),
+                value: Some(endpoint.clone()),
+                value_from: None,
+            });
+        }
+        if let Some(url) = settings.pushgateway_url.as_ref() {
+            env.push(EnvVar {
+                name: maintenance::ENV_WORKER_PUSHGATEWAY_URL.into(),
+                value: Some(url.clone()),
+                value_from: None,
+            });
+        }
 
-        match api
-            .create(
-                &pp,
-                &Job {
-                    metadata: kube::core::ObjectMeta {
-                        annotations: Some(settings.annotations.clone()),
-                        labels: Some(settings.labels.clone()),
-                        name: Some(format!("{}-{}", self.name_any(), action).to_lowercase()),
-                        namespace: Some(settings.namespace.clone()),
-                        //                        owner_references: Some(vec![owner]),
-                        ..Default::default()
-                    },
-                    spec: Some(k8s_openapi::api::batch::v1::JobSpec {
-                        template: PodTemplateSpec {
-                            metadata: Some(ObjectMeta {
-                                annotations: Some(settings.annotations.clone()),
-                                labels: Some(settings.labels.clone()),
-                                ..Default::default()
-                            }),
-                            spec: Some(k8s_openapi::api::core::v1::PodSpec {
-                                containers: vec![Container {
-                                    args: Some(vec![
-                                        "--table".into(),
-                                        self.name_unchecked(),
-                                        "--namespace".into(),
-                                        namespace.to_owned(),
-                                        "--action".into(),
-                                        action.to_string().to_lowercase(),
-                                        "--worker-name".into(),
-                                        "delta-maintenance-worker".into(),
-                                    ]),
-                                    env: Some(env),
-                                    image: Some(settings.image.clone()),
-                                    name: "maintenance".into(),
-                                    resources: settings.resource_requirements.clone(),
-                                    ..Default::default()
-                                }],
-                                restart_policy: Some("Never".into()),
-                                service_account_name: Some(settings.service_account.clone()),
-                                ..Default::default()
-                            }),
-                        },
-                        ttl_seconds_after_finished: Some(60),
-                        ..Default::default()
-                    }),
+        let mut job_labels = settings.labels.clone();
+        job_labels.insert(
+            maintenance::LABEL_ACTION.into(),
+            maintenance::join_actions(actions, maintenance::LABEL_ACTION_SEPARATOR),
+        );
+        let mut job_annotations = settings.annotations.clone();
+        job_annotations.insert(
+            maintenance::ANNOTATION_TABLE_NAMESPACE.into(),
+            namespace.to_owned(),
+        );
+        job_annotations.insert(maintenance::ANNOTATION_TABLE_NAME.into(), self.name_any());
+
+        api.create(
+            &pp,
+            &Job {
+                metadata: kube::core::ObjectMeta {
+                    annotations: Some(job_annotations),
+                    labels: Some(job_labels),
+                    generate_name: Some(jobs::name_prefix(
+                        &self.name_any(),
+                        &maintenance::join_actions(actions, "-"),
+                    )),
+                    namespace: Some(settings.namespace.clone()),
+                    // Owners must be in the namespace of the job
+                    owner_references: (settings.namespace == namespace)
+                        .then(|| self.controller_owner_ref(&()))
+                        .flatten()
+                        .map(|owner| vec![owner]),
                     ..Default::default()
                 },
-            )
-            .await
-        {
-            Ok(_) => Ok(()),
-            Err(e) => match e {
-                kube::Error::Api(r) if r.code == 409 => Ok(()),
-                _ => Err(e.into()),
+                spec: Some(k8s_openapi::api::batch::v1::JobSpec {
+                    template: PodTemplateSpec {
+                        metadata: Some(ObjectMeta {
+                            annotations: Some(settings.annotations.clone()),
+                            labels: Some(settings.labels.clone()),
+                            ..Default::default()
+                        }),
+                        spec: Some(k8s_openapi::api::core::v1::PodSpec {
+                            containers: vec![Container {
+                                args: Some(vec![
+                                    "--table".into(),
+                                    self.name_unchecked(),
+                                    "--namespace".into(),
+                                    namespace.to_owned(),
+                                    "--action".into(),
+                                    maintenance::join_actions(actions, ","),
+                                    "--worker-name".into(),
+                                    "delta-maintenance-worker".into(),
+                                ]),
+                                env: Some(env),
+                                image: Some(settings.image.clone()),
+                                name: "maintenance".into(),
+                                resources: settings.resource_requirements.clone(),
+                                termination_message_path: Some(
+                                    failure::TERMINATION_MESSAGE_PATH.into(),
+                                ),
+                                termination_message_policy: Some("FallbackToLogsOnError".into()),
+                                ..Default::default()
+                            }],
+                            restart_policy: Some("Never".into()),
+                            service_account_name: Some(settings.service_account.clone()),
+                            ..Default::default()
+                        }),
+                    },
+                    backoff_limit: lifecycle.backoff_limit,
+                    active_deadline_seconds: lifecycle
+                        .active_deadline
+                        .map(|d| std::time::Duration::from(d).as_secs() as i64),
+                    ttl_seconds_after_finished: lifecycle
+                        .ttl_after_finished
+                        .map(|d| std::time::Duration::from(d).as_secs() as i32),
+                    ..Default::default()
+                }),
+                ..Default::default()
             },
-        }
+        )
+        .await?;
+        Ok(())
     }
 
-    fn requires_vacuum(&self, table: &DeltaLakeTable) -> bool {
-        if let Some(vacuum_configuration) = &self.spec.vacuum_configuration {
-            if let Some(true) = vacuum_configuration.disable {
-                return false;
-            }
+    /// Builds the [`MaintenancePolicy`] configured for `action`, or `None` when the
+    /// action is not configured or disabled
+    fn maintenance_policy(
+        &self,
+        action: &maintenance::Action,
+        defaults: &MaintenanceDefaults,
+    ) -> Option<Box<dyn MaintenancePolicy>> {
+        let (disable, criteria, time_interval, commit_interval) = match action {
+            maintenance::Action::Checkpoint => self
+                .spec
+                .checkpoint_configuration
+                .as_ref()
+                .map(|c| (c.disable, &c.criteria, c.time_interval, c.commit_interval))?,
+            maintenance::Action::Optimize => self
+                .spec
+                .optimize_configuration
+                .as_ref()
+                .map(|c| (c.disable, &c.criteria, c.time_interval, c.commit_interval))?,
+            maintenance::Action::Vacuum => self
+                .spec
+                .vacuum_configuration
+                .as_ref()
+                .map(|c| (c.disable, &c.criteria, c.time_interval, c.commit_interval))?,
+        };
 
-            let default_status = DeltaTableStatus::default();
-            let status = self.status.as_ref().unwrap_or(&default_status);
-            return match_maintenance_criteria!(
-                vacuum_configuration.criteria,
-                status.last_vacuum_timestamp,
-                vacuum_configuration.time_interval,
-                DEFAULT_VACUUM_INTERVAL,
-                status.last_vacuum_commit,
-                table.version(),
-                vacuum_configuration.commit_interval,
-                100
-            );
+        if let Some(true) = disable {
+            return None;
         }
 
-        false
+        Some(criteria_policy(
+            criteria.as_ref(),
+            time_interval.map(|i| i.into()),
+            commit_interval,
+            defaults.for_action(action),
+        ))
     }
 
-    fn requires_optimize(&self, table: &DeltaLakeTable) -> bool {
-        if let Some(config) = &self.spec.optimize_configuration {
-            if let Some(true) = config.disable {
-                return false;
+    pub(crate) fn requires_maintenance(
+        &self,
+        action: &maintenance::Action,
+        snapshot: &SnapshotSummary,
+        defaults: &MaintenanceDefaults,
+        clock: &dyn Clock,
+    ) -> bool {
+        match self.maintenance_policy(action, defaults) {
+            Some(policy) => {
+                let default_status = DeltaTableStatus::default();
+                let status = self.status.as_ref().unwrap_or(&default_status);
+                policy.is_due(action, status, snapshot, clock)
             }
-
-            let default_status = DeltaTableStatus::default();
-            let status = self.status.as_ref().unwrap_or(&default_status);
-            return match_maintenance_criteria!(
-                config.criteria,
-                status.last_optimize_timestamp,
-                config.time_interval,
-                DEFAULT_OPTIMIZE_INTERVAL,
-                status.last_optimize_commit,
-                table.version(),
-                config.commit_interval,
-                50
-            );
+            None => false,
         }
+    }
 
-        false
+    /// Every action due on the table, sorted by `order`
+    pub(crate) fn due_actions(
+        &self,
+        snapshot: &SnapshotSummary,
+        defaults: &MaintenanceDefaults,
+        order: &[maintenance::Action],
+        clock: &dyn Clock,
+    ) -> Vec<maintenance::Action> {
+        let mut due: Vec<maintenance::Action> = maintenance::Action::value_variants()
+            .iter()
+            .filter(|action| self.requires_maintenance(action, snapshot, defaults, clock))
+            .cloned()
+            .collect();
+        maintenance::sort_actions(&mut due, order);
+        due
     }
 
-    fn requires_checkpoint(&self, table: &DeltaLakeTable) -> bool {
-        if let Some(config) = &self.spec.checkpoint_configuration {
-            if let Some(true) = config.disable {
-                return false;
-            }
+    /// The queued `actions` that are still due on the table, or asked for by its run
+    /// request, once they get the capacity to start
+    pub fn still_due(
+        &self,
+        actions: &[maintenance::Action],
+        defaults: &MaintenanceDefaults,
+        clock: &dyn Clock,
+    ) -> Vec<maintenance::Action> {
+        let snapshot = SnapshotSummary {
+            version: self
+                .status
+                .as_ref()
+                .and_then(|s| s.version)
+                .unwrap_or_default(),
+        };
+        let requested = match maintenance::RunRequest::from_annotations(self.annotations()) {
+            Some(Ok(request)) => request.actions,
+            _ => Vec::new(),
+        };
+        actions
+            .iter()
+            .filter(|action| {
+                requested.contains(action)
+                    || self.requires_maintenance(action, &snapshot, defaults, clock)
+            })
+            .cloned()
+            .collect()
+    }
 
-            let default_status = DeltaTableStatus::default();
-            let status = self.status.as_ref().unwrap_or(&default_status);
-            return match_maintenance_criteria!(
-                config.criteria,
-                status.last_checkpoint_timestamp,
-                config.time_interval,
-                DEFAULT_CHECKPOINT_INTERVAL,
-                status.last_checkpoint_commit,
-                table.version(),
-                config.commit_interval,
-                10
-            );
-        }
+    /// Seconds since the unix epoch at which `action` is next due by time, or `None`
+    /// when it is disabled or only scheduled by commits
+    pub fn next_maintenance(
+        &self,
+        action: &maintenance::Action,
+        defaults: &MaintenanceDefaults,
+        clock: &dyn Clock,
+    ) -> Option<i64> {
+        let policy = self.maintenance_policy(action, defaults)?;
+        let default_status = DeltaTableStatus::default();
+        let status = self.status.as_ref().unwrap_or(&default_status);
+        policy.next_due(action, status, clock)
+    }
 
-        false
+    /// The [`jobs::JobLifecycle`] of the job running `actions`, from the settings of
+    /// each action and the controller's `defaults`
+    pub fn job_lifecycle(
+        &self,
+        actions: &[maintenance::Action],
+        defaults: &jobs::JobLifecycle,
+    ) -> jobs::JobLifecycle {
+        let lifecycles: Vec<jobs::JobLifecycle> = actions
+            .iter()
+            .map(|action| {
+                let job = match action {
+                    maintenance::Action::Checkpoint => self
+                        .spec
+                        .checkpoint_configuration
+                        .as_ref()
+                        .and_then(|c| c.job.as_ref()),
+                    maintenance::Action::Optimize => self
+                        .spec
+                        .optimize_configuration
+                        .as_ref()
+                        .and_then(|c| c.job.as_ref()),
+                    maintenance::Action::Vacuum => self
+                        .spec
+                        .vacuum_configuration
+                        .as_ref()
+                        .and_then(|c| c.job.as_ref()),
+                };
+                job.map_or(defaults.clone(), |job| job.or(defaults))
+            })
+            .collect();
+        jobs::JobLifecycle::for_pipeline(&lifecycles)
     }
 
-    async fn accumulate_storage_options(
+    /// Resolves the storage options of the spec along with those referenced from
+    /// Secrets and ConfigMaps in `namespace`
+    #[tracing::instrument(name = "resolve_storage_options", skip_all)]
+    pub async fn accumulate_storage_options(
         &self,
         client: Client,
         namespace: &str,
@@ -781,3 +1989,129 @@ impl DeltaTable {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ready_condition, set_condition, status_time, ColumnMappingMode, DeltaTableCondition,
+        DeltaTableConfiguration, Error, IsolationLevel, CONDITION_READY,
+    };
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_set_condition_transition_time() {
+        let mut conditions = Vec::new();
+        set_condition(
+            &mut conditions,
+            DeltaTableCondition::new("PropertiesApplied", true, "Applied", None),
+            "t1",
+        );
+        set_condition(
+            &mut conditions,
+            DeltaTableCondition::new("PropertiesApplied", true, "Applied", None),
+            "t2",
+        );
+        assert_eq!(1, conditions.len());
+        assert_eq!(Some("t1".to_owned()), conditions[0].last_transition_time);
+
+        set_condition(
+            &mut conditions,
+            DeltaTableCondition::new("PropertiesApplied", false, "UnsupportedProperty", None),
+            "t3",
+        );
+        assert_eq!("False", conditions[0].status);
+        assert_eq!(Some("t3".to_owned()), conditions[0].last_transition_time);
+    }
+
+    #[test]
+    fn test_ready_condition() {
+        let mut conditions = vec![
+            DeltaTableCondition::new("SchemaApplied", true, "Applied", None),
+            DeltaTableCondition::new(CONDITION_READY, false, "ReconcileFailed", None),
+        ];
+        let ready = ready_condition(&conditions, None);
+        assert_eq!("True", ready.status);
+        assert_eq!(Some("Reconciled".to_owned()), ready.reason);
+
+        conditions.push(DeltaTableCondition::new(
+            "ProtocolUpToDate",
+            false,
+            "UpgradeNotAllowed",
+            Some("set allow_upgrade".into()),
+        ));
+        conditions.push(DeltaTableCondition::new(
+            "ConstraintsApplied",
+            false,
+            "ConstraintViolated",
+            None,
+        ));
+        let ready = ready_condition(&conditions, None);
+        assert_eq!("False", ready.status);
+        assert_eq!(Some("UpgradeNotAllowed".to_owned()), ready.reason);
+        assert_eq!(
+            Some("ProtocolUpToDate: set allow_upgrade; ConstraintsApplied".to_owned()),
+            ready.message
+        );
+
+        let ready = ready_condition(&[], Some("table not found".into()));
+        assert_eq!("False", ready.status);
+        assert_eq!(Some("ReconcileFailed".to_owned()), ready.reason);
+    }
+
+    #[test]
+    fn test_status_time() {
+        assert_eq!(
+            Some("2024-01-01T00:00:00+00:00".to_owned()),
+            status_time(Some(1_704_067_200))
+        );
+        assert_eq!(None, status_time(None));
+    }
+
+    #[test]
+    fn test_typed_properties() {
+        let configuration = DeltaTableConfiguration {
+            append_only: Some(true),
+            log_retention_duration: Some("168h".parse().unwrap()),
+            checkpoint_interval: Some(20),
+            data_skipping_stats_columns: Some(vec!["id".into(), "day".into()]),
+            isolation_level: Some(IsolationLevel::WriteSerializable),
+            column_mapping_mode: Some(ColumnMappingMode::Name),
+            additional_settings: Some(HashMap::from([
+                ("delta.appendOnly".to_owned(), "false".to_owned()),
+                ("owner".to_owned(), "data".to_owned()),
+            ])),
+            ..Default::default()
+        };
+        let properties = configuration.properties().unwrap();
+        assert_eq!(
+            vec![
+                ("delta.appendOnly", "true"),
+                ("delta.checkpointInterval", "20"),
+                ("delta.columnMapping.mode", "name"),
+                ("delta.dataSkippingStatsColumns", "id,day"),
+                ("delta.isolationLevel", "WriteSerializable"),
+                ("delta.logRetentionDuration", "interval 1 week"),
+                ("owner", "data"),
+            ],
+            properties
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_unknown_delta_property() {
+        let configuration = DeltaTableConfiguration {
+            additional_settings: Some(HashMap::from([(
+                "delta.logRetentionDuraton".to_owned(),
+                "interval 7 day".to_owned(),
+            )])),
+            ..Default::default()
+        };
+        assert!(matches!(
+            configuration.properties(),
+            Err(Error::UnknownProperty { key }) if key == "delta.logRetentionDuraton"
+        ));
+    }
+}
@@ -1,19 +1,42 @@
 //! A collection of maintenance settings and functions for delta table maintenance
-use std::{collections::BTreeMap, fmt::Display, sync::Arc, time::Duration};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    sync::Arc,
+    time::Duration,
+};
 
-use crate::{DeltaLakeTable, DeltaTable, Error};
+use crate::{
+    columns,
+    jobs::{self, JobLifecycle},
+    policy::MaintenanceDefaults,
+    DeltaLakeTable, DeltaTable, Error,
+};
+use async_trait::async_trait;
 use clap::ValueEnum;
-use deltalake::{
-    arrow::record_batch::RecordBatch,
-    kernel::Schema,
-    writer::{DeltaWriter, RecordBatchWriter},
-    DeltaOps,
+use deltalake::DeltaOps;
+use k8s_openapi::api::{batch::v1::Job, core::v1::ResourceRequirements};
+use kube::{
+    api::{ListParams, Patch, PatchParams},
+    runtime::events::{Event, EventType, Recorder},
+    Api, Client, ResourceExt,
 };
-use k8s_openapi::api::core::v1::ResourceRequirements;
-use kube::ResourceExt;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 
 pub const ENV_WORKER_POD_NAME: &str = "CONTROLLER_POD_NAME";
+/// Pushgateway the worker pushes its metrics to before it exits
+pub const ENV_WORKER_PUSHGATEWAY_URL: &str = "PUSHGATEWAY_URL";
+/// Histogram of the duration of each maintenance action, by outcome
+pub const METRIC_MAINTENANCE_DURATION: &str = "delta_table_maintenance_duration_seconds";
+/// Counter of maintenance actions, by outcome
+pub const METRIC_MAINTENANCE_TOTAL: &str = "delta_table_maintenance_total";
+pub const LABEL_ACTION: &str = "delta-operator.rs/action";
+/// Separates the actions of a pipeline in the [`LABEL_ACTION`] label
+pub const LABEL_ACTION_SEPARATOR: &str = "-";
+pub const ANNOTATION_TABLE_NAME: &str = "delta-operator.rs/table-name";
+pub const ANNOTATION_TABLE_NAMESPACE: &str = "delta-operator.rs/table-namespace";
 
 /// Represents settings for a maintenance job
 pub struct JobSettings {
@@ -24,16 +47,32 @@ pub struct JobSettings {
     pub service_account: String,
     pub resource_requirements: Option<ResourceRequirements>,
     pub resync_interval: Option<Duration>,
+    pub maintenance_defaults: MaintenanceDefaults,
+    /// Order in which due actions run within a table's maintenance pipeline
+    pub maintenance_order: Vec<Action>,
+    /// Report status without creating tables, updating schemas or running maintenance
+    pub dry_run: bool,
+    /// Prometheus Pushgateway that workers push their metrics to before they exit
+    pub pushgateway_url: Option<String>,
+    /// OTLP endpoint that workers export their spans to
+    pub otlp_endpoint: Option<String>,
+    /// Lifecycle of worker jobs for actions that do not set their own
+    pub job_lifecycle: JobLifecycle,
 }
 
 /// Action options for maintenance
-#[derive(Deserialize, Serialize, Clone, Debug, ValueEnum, PartialEq)]
+#[derive(Deserialize, Serialize, Clone, Debug, ValueEnum, PartialEq, Eq, Hash, JsonSchema)]
 pub enum Action {
     Checkpoint,
     Optimize,
     Vacuum,
 }
 
+/// The default [`JobSettings::maintenance_order`]: compact first so the checkpoint
+/// covers the optimized files and vacuum removes what optimize replaced
+pub const DEFAULT_MAINTENANCE_ORDER: [Action; 3] =
+    [Action::Optimize, Action::Checkpoint, Action::Vacuum];
+
 impl Display for Action {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -44,6 +83,267 @@ impl Display for Action {
     }
 }
 
+/// A request to run maintenance now, made with the [`crate::ANNOTATION_RUN`] annotation
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunRequest {
+    pub id: String,
+    pub actions: Vec<Action>,
+}
+
+impl RunRequest {
+    /// Reads the request from `annotations`. The id comes from [`crate::ANNOTATION_RUN_ID`],
+    /// falling back to the value of [`crate::ANNOTATION_RUN`].
+    ///
+    /// Returns `Some(Err(..))` with the request id and the invalid value when an action
+    /// cannot be parsed.
+    pub fn from_annotations(
+        annotations: &BTreeMap<String, String>,
+    ) -> Option<Result<Self, (String, String)>> {
+        let value = annotations.get(crate::ANNOTATION_RUN)?;
+        let id = annotations
+            .get(crate::ANNOTATION_RUN_ID)
+            .unwrap_or(value)
+            .clone();
+        let mut actions = Vec::new();
+        for part in value.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            match Action::from_str(part, true) {
+                Ok(action) if !actions.contains(&action) => actions.push(action),
+                Ok(_) => {}
+                Err(_) => return Some(Err((id, part.to_owned()))),
+            }
+        }
+
+        Some(Ok(Self { id, actions }))
+    }
+}
+
+/// Sorts `actions` by their position in `order`; actions not listed go last
+pub fn sort_actions(actions: &mut [Action], order: &[Action]) {
+    actions.sort_by_key(|a| order.iter().position(|o| o == a).unwrap_or(order.len()));
+}
+
+/// Joins `actions` with `separator` in their lowercase command line form
+pub fn join_actions(actions: &[Action], separator: &str) -> String {
+    actions
+        .iter()
+        .map(|a| a.to_string().to_lowercase())
+        .collect::<Vec<String>>()
+        .join(separator)
+}
+
+/// Maintenance that an executor has started and not yet finished
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunningMaintenance {
+    pub namespace: String,
+    pub table: String,
+    pub actions: Vec<Action>,
+}
+
+/// Runs maintenance actions that [`DeltaTable::reconcile`] decided are due
+#[async_trait]
+pub trait MaintenanceExecutor: Send + Sync {
+    /// Starts `actions` for `doc`, to be run in order as one pipeline.
+    /// Implementations may return before the actions complete.
+    async fn execute(
+        &self,
+        doc: &DeltaTable,
+        actions: Vec<Action>,
+        storage_options: &HashMap<String, String>,
+    ) -> Result<(), Error>;
+
+    /// Lists the maintenance started by this executor that has not finished
+    async fn running(&self) -> Result<Vec<RunningMaintenance>, Error>;
+
+    /// Drops maintenance of the table `namespace/table` that has not started yet
+    async fn cancel(&self, _namespace: &str, _table: &str) {}
+
+    /// Drops maintenance of the tables of `namespace` that has not started yet, once the
+    /// namespace is no longer watched
+    async fn cancel_namespace(&self, _namespace: &str) {}
+
+    /// Stops accepting work and waits for or cancels running actions
+    async fn shutdown(&self) {}
+}
+
+/// [`MaintenanceExecutor`] that creates a worker [`k8s_openapi::api::batch::v1::Job`] per pipeline
+pub struct JobExecutor {
+    client: Client,
+    settings: Arc<JobSettings>,
+}
+
+impl JobExecutor {
+    pub fn new(client: Client, settings: Arc<JobSettings>) -> Self {
+        Self { client, settings }
+    }
+}
+
+#[async_trait]
+impl MaintenanceExecutor for JobExecutor {
+    async fn execute(
+        &self,
+        doc: &DeltaTable,
+        actions: Vec<Action>,
+        storage_options: &HashMap<String, String>,
+    ) -> Result<(), Error> {
+        let namespace = doc.namespace().unwrap();
+        let lifecycle = doc.job_lifecycle(&actions, &self.settings.job_lifecycle);
+        doc.create_job(
+            self.client.clone(),
+            &actions,
+            &namespace,
+            &self.settings,
+            &lifecycle,
+            storage_options,
+        )
+        .await?;
+
+        let api: Api<Job> = Api::namespaced(self.client.clone(), &self.settings.namespace);
+        if let Err(e) = jobs::collect_garbage(&api, &namespace, &doc.name_any(), &lifecycle).await {
+            tracing::error!(
+                "failed to delete old jobs of {}/{}: {}",
+                namespace,
+                doc.name_any(),
+                e
+            );
+        }
+        Ok(())
+    }
+
+    async fn running(&self) -> Result<Vec<RunningMaintenance>, Error> {
+        let api: Api<Job> = Api::namespaced(self.client.clone(), &self.settings.namespace);
+        let jobs = api
+            .list(&ListParams::default().labels(LABEL_ACTION))
+            .await?;
+        Ok(jobs
+            .items
+            .into_iter()
+            .filter(|job| jobs::job_succeeded(job).is_none())
+            .filter_map(|job| {
+                let annotations = job.metadata.annotations.as_ref()?;
+                let actions = job.metadata.labels.as_ref()?.get(LABEL_ACTION)?;
+                Some(RunningMaintenance {
+                    namespace: annotations.get(ANNOTATION_TABLE_NAMESPACE)?.clone(),
+                    table: annotations.get(ANNOTATION_TABLE_NAME)?.clone(),
+                    actions: actions
+                        .split(LABEL_ACTION_SEPARATOR)
+                        .filter_map(|a| Action::from_str(a, true).ok())
+                        .collect(),
+                })
+            })
+            .collect())
+    }
+}
+
+/// Runs `action` against `delta_lake_table` and returns the resulting table along with
+/// the status patch recording it
+#[tracing::instrument(name = "maintenance", skip_all, fields(table = %doc.name_any(), action = %action))]
+pub async fn perform_maintenance(
+    doc: &DeltaTable,
+    delta_lake_table: DeltaLakeTable,
+    action: &Action,
+) -> Result<(DeltaLakeTable, serde_json::Value), Error> {
+    match action {
+        Action::Checkpoint => checkpoint_table(doc, delta_lake_table).await.map(|dt| {
+            let patch = json!({
+                "status": {
+                    "last_checkpoint_commit": dt.version(),
+                    "last_checkpoint_timestamp": chrono::Utc::now().timestamp(),
+                },
+            });
+            (dt, patch)
+        }),
+        Action::Optimize => optimize_table(doc, delta_lake_table).await.map(|dt| {
+            let patch = json!({
+                "status": {
+                    "last_optimize_commit": dt.version(),
+                    "last_optimize_timestamp": chrono::Utc::now().timestamp(),
+                }
+            });
+            (dt, patch)
+        }),
+        Action::Vacuum => vacuum_table(doc, delta_lake_table).await.map(|dt| {
+            let patch = json!({
+                "status": {
+                    "last_vacuum_commit": dt.version(),
+                    "last_vacuum_timestamp": chrono::Utc::now().timestamp(),
+                }
+            });
+            (dt, patch)
+        }),
+    }
+}
+
+/// Runs `actions` in order on the same `delta_lake_table`, publishing the status of each
+/// step with [`publish_success`]. Stops at the first action that fails.
+pub async fn perform_pipeline(
+    api: &Api<DeltaTable>,
+    recorder: &Recorder,
+    doc: &DeltaTable,
+    mut delta_lake_table: DeltaLakeTable,
+    actions: &[Action],
+) -> Result<DeltaLakeTable, Error> {
+    let name = doc.name_any();
+    for action in actions {
+        let started = std::time::Instant::now();
+        let step = async {
+            let (table, value) = perform_maintenance(doc, delta_lake_table, action).await?;
+            publish_success(api, recorder, &name, action, value).await?;
+            Ok(table)
+        };
+        let result = step.await;
+        record_maintenance(doc, action, started.elapsed(), result.is_ok());
+        delta_lake_table = result.map_err(|e: Error| Error::Maintenance {
+            action: action.clone(),
+            source: Box::new(e),
+        })?;
+    }
+
+    Ok(delta_lake_table)
+}
+
+/// Records the duration and outcome of `action` on `doc` in
+/// [`METRIC_MAINTENANCE_DURATION`] and [`METRIC_MAINTENANCE_TOTAL`]
+fn record_maintenance(doc: &DeltaTable, action: &Action, duration: Duration, success: bool) {
+    let labels = [
+        ("table", doc.name_any()),
+        ("namespace", doc.namespace().unwrap_or_default()),
+        ("action", action.to_string().to_lowercase()),
+        (
+            "outcome",
+            if success { "success" } else { "failure" }.to_owned(),
+        ),
+    ];
+    metrics::histogram!(METRIC_MAINTENANCE_DURATION, duration.as_secs_f64(), &labels);
+    metrics::increment_counter!(METRIC_MAINTENANCE_TOTAL, &labels);
+}
+
+/// Patches the status of the [`DeltaTable`] named `name` with the result of
+/// [`perform_maintenance`] and publishes an event for it
+pub async fn publish_success(
+    api: &Api<DeltaTable>,
+    recorder: &Recorder,
+    name: &str,
+    action: &Action,
+    value: serde_json::Value,
+) -> Result<(), Error> {
+    let pp = PatchParams::default();
+    api.patch_status(name, &pp, &Patch::Merge(value)).await?;
+    if let Err(e) = recorder
+        .publish(Event {
+            type_: EventType::Normal,
+            reason: format!("{} requested by controller", action),
+            note: Some("Success".into()),
+            action: format!("{}", action),
+            secondary: None,
+        })
+        .await
+    {
+        tracing::error!("failed to record delta table changes: {}", e);
+    }
+
+    Ok(())
+}
+
 /// Performs an optimize action on the specified [`deltalake::DeltaTable`] resource
 /// using the [`DeltaTable`] provided to access it
 pub async fn optimize_table(
@@ -51,6 +351,12 @@ pub async fn optimize_table(
     table: DeltaLakeTable,
 ) -> Result<DeltaLakeTable, Error> {
     let result = DeltaOps(table).optimize().await?;
+    tracing::info!(
+        files_added = result.1.num_files_added,
+        files_removed = result.1.num_files_removed,
+        "optimized {}",
+        doc.name_any()
+    );
     metrics::increment_counter!(
         "optimize_executed_count",
         "table" => doc.name_any(),
@@ -95,7 +401,12 @@ pub async fn vacuum_table(
             vacuum = vacuum.with_retention_period(duration);
         }
     }
-    let (result, _) = vacuum.await?;
+    let (result, vacuumed) = vacuum.await?;
+    tracing::info!(
+        files_deleted = vacuumed.files_deleted.len(),
+        "vacuumed {}",
+        doc.name_any()
+    );
     metrics::increment_counter!(
         "vacuum_executed_count",
         "table" => doc.name_any(),
@@ -106,9 +417,13 @@ pub async fn vacuum_table(
 
 /// Updates the schema [`deltalake::DeltaTable`] resource using the `schema` property of
 /// the [`DeltaTable`] provided to access it
+///
+/// New columns are added and column metadata is replaced with the spec's, see
+/// [`columns::merge`]; nothing is committed when the schema already matches.
+#[tracing::instrument(name = "update_schema", skip_all)]
 pub(crate) async fn update_schema(
     doc: &DeltaTable,
-    table: DeltaLakeTable,
+    mut table: DeltaLakeTable,
 ) -> Result<DeltaLakeTable, Error> {
     match doc.spec.schema_settings.manage.as_ref() {
         Some(true) => {}
@@ -119,15 +434,68 @@ pub(crate) async fn update_schema(
         None => return Ok(table),
     };
 
-    let schema: Schema = serde_json::de::from_str(&doc.spec.schema_settings.value)?;
+    let schema = columns::merge(current_schema, &doc.spec.schema_settings.schema()?)
+        .map_err(|reason| Error::SchemaUpdate { reason })?;
     if current_schema == &schema {
         return Ok(table);
     }
-    let mut writer = RecordBatchWriter::for_table(&table)?;
-    let arrow_schema = deltalake::arrow::datatypes::Schema::try_from(&schema)?;
-    let record_batch = RecordBatch::new_empty(Arc::new(arrow_schema));
-    writer
-        .write_with_mode(record_batch, deltalake::writer::WriteMode::MergeSchema)
-        .await?;
+    columns::set_schema(&mut table, &schema).await?;
     Ok(table)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{sort_actions, Action, RunRequest};
+    use crate::{ANNOTATION_RUN, ANNOTATION_RUN_ID};
+
+    #[test]
+    fn test_run_request_missing() {
+        assert!(RunRequest::from_annotations(&BTreeMap::new()).is_none());
+        let annotations = BTreeMap::from([(ANNOTATION_RUN_ID.to_owned(), "1".to_owned())]);
+        assert!(RunRequest::from_annotations(&annotations).is_none());
+    }
+
+    #[test]
+    fn test_run_request_with_id() {
+        let annotations = BTreeMap::from([
+            (
+                ANNOTATION_RUN.to_owned(),
+                "optimize, Vacuum,optimize".to_owned(),
+            ),
+            (ANNOTATION_RUN_ID.to_owned(), "req-1".to_owned()),
+        ]);
+        let request = RunRequest::from_annotations(&annotations).unwrap().unwrap();
+        assert_eq!("req-1", request.id);
+        assert_eq!(vec![Action::Optimize, Action::Vacuum], request.actions);
+    }
+
+    #[test]
+    fn test_run_request_without_id() {
+        let annotations = BTreeMap::from([(ANNOTATION_RUN.to_owned(), "checkpoint".to_owned())]);
+        let request = RunRequest::from_annotations(&annotations).unwrap().unwrap();
+        assert_eq!("checkpoint", request.id);
+        assert_eq!(vec![Action::Checkpoint], request.actions);
+    }
+
+    #[test]
+    fn test_run_request_invalid_action() {
+        let annotations = BTreeMap::from([
+            (ANNOTATION_RUN.to_owned(), "optimize,compact".to_owned()),
+            (ANNOTATION_RUN_ID.to_owned(), "req-2".to_owned()),
+        ]);
+        let result = RunRequest::from_annotations(&annotations).unwrap();
+        assert_eq!(Err(("req-2".to_owned(), "compact".to_owned())), result);
+    }
+
+    #[test]
+    fn test_sort_actions() {
+        let mut actions = vec![Action::Vacuum, Action::Checkpoint, Action::Optimize];
+        sort_actions(&mut actions, &[Action::Optimize, Action::Vacuum]);
+        assert_eq!(
+            vec![Action::Optimize, Action::Vacuum, Action::Checkpoint],
+            actions
+        );
+    }
+}
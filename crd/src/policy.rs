@@ -0,0 +1,818 @@
+//! Policies that decide when a maintenance [`Action`] is due for a table
+use std::time::Duration;
+
+use crate::{maintenance::Action, DeltaLakeTable, DeltaTableStatus, MaintenanceCriteria};
+
+// 30 days
+pub const DEFAULT_VACUUM_INTERVAL: Duration = Duration::from_secs(2_592_000);
+// 24 hours
+pub const DEFAULT_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(86_400);
+// 24 hours
+pub const DEFAULT_OPTIMIZE_INTERVAL: Duration = Duration::from_secs(86_400);
+pub const DEFAULT_VACUUM_COMMIT_INTERVAL: i64 = 100;
+pub const DEFAULT_OPTIMIZE_COMMIT_INTERVAL: i64 = 50;
+pub const DEFAULT_CHECKPOINT_COMMIT_INTERVAL: i64 = 10;
+
+/// Source of the current time for policy evaluation
+pub trait Clock: Send + Sync {
+    /// Seconds since the unix epoch
+    fn timestamp(&self) -> i64;
+}
+
+/// [`Clock`] backed by the system time
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn timestamp(&self) -> i64 {
+        chrono::Utc::now().timestamp()
+    }
+}
+
+/// [`Clock`] that always returns the same timestamp
+#[derive(Clone, Copy, Debug)]
+pub struct FixedClock(pub i64);
+
+impl Clock for FixedClock {
+    fn timestamp(&self) -> i64 {
+        self.0
+    }
+}
+
+/// The parts of a loaded table that policies are evaluated against
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SnapshotSummary {
+    pub version: i64,
+}
+
+impl From<&DeltaLakeTable> for SnapshotSummary {
+    fn from(table: &DeltaLakeTable) -> Self {
+        Self {
+            version: table.version(),
+        }
+    }
+}
+
+/// Decides whether a maintenance action should run
+pub trait MaintenancePolicy: Send + Sync {
+    fn is_due(
+        &self,
+        action: &Action,
+        status: &DeltaTableStatus,
+        snapshot: &SnapshotSummary,
+        clock: &dyn Clock,
+    ) -> bool;
+
+    /// Seconds since the unix epoch at which the action becomes due by time, or `None`
+    /// when the policy does not schedule it by time
+    fn next_due(
+        &self,
+        _action: &Action,
+        _status: &DeltaTableStatus,
+        _clock: &dyn Clock,
+    ) -> Option<i64> {
+        None
+    }
+}
+
+/// Due when `interval` has elapsed since the action last ran
+#[derive(Clone, Debug)]
+pub struct TimeIntervalPolicy {
+    pub interval: Duration,
+}
+
+impl MaintenancePolicy for TimeIntervalPolicy {
+    fn is_due(
+        &self,
+        action: &Action,
+        status: &DeltaTableStatus,
+        _: &SnapshotSummary,
+        clock: &dyn Clock,
+    ) -> bool {
+        match status.last_maintenance_timestamp(action) {
+            // A timestamp in the future (clock skew) counts as no time elapsed
+            Some(last_timestamp) => {
+                let elapsed = clock.timestamp().saturating_sub(last_timestamp).max(0);
+                Duration::from_secs(elapsed as u64) >= self.interval
+            }
+            None => true,
+        }
+    }
+
+    fn next_due(
+        &self,
+        action: &Action,
+        status: &DeltaTableStatus,
+        clock: &dyn Clock,
+    ) -> Option<i64> {
+        match status.last_maintenance_timestamp(action) {
+            Some(last_timestamp) => {
+                Some(last_timestamp.saturating_add(self.interval.as_secs() as i64))
+            }
+            None => Some(clock.timestamp()),
+        }
+    }
+}
+
+/// Due when `interval` commits have been made since the action last ran
+#[derive(Clone, Debug)]
+pub struct CommitIntervalPolicy {
+    pub interval: i64,
+}
+
+impl MaintenancePolicy for CommitIntervalPolicy {
+    fn is_due(
+        &self,
+        action: &Action,
+        status: &DeltaTableStatus,
+        snapshot: &SnapshotSummary,
+        _: &dyn Clock,
+    ) -> bool {
+        match status.last_maintenance_commit(action) {
+            // The table was restored or recreated below the recorded commit,
+            // so the recorded commit no longer describes this history
+            Some(last_commit) if snapshot.version < last_commit => true,
+            Some(last_commit) => snapshot.version - last_commit >= self.interval,
+            None => true,
+        }
+    }
+}
+
+/// Due when any of the contained policies is due
+pub struct AnyPolicy {
+    policies: Vec<Box<dyn MaintenancePolicy>>,
+}
+
+impl AnyPolicy {
+    pub fn new(policies: Vec<Box<dyn MaintenancePolicy>>) -> Self {
+        Self { policies }
+    }
+}
+
+impl MaintenancePolicy for AnyPolicy {
+    fn is_due(
+        &self,
+        action: &Action,
+        status: &DeltaTableStatus,
+        snapshot: &SnapshotSummary,
+        clock: &dyn Clock,
+    ) -> bool {
+        self.policies
+            .iter()
+            .any(|p| p.is_due(action, status, snapshot, clock))
+    }
+
+    fn next_due(
+        &self,
+        action: &Action,
+        status: &DeltaTableStatus,
+        clock: &dyn Clock,
+    ) -> Option<i64> {
+        self.policies
+            .iter()
+            .filter_map(|p| p.next_due(action, status, clock))
+            .min()
+    }
+}
+
+/// Intervals used when a table does not specify its own
+#[derive(Clone, Debug, PartialEq)]
+pub struct PolicyDefaults {
+    pub time_interval: Duration,
+    pub commit_interval: i64,
+}
+
+/// [`PolicyDefaults`] for each maintenance [`Action`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaintenanceDefaults {
+    pub checkpoint: PolicyDefaults,
+    pub optimize: PolicyDefaults,
+    pub vacuum: PolicyDefaults,
+}
+
+impl Default for MaintenanceDefaults {
+    fn default() -> Self {
+        Self {
+            checkpoint: PolicyDefaults {
+                time_interval: DEFAULT_CHECKPOINT_INTERVAL,
+                commit_interval: DEFAULT_CHECKPOINT_COMMIT_INTERVAL,
+            },
+            optimize: PolicyDefaults {
+                time_interval: DEFAULT_OPTIMIZE_INTERVAL,
+                commit_interval: DEFAULT_OPTIMIZE_COMMIT_INTERVAL,
+            },
+            vacuum: PolicyDefaults {
+                time_interval: DEFAULT_VACUUM_INTERVAL,
+                commit_interval: DEFAULT_VACUUM_COMMIT_INTERVAL,
+            },
+        }
+    }
+}
+
+impl MaintenanceDefaults {
+    pub fn for_action(&self, action: &Action) -> &PolicyDefaults {
+        match action {
+            Action::Checkpoint => &self.checkpoint,
+            Action::Optimize => &self.optimize,
+            Action::Vacuum => &self.vacuum,
+        }
+    }
+}
+
+/// Builds the policy described by a table's [`MaintenanceCriteria`], falling back to
+/// `defaults` for intervals that are not set
+pub fn criteria_policy(
+    criteria: Option<&MaintenanceCriteria>,
+    time_interval: Option<Duration>,
+    commit_interval: Option<i32>,
+    defaults: &PolicyDefaults,
+) -> Box<dyn MaintenancePolicy> {
+    let time = TimeIntervalPolicy {
+        interval: time_interval.unwrap_or(defaults.time_interval),
+    };
+    let commit = CommitIntervalPolicy {
+        interval: commit_interval
+            .map(i64::from)
+            .unwrap_or(defaults.commit_interval),
+    };
+    match criteria.unwrap_or(&MaintenanceCriteria::Time) {
+        MaintenanceCriteria::Time => Box::new(time),
+        MaintenanceCriteria::Commit => Box::new(commit),
+        MaintenanceCriteria::TimeAndCommit => {
+            Box::new(AnyPolicy::new(vec![Box::new(time), Box::new(commit)]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        DeltaTable, DeltaTableMaintenceConfiguration, DeltaTableSpec, DeltaTableVacuumConfiguration,
+    };
+
+    const NOW: i64 = 1_700_000_000;
+    const HOUR: i64 = 3_600;
+
+    #[test]
+    fn test_time_policy_never_run() {
+        let policy = TimeIntervalPolicy {
+            interval: Duration::from_secs(3_600),
+        };
+        assert!(policy.is_due(
+            &Action::Optimize,
+            &DeltaTableStatus {
+                last_checkpoint_commit: None,
+                last_checkpoint_timestamp: None,
+                last_optimize_commit: None,
+                last_optimize_timestamp: None,
+                last_vacuum_commit: None,
+                last_vacuum_timestamp: None,
+                ..Default::default()
+            },
+            &SnapshotSummary { version: 0 },
+            &FixedClock(NOW)
+        ));
+    }
+
+    #[test]
+    fn test_time_policy_boundaries() {
+        let policy = TimeIntervalPolicy {
+            interval: Duration::from_secs(3_600),
+        };
+        let s = DeltaTableStatus {
+            last_checkpoint_commit: None,
+            last_checkpoint_timestamp: Some(NOW - HOUR),
+            last_optimize_commit: None,
+            last_optimize_timestamp: Some(NOW - HOUR),
+            last_vacuum_commit: None,
+            last_vacuum_timestamp: Some(NOW - HOUR),
+            ..Default::default()
+        };
+        assert!(policy.is_due(
+            &Action::Optimize,
+            &s,
+            &SnapshotSummary { version: 0 },
+            &FixedClock(NOW)
+        ));
+        assert!(!policy.is_due(
+            &Action::Optimize,
+            &s,
+            &SnapshotSummary { version: 0 },
+            &FixedClock(NOW - 1)
+        ));
+    }
+
+    #[test]
+    fn test_time_policy_future_timestamp() {
+        let policy = TimeIntervalPolicy {
+            interval: Duration::from_secs(3_600),
+        };
+        let s = DeltaTableStatus {
+            last_checkpoint_commit: None,
+            last_checkpoint_timestamp: Some(NOW + HOUR),
+            last_optimize_commit: None,
+            last_optimize_timestamp: Some(NOW + HOUR),
+            last_vacuum_commit: None,
+            last_vacuum_timestamp: Some(NOW + HOUR),
+            ..Default::default()
+        };
+        assert!(!policy.is_due(
+            &Action::Vacuum,
+            &s,
+            &SnapshotSummary { version: 0 },
+            &FixedClock(NOW)
+        ));
+    }
+
+    #[test]
+    fn test_time_policy_zero_interval() {
+        let policy = TimeIntervalPolicy {
+            interval: Duration::ZERO,
+        };
+        let s = DeltaTableStatus {
+            last_checkpoint_commit: None,
+            last_checkpoint_timestamp: Some(NOW),
+            last_optimize_commit: None,
+            last_optimize_timestamp: Some(NOW),
+            last_vacuum_commit: None,
+            last_vacuum_timestamp: Some(NOW),
+            ..Default::default()
+        };
+        assert!(policy.is_due(
+            &Action::Checkpoint,
+            &s,
+            &SnapshotSummary { version: 0 },
+            &FixedClock(NOW)
+        ));
+    }
+
+    #[test]
+    fn test_commit_policy_never_run() {
+        let policy = CommitIntervalPolicy { interval: 10 };
+        assert!(policy.is_due(
+            &Action::Checkpoint,
+            &DeltaTableStatus {
+                last_checkpoint_commit: None,
+                last_checkpoint_timestamp: None,
+                last_optimize_commit: None,
+                last_optimize_timestamp: None,
+                last_vacuum_commit: None,
+                last_vacuum_timestamp: None,
+                ..Default::default()
+            },
+            &SnapshotSummary { version: 0 },
+            &FixedClock(NOW)
+        ));
+    }
+
+    #[test]
+    fn test_commit_policy_boundaries() {
+        let policy = CommitIntervalPolicy { interval: 10 };
+        let s = DeltaTableStatus {
+            last_checkpoint_commit: Some(5),
+            last_checkpoint_timestamp: None,
+            last_optimize_commit: Some(5),
+            last_optimize_timestamp: None,
+            last_vacuum_commit: Some(5),
+            last_vacuum_timestamp: None,
+            ..Default::default()
+        };
+        assert!(!policy.is_due(
+            &Action::Checkpoint,
+            &s,
+            &SnapshotSummary { version: 14 },
+            &FixedClock(NOW)
+        ));
+        assert!(policy.is_due(
+            &Action::Checkpoint,
+            &s,
+            &SnapshotSummary { version: 15 },
+            &FixedClock(NOW)
+        ));
+    }
+
+    #[test]
+    fn test_commit_policy_version_went_backwards() {
+        let policy = CommitIntervalPolicy { interval: 10 };
+        let s = DeltaTableStatus {
+            last_checkpoint_commit: Some(50),
+            last_checkpoint_timestamp: None,
+            last_optimize_commit: Some(50),
+            last_optimize_timestamp: None,
+            last_vacuum_commit: Some(50),
+            last_vacuum_timestamp: None,
+            ..Default::default()
+        };
+        assert!(policy.is_due(
+            &Action::Optimize,
+            &s,
+            &SnapshotSummary { version: 3 },
+            &FixedClock(NOW)
+        ));
+    }
+
+    #[test]
+    fn test_commit_policy_ignores_clock() {
+        let policy = CommitIntervalPolicy { interval: 10 };
+        let s = DeltaTableStatus {
+            last_checkpoint_commit: Some(5),
+            last_checkpoint_timestamp: Some(0),
+            last_optimize_commit: Some(5),
+            last_optimize_timestamp: Some(0),
+            last_vacuum_commit: Some(5),
+            last_vacuum_timestamp: Some(0),
+            ..Default::default()
+        };
+        assert!(!policy.is_due(
+            &Action::Optimize,
+            &s,
+            &SnapshotSummary { version: 6 },
+            &FixedClock(i64::MAX)
+        ));
+    }
+
+    #[test]
+    fn test_any_policy() {
+        let policy = AnyPolicy::new(vec![
+            Box::new(TimeIntervalPolicy {
+                interval: Duration::from_secs(3_600),
+            }),
+            Box::new(CommitIntervalPolicy { interval: 10 }),
+        ]);
+        let s = DeltaTableStatus {
+            last_checkpoint_commit: Some(0),
+            last_checkpoint_timestamp: Some(NOW),
+            last_optimize_commit: Some(0),
+            last_optimize_timestamp: Some(NOW),
+            last_vacuum_commit: Some(0),
+            last_vacuum_timestamp: Some(NOW),
+            ..Default::default()
+        };
+        assert!(!policy.is_due(
+            &Action::Vacuum,
+            &s,
+            &SnapshotSummary { version: 1 },
+            &FixedClock(NOW)
+        ));
+        assert!(policy.is_due(
+            &Action::Vacuum,
+            &s,
+            &SnapshotSummary { version: 10 },
+            &FixedClock(NOW)
+        ));
+        assert!(policy.is_due(
+            &Action::Vacuum,
+            &s,
+            &SnapshotSummary { version: 1 },
+            &FixedClock(NOW + HOUR)
+        ));
+        assert!(!AnyPolicy::new(vec![]).is_due(
+            &Action::Vacuum,
+            &s,
+            &SnapshotSummary { version: 1 },
+            &FixedClock(NOW)
+        ));
+    }
+
+    #[test]
+    fn test_next_due() {
+        let time = TimeIntervalPolicy {
+            interval: Duration::from_secs(3_600),
+        };
+        let commit = CommitIntervalPolicy { interval: 10 };
+        let clock = FixedClock(NOW);
+        let s = DeltaTableStatus {
+            last_checkpoint_commit: Some(0),
+            last_checkpoint_timestamp: Some(NOW - 600),
+            last_optimize_commit: Some(0),
+            last_optimize_timestamp: Some(NOW - 600),
+            last_vacuum_commit: Some(0),
+            last_vacuum_timestamp: Some(NOW - 600),
+            ..Default::default()
+        };
+        assert_eq!(
+            Some(NOW + 3_000),
+            time.next_due(&Action::Optimize, &s, &clock)
+        );
+        assert_eq!(
+            Some(NOW),
+            time.next_due(
+                &Action::Optimize,
+                &DeltaTableStatus {
+                    last_checkpoint_commit: None,
+                    last_checkpoint_timestamp: None,
+                    last_optimize_commit: None,
+                    last_optimize_timestamp: None,
+                    last_vacuum_commit: None,
+                    last_vacuum_timestamp: None,
+                    ..Default::default()
+                },
+                &clock
+            )
+        );
+        assert_eq!(None, commit.next_due(&Action::Optimize, &s, &clock));
+
+        let any = AnyPolicy::new(vec![
+            Box::new(commit),
+            Box::new(time),
+            Box::new(TimeIntervalPolicy {
+                interval: Duration::from_secs(2 * 3_600),
+            }),
+        ]);
+        assert_eq!(
+            Some(NOW + 3_000),
+            any.next_due(&Action::Optimize, &s, &clock)
+        );
+    }
+
+    #[test]
+    fn test_policy_reads_status_for_action() {
+        let policy = CommitIntervalPolicy { interval: 10 };
+        let s = DeltaTableStatus {
+            last_checkpoint_commit: Some(0),
+            last_optimize_commit: Some(20),
+            ..Default::default()
+        };
+        assert!(policy.is_due(
+            &Action::Checkpoint,
+            &s,
+            &SnapshotSummary { version: 20 },
+            &FixedClock(NOW)
+        ));
+        assert!(!policy.is_due(
+            &Action::Optimize,
+            &s,
+            &SnapshotSummary { version: 20 },
+            &FixedClock(NOW)
+        ));
+        assert!(policy.is_due(
+            &Action::Vacuum,
+            &s,
+            &SnapshotSummary { version: 20 },
+            &FixedClock(NOW)
+        ));
+    }
+
+    #[test]
+    fn test_criteria_policy_defaults() {
+        let defaults = PolicyDefaults {
+            time_interval: Duration::from_secs(2 * 3_600),
+            commit_interval: 5,
+        };
+        let s = DeltaTableStatus {
+            last_checkpoint_commit: Some(0),
+            last_checkpoint_timestamp: Some(NOW - HOUR),
+            last_optimize_commit: Some(0),
+            last_optimize_timestamp: Some(NOW - HOUR),
+            last_vacuum_commit: Some(0),
+            last_vacuum_timestamp: Some(NOW - HOUR),
+            ..Default::default()
+        };
+        let time = criteria_policy(None, None, None, &defaults);
+        assert!(!time.is_due(
+            &Action::Optimize,
+            &s,
+            &SnapshotSummary { version: 100 },
+            &FixedClock(NOW)
+        ));
+        assert!(time.is_due(
+            &Action::Optimize,
+            &s,
+            &SnapshotSummary { version: 0 },
+            &FixedClock(NOW + HOUR)
+        ));
+
+        let commit = criteria_policy(Some(&MaintenanceCriteria::Commit), None, None, &defaults);
+        assert!(!commit.is_due(
+            &Action::Optimize,
+            &s,
+            &SnapshotSummary { version: 4 },
+            &FixedClock(NOW)
+        ));
+        assert!(commit.is_due(
+            &Action::Optimize,
+            &s,
+            &SnapshotSummary { version: 5 },
+            &FixedClock(NOW)
+        ));
+    }
+
+    #[test]
+    fn test_criteria_policy_overrides() {
+        let defaults = PolicyDefaults {
+            time_interval: Duration::from_secs(2 * 3_600),
+            commit_interval: 5,
+        };
+        let s = DeltaTableStatus {
+            last_checkpoint_commit: Some(0),
+            last_checkpoint_timestamp: Some(NOW - HOUR),
+            last_optimize_commit: Some(0),
+            last_optimize_timestamp: Some(NOW - HOUR),
+            last_vacuum_commit: Some(0),
+            last_vacuum_timestamp: Some(NOW - HOUR),
+            ..Default::default()
+        };
+        let policy = criteria_policy(
+            Some(&MaintenanceCriteria::TimeAndCommit),
+            Some(Duration::from_secs(3_600)),
+            Some(100),
+            &defaults,
+        );
+        assert!(policy.is_due(
+            &Action::Optimize,
+            &s,
+            &SnapshotSummary { version: 5 },
+            &FixedClock(NOW)
+        ));
+        assert!(!policy.is_due(
+            &Action::Optimize,
+            &s,
+            &SnapshotSummary { version: 5 },
+            &FixedClock(NOW - 1)
+        ));
+        assert!(policy.is_due(
+            &Action::Optimize,
+            &s,
+            &SnapshotSummary { version: 100 },
+            &FixedClock(NOW - 1)
+        ));
+    }
+
+    #[test]
+    fn test_maintenance_defaults() {
+        let defaults = MaintenanceDefaults::default();
+        assert_eq!(10, defaults.for_action(&Action::Checkpoint).commit_interval);
+        assert_eq!(50, defaults.for_action(&Action::Optimize).commit_interval);
+        assert_eq!(100, defaults.for_action(&Action::Vacuum).commit_interval);
+        assert_eq!(
+            DEFAULT_VACUUM_INTERVAL,
+            defaults.for_action(&Action::Vacuum).time_interval
+        );
+    }
+
+    #[test]
+    fn test_requires_maintenance() {
+        let mut table = DeltaTable::new(
+            "test",
+            DeltaTableSpec {
+                optimize_configuration: Some(DeltaTableMaintenceConfiguration {
+                    time_interval: None,
+                    commit_interval: Some(1),
+                    disable: None,
+                    criteria: Some(MaintenanceCriteria::Commit),
+                    job: None,
+                }),
+                vacuum_configuration: Some(DeltaTableVacuumConfiguration {
+                    time_interval: None,
+                    commit_interval: None,
+                    disable: Some(true),
+                    retention_period: None,
+                    criteria: None,
+                    job: None,
+                }),
+                ..Default::default()
+            },
+        );
+        table.status = Some(DeltaTableStatus {
+            last_checkpoint_commit: Some(1),
+            last_checkpoint_timestamp: Some(NOW),
+            last_optimize_commit: Some(1),
+            last_optimize_timestamp: Some(NOW),
+            last_vacuum_commit: Some(1),
+            last_vacuum_timestamp: Some(NOW),
+            ..Default::default()
+        });
+        let defaults = MaintenanceDefaults::default();
+        let clock = FixedClock(NOW);
+
+        assert!(!table.requires_maintenance(
+            &Action::Checkpoint,
+            &SnapshotSummary { version: 100 },
+            &defaults,
+            &clock
+        ));
+        assert!(!table.requires_maintenance(
+            &Action::Vacuum,
+            &SnapshotSummary { version: 100 },
+            &defaults,
+            &clock
+        ));
+        assert!(!table.requires_maintenance(
+            &Action::Optimize,
+            &SnapshotSummary { version: 1 },
+            &defaults,
+            &clock
+        ));
+        assert!(table.requires_maintenance(
+            &Action::Optimize,
+            &SnapshotSummary { version: 2 },
+            &defaults,
+            &clock
+        ));
+
+        table.status = None;
+        assert!(table.requires_maintenance(
+            &Action::Optimize,
+            &SnapshotSummary { version: 0 },
+            &defaults,
+            &clock
+        ));
+    }
+
+    #[test]
+    fn test_due_actions() {
+        let table = DeltaTable::new(
+            "test",
+            DeltaTableSpec {
+                checkpoint_configuration: Some(DeltaTableMaintenceConfiguration {
+                    time_interval: None,
+                    commit_interval: Some(1),
+                    disable: None,
+                    criteria: Some(MaintenanceCriteria::Commit),
+                    job: None,
+                }),
+                optimize_configuration: Some(DeltaTableMaintenceConfiguration {
+                    time_interval: None,
+                    commit_interval: Some(1),
+                    disable: None,
+                    criteria: Some(MaintenanceCriteria::Commit),
+                    job: None,
+                }),
+                ..Default::default()
+            },
+        );
+        let defaults = MaintenanceDefaults::default();
+        let clock = FixedClock(NOW);
+        let due = |order: &[Action]| {
+            table.due_actions(&SnapshotSummary { version: 2 }, &defaults, order, &clock)
+        };
+
+        assert_eq!(
+            vec![Action::Optimize, Action::Checkpoint],
+            due(&[Action::Optimize, Action::Checkpoint, Action::Vacuum])
+        );
+        // Actions left out of the order still run, last
+        assert_eq!(
+            vec![Action::Optimize, Action::Checkpoint],
+            due(&[Action::Vacuum, Action::Optimize])
+        );
+        assert_eq!(
+            vec![Action::Optimize, Action::Checkpoint],
+            due(&[Action::Optimize, Action::Optimize])
+        );
+    }
+
+    #[test]
+    fn test_still_due() {
+        let mut table = DeltaTable::new(
+            "test",
+            DeltaTableSpec {
+                optimize_configuration: Some(DeltaTableMaintenceConfiguration {
+                    time_interval: None,
+                    commit_interval: Some(10),
+                    disable: None,
+                    criteria: Some(MaintenanceCriteria::Commit),
+                    job: None,
+                }),
+                vacuum_configuration: Some(DeltaTableVacuumConfiguration {
+                    time_interval: None,
+                    commit_interval: None,
+                    disable: Some(true),
+                    retention_period: None,
+                    criteria: None,
+                    job: None,
+                }),
+                ..Default::default()
+            },
+        );
+        table.status = Some(DeltaTableStatus {
+            version: Some(10),
+            last_optimize_commit: Some(0),
+            ..Default::default()
+        });
+        let defaults = MaintenanceDefaults::default();
+        let clock = FixedClock(NOW);
+        let actions = [Action::Optimize, Action::Vacuum];
+        assert_eq!(
+            vec![Action::Optimize],
+            table.still_due(&actions, &defaults, &clock)
+        );
+
+        // Optimized by another pipeline while queued
+        table.status.as_mut().unwrap().last_optimize_commit = Some(10);
+        assert!(table.still_due(&actions, &defaults, &clock).is_empty());
+
+        // Requested actions are started even when their policy is not due
+        table.metadata.annotations = Some(std::collections::BTreeMap::from([(
+            crate::ANNOTATION_RUN.to_owned(),
+            "vacuum".to_owned(),
+        )]));
+        assert_eq!(
+            vec![Action::Vacuum],
+            table.still_due(&actions, &defaults, &clock)
+        );
+    }
+}
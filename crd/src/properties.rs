@@ -0,0 +1,273 @@
+//! Reconciles the table properties in a [`DeltaTable`](crate::DeltaTable) spec onto an
+//! existing table
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+};
+
+use deltalake::{
+    kernel::{Action, Protocol, WriterFeatures},
+    DeltaConfigKey,
+};
+use serde_json::json;
+
+use crate::{commit::commit, DeltaLakeTable, Error};
+
+/// Name of the operation recorded in the commit info of a property update
+pub const OPERATION_SET_PROPERTIES: &str = "SET TBLPROPERTIES";
+
+/// The result of comparing desired properties with a table's configuration
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PropertyPlan {
+    /// Properties to set on the table
+    pub changes: BTreeMap<String, String>,
+    /// `delta.` keys that delta-rs does not know
+    pub unsupported: Vec<String>,
+    /// Properties that need a newer protocol than the table has
+    pub requires_upgrade: Vec<String>,
+}
+
+impl PropertyPlan {
+    /// Compares `desired` with the `current` configuration. Properties missing from
+    /// `desired` are left untouched, since other writers may own them.
+    pub fn new(
+        desired: &[(String, Option<String>)],
+        current: &HashMap<String, Option<String>>,
+        protocol: &Protocol,
+    ) -> Self {
+        let mut plan = Self::default();
+        for (key, value) in desired {
+            let value = match value {
+                Some(value) => value,
+                None => continue,
+            };
+            if key.starts_with("delta.") {
+                let config_key = match DeltaConfigKey::from_str(key) {
+                    Ok(config_key) => config_key,
+                    Err(_) => {
+                        plan.unsupported.push(key.clone());
+                        continue;
+                    }
+                };
+                if !supports(protocol, &config_key, value) {
+                    plan.requires_upgrade.push(key.clone());
+                    continue;
+                }
+            }
+            if current.get(key).and_then(|v| v.as_ref()) != Some(value) {
+                plan.changes.insert(key.clone(), value.clone());
+            }
+        }
+        plan.unsupported.sort();
+        plan.requires_upgrade.sort();
+        plan
+    }
+}
+
+/// Formats `duration` as a Delta interval in its largest whole unit, e.g. `interval 7 day`
+pub fn format_interval(duration: std::time::Duration) -> String {
+    const UNITS: [(&str, u128); 6] = [
+        ("week", 604_800_000),
+        ("day", 86_400_000),
+        ("hour", 3_600_000),
+        ("minute", 60_000),
+        ("second", 1_000),
+        ("millisecond", 1),
+    ];
+    let millis = duration.as_millis();
+    let (unit, size) = UNITS
+        .iter()
+        .find(|(_, size)| millis.is_multiple_of(*size))
+        .unwrap_or(&UNITS[5]);
+    format!("interval {} {}", millis / size, unit)
+}
+
+/// The writer version and, for writer version 7, the feature needed to set `key` to
+/// `value`, or `None` when any protocol will do
+fn required_writer(key: &DeltaConfigKey, value: &str) -> Option<(i32, WriterFeatures)> {
+    let enabled = value.eq_ignore_ascii_case("true");
+    match key {
+        DeltaConfigKey::AppendOnly if enabled => Some((2, WriterFeatures::AppendOnly)),
+        DeltaConfigKey::EnableChangeDataFeed if enabled => {
+            Some((4, WriterFeatures::ChangeDataFeed))
+        }
+        DeltaConfigKey::ColumnMappingMode if !value.eq_ignore_ascii_case("none") => {
+            Some((5, WriterFeatures::ColumnMapping))
+        }
+        DeltaConfigKey::EnableDeletionVectors if enabled => {
+            Some((7, WriterFeatures::DeletionVectors))
+        }
+        _ => None,
+    }
+}
+
+fn supports(protocol: &Protocol, key: &DeltaConfigKey, value: &str) -> bool {
+    match key {
+        DeltaConfigKey::MinReaderVersion => value
+            .parse::<i32>()
+            .is_ok_and(|v| v <= protocol.min_reader_version),
+        DeltaConfigKey::MinWriterVersion => value
+            .parse::<i32>()
+            .is_ok_and(|v| v <= protocol.min_writer_version),
+        _ => match required_writer(key, value) {
+            None => true,
+            Some((version, _)) if protocol.min_writer_version < version => false,
+            Some((_, feature)) if protocol.min_writer_version >= 7 => protocol
+                .writer_features
+                .as_ref()
+                .is_some_and(|f| f.contains(&feature)),
+            Some(_) => true,
+        },
+    }
+}
+
+/// Commits `changes` to the configuration of `table`, like `ALTER TABLE ... SET
+/// TBLPROPERTIES`, and reloads it
+pub async fn set_properties(
+    table: &mut DeltaLakeTable,
+    changes: &BTreeMap<String, String>,
+) -> Result<(), Error> {
+    let mut metadata = table.metadata()?.clone();
+    for (key, value) in changes {
+        metadata
+            .configuration
+            .insert(key.clone(), Some(value.clone()));
+    }
+    let parameters = HashMap::from([(
+        "properties".to_owned(),
+        json!(serde_json::to_string(changes)?),
+    )]);
+    commit(
+        table,
+        vec![serde_json::to_value(Action::Metadata(metadata))?],
+        OPERATION_SET_PROPERTIES,
+        parameters,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        time::Duration,
+    };
+
+    use deltalake::kernel::{Protocol, WriterFeatures};
+
+    use super::{format_interval, PropertyPlan};
+
+    #[test]
+    fn test_plan_changes() {
+        let plan = PropertyPlan::new(
+            &[
+                (
+                    "delta.logRetentionDuration".to_owned(),
+                    Some("interval 7 days".to_owned()),
+                ),
+                ("delta.checkpointInterval".to_owned(), Some("10".to_owned())),
+                ("owner".to_owned(), Some("data".to_owned())),
+            ],
+            &HashMap::from([
+                ("delta.checkpointInterval".to_owned(), Some("10".to_owned())),
+                (
+                    "delta.logRetentionDuration".to_owned(),
+                    Some("interval 30 days".to_owned()),
+                ),
+                ("other".to_owned(), Some("kept".to_owned())),
+            ]),
+            &Protocol::new(1, 2),
+        );
+        assert_eq!(
+            vec![
+                ("delta.logRetentionDuration", "interval 7 days"),
+                ("owner", "data")
+            ],
+            plan.changes
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect::<Vec<_>>()
+        );
+        assert!(plan.unsupported.is_empty());
+        assert!(plan.requires_upgrade.is_empty());
+    }
+
+    #[test]
+    fn test_plan_unsupported() {
+        let plan = PropertyPlan::new(
+            &[(
+                "delta.logRetentionDuraton".to_owned(),
+                Some("interval 7 days".to_owned()),
+            )],
+            &HashMap::new(),
+            &Protocol::new(1, 2),
+        );
+        assert!(plan.changes.is_empty());
+        assert_eq!(vec!["delta.logRetentionDuraton"], plan.unsupported);
+    }
+
+    #[test]
+    fn test_plan_requires_upgrade() {
+        let properties = vec![
+            ("delta.appendOnly".to_owned(), Some("true".to_owned())),
+            (
+                "delta.enableChangeDataFeed".to_owned(),
+                Some("true".to_owned()),
+            ),
+        ];
+        let plan = PropertyPlan::new(&properties, &HashMap::new(), &Protocol::new(1, 2));
+        assert_eq!(1, plan.changes.len());
+        assert_eq!(vec!["delta.enableChangeDataFeed"], plan.requires_upgrade);
+
+        let plan = PropertyPlan::new(&properties, &HashMap::new(), &Protocol::new(1, 1));
+        assert!(plan.changes.is_empty());
+        assert_eq!(2, plan.requires_upgrade.len());
+
+        // Disabling never needs an upgrade
+        let plan = PropertyPlan::new(
+            &[("delta.appendOnly".to_owned(), Some("false".to_owned()))],
+            &HashMap::from([("delta.appendOnly".to_owned(), Some("true".to_owned()))]),
+            &Protocol::new(1, 1),
+        );
+        assert_eq!(1, plan.changes.len());
+    }
+
+    #[test]
+    fn test_plan_writer_features() {
+        let properties = vec![(
+            "delta.enableChangeDataFeed".to_owned(),
+            Some("true".to_owned()),
+        )];
+        let protocol = Protocol::new(3, 7).with_writer_features(vec![WriterFeatures::AppendOnly]);
+        let plan = PropertyPlan::new(&properties, &HashMap::new(), &protocol);
+        assert_eq!(vec!["delta.enableChangeDataFeed"], plan.requires_upgrade);
+
+        let protocol = Protocol {
+            writer_features: Some(HashSet::from([WriterFeatures::ChangeDataFeed])),
+            ..Protocol::new(3, 7)
+        };
+        let plan = PropertyPlan::new(&properties, &HashMap::new(), &protocol);
+        assert_eq!(1, plan.changes.len());
+    }
+
+    #[test]
+    fn test_format_interval() {
+        assert_eq!(
+            "interval 2 week",
+            format_interval(Duration::from_secs(1_209_600))
+        );
+        assert_eq!(
+            "interval 30 day",
+            format_interval(Duration::from_secs(2_592_000))
+        );
+        assert_eq!(
+            "interval 90 minute",
+            format_interval(Duration::from_secs(5_400))
+        );
+        assert_eq!(
+            "interval 1500 millisecond",
+            format_interval(Duration::from_millis(1_500))
+        );
+        assert_eq!("interval 0 week", format_interval(Duration::ZERO));
+    }
+}
@@ -0,0 +1,430 @@
+//! Reports and upgrades the Delta protocol of a table
+use std::collections::{HashMap, HashSet};
+
+use deltalake::kernel::{Protocol, ReaderFeatures, WriterFeatures};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{commit::commit, DeltaLakeTable, Error};
+
+/// Name of the operation recorded in the commit info of a protocol upgrade
+pub const OPERATION_UPGRADE_PROTOCOL: &str = "UPGRADE PROTOCOL";
+
+/// Protocol versions and table features requested for a table
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+pub struct DeltaTableProtocol {
+    #[schemars(range(min = 1, max = 3))]
+    pub min_reader_version: Option<i32>,
+    #[schemars(range(min = 1, max = 7))]
+    pub min_writer_version: Option<i32>,
+    /// Table features to enable, which upgrades the table to writer version 7 and, for
+    /// features readers must support, reader version 3
+    pub features: Option<Vec<TableFeature>>,
+    /// Apply upgrades. Upgrades cannot be undone.
+    pub allow_upgrade: Option<bool>,
+    /// Readers that must be able to read the table. A warning event is published when an
+    /// upgrade locks one of them out.
+    pub readers: Option<Vec<DeltaReader>>,
+}
+
+/// A consumer of the table and the protocol it supports
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq)]
+pub struct DeltaReader {
+    pub name: String,
+    /// Highest reader version the consumer supports
+    pub reader_version: i32,
+    /// Reader features the consumer supports, for reader version 3
+    pub reader_features: Option<Vec<TableFeature>>,
+}
+
+/// A Delta table feature
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum TableFeature {
+    AppendOnly,
+    Invariants,
+    CheckConstraints,
+    ChangeDataFeed,
+    GeneratedColumns,
+    ColumnMapping,
+    IdentityColumns,
+    DeletionVectors,
+    RowTracking,
+    TimestampNtz,
+    DomainMetadata,
+    V2Checkpoint,
+}
+
+impl TableFeature {
+    /// The name of the feature in the protocol
+    pub fn name(&self) -> &'static str {
+        match self {
+            TableFeature::AppendOnly => "appendOnly",
+            TableFeature::Invariants => "invariants",
+            TableFeature::CheckConstraints => "checkConstraints",
+            TableFeature::ChangeDataFeed => "changeDataFeed",
+            TableFeature::GeneratedColumns => "generatedColumns",
+            TableFeature::ColumnMapping => "columnMapping",
+            TableFeature::IdentityColumns => "identityColumns",
+            TableFeature::DeletionVectors => "deletionVectors",
+            TableFeature::RowTracking => "rowTracking",
+            TableFeature::TimestampNtz => "timestampNtz",
+            TableFeature::DomainMetadata => "domainMetadata",
+            TableFeature::V2Checkpoint => "v2Checkpoint",
+        }
+    }
+
+    /// Whether readers must support the feature
+    pub fn is_reader_feature(&self) -> bool {
+        matches!(
+            self,
+            TableFeature::ColumnMapping
+                | TableFeature::DeletionVectors
+                | TableFeature::TimestampNtz
+                | TableFeature::V2Checkpoint
+        )
+    }
+}
+
+/// The protocol of a table as reported in its status
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema, PartialEq)]
+pub struct ProtocolStatus {
+    pub min_reader_version: i32,
+    pub min_writer_version: i32,
+    pub reader_features: Option<Vec<String>>,
+    pub writer_features: Option<Vec<String>>,
+}
+
+impl From<&Protocol> for ProtocolStatus {
+    fn from(protocol: &Protocol) -> Self {
+        fn sorted<T: AsRef<str>>(features: &HashSet<T>) -> Vec<String> {
+            let mut names: Vec<String> = features.iter().map(|f| f.as_ref().to_owned()).collect();
+            names.sort();
+            names
+        }
+        Self {
+            min_reader_version: protocol.min_reader_version,
+            min_writer_version: protocol.min_writer_version,
+            reader_features: protocol.reader_features.as_ref().map(sorted),
+            writer_features: protocol.writer_features.as_ref().map(sorted),
+        }
+    }
+}
+
+/// Writer features implied by a legacy writer version
+fn implied_writer_features(version: i32) -> Vec<WriterFeatures> {
+    let mut features = Vec::new();
+    if version >= 2 {
+        features.extend([WriterFeatures::AppendOnly, WriterFeatures::Invariants]);
+    }
+    if version >= 3 {
+        features.push(WriterFeatures::CheckConstraints);
+    }
+    if version >= 4 {
+        features.extend([
+            WriterFeatures::ChangeDataFeed,
+            WriterFeatures::GeneratedColumns,
+        ]);
+    }
+    if version >= 5 {
+        features.push(WriterFeatures::ColumnMapping);
+    }
+    if version >= 6 {
+        features.push(WriterFeatures::IdentityColumns);
+    }
+    features
+}
+
+/// Whether `protocol` already supports `feature`, explicitly or through its versions
+pub(crate) fn supports(protocol: &Protocol, feature: &TableFeature) -> bool {
+    let writer = match protocol.min_writer_version {
+        7 => protocol
+            .writer_features
+            .iter()
+            .flatten()
+            .any(|f| f.as_ref() == feature.name()),
+        version => implied_writer_features(version)
+            .iter()
+            .any(|f| f.as_ref() == feature.name()),
+    };
+    let reader = match (feature.is_reader_feature(), protocol.min_reader_version) {
+        (false, _) => true,
+        (true, 3) => protocol
+            .reader_features
+            .iter()
+            .flatten()
+            .any(|f| f.as_ref() == feature.name()),
+        (true, 2) => *feature == TableFeature::ColumnMapping,
+        (true, _) => false,
+    };
+    writer && reader
+}
+
+/// The protocol `spec` asks for, which is never older than `current`
+pub fn target(current: &Protocol, spec: &DeltaTableProtocol) -> Protocol {
+    let features: Vec<TableFeature> = spec
+        .features
+        .iter()
+        .flatten()
+        .filter(|f| !supports(current, f))
+        .cloned()
+        .collect();
+    let mut reader = current
+        .min_reader_version
+        .max(spec.min_reader_version.unwrap_or_default());
+    let mut writer = current
+        .min_writer_version
+        .max(spec.min_writer_version.unwrap_or_default());
+    if features.iter().any(|f| f.is_reader_feature()) {
+        reader = reader.max(3);
+    }
+    if !features.is_empty() || reader >= 3 {
+        writer = 7;
+    }
+
+    let mut target = Protocol::new(reader, writer);
+    if writer >= 7 {
+        let mut writer_features: HashSet<WriterFeatures> = match current.min_writer_version {
+            7 => current.writer_features.clone().unwrap_or_default(),
+            version => implied_writer_features(version).into_iter().collect(),
+        };
+        writer_features.extend(features.iter().map(|f| WriterFeatures::from(f.name())));
+        target.writer_features = Some(writer_features);
+    }
+    if reader >= 3 {
+        let mut reader_features: HashSet<ReaderFeatures> = match current.min_reader_version {
+            3 => current.reader_features.clone().unwrap_or_default(),
+            2 => HashSet::from([ReaderFeatures::ColumnMapping]),
+            _ => HashSet::new(),
+        };
+        reader_features.extend(
+            features
+                .iter()
+                .filter(|f| f.is_reader_feature())
+                .map(|f| ReaderFeatures::from(f.name())),
+        );
+        target.reader_features = Some(reader_features);
+    }
+    target
+}
+
+fn can_read(protocol: &Protocol, reader: &DeltaReader) -> bool {
+    if protocol.min_reader_version > reader.reader_version {
+        return false;
+    }
+    if protocol.min_reader_version < 3 {
+        return true;
+    }
+    let supported: Vec<&str> = reader
+        .reader_features
+        .iter()
+        .flatten()
+        .map(|f| f.name())
+        .collect();
+    protocol
+        .reader_features
+        .iter()
+        .flatten()
+        .all(|f| supported.contains(&f.as_ref()))
+}
+
+/// Names of the `readers` that can read `current` but not `target`
+pub fn locked_out(current: &Protocol, target: &Protocol, readers: &[DeltaReader]) -> Vec<String> {
+    readers
+        .iter()
+        .filter(|r| can_read(current, r) && !can_read(target, r))
+        .map(|r| r.name.clone())
+        .collect()
+}
+
+/// The protocol action for `protocol`
+///
+/// delta-rs writes the `timestampNtz` feature as `timestampWithoutTimezone`, so the
+/// action is built from the feature names instead.
+fn protocol_action(protocol: &Protocol) -> serde_json::Value {
+    let status = ProtocolStatus::from(protocol);
+    let mut action = json!({
+        "minReaderVersion": status.min_reader_version,
+        "minWriterVersion": status.min_writer_version,
+    });
+    if let Some(features) = status.reader_features {
+        action["readerFeatures"] = json!(features);
+    }
+    if let Some(features) = status.writer_features {
+        action["writerFeatures"] = json!(features);
+    }
+    json!({ "protocol": action })
+}
+
+/// Commits `protocol` to `table` and reloads it
+pub async fn upgrade(table: &mut DeltaLakeTable, protocol: Protocol) -> Result<(), Error> {
+    let action = protocol_action(&protocol);
+    let parameters = HashMap::from([
+        (
+            "newProtocol".to_owned(),
+            json!(action["protocol"].to_string()),
+        ),
+        (
+            "oldProtocol".to_owned(),
+            json!(protocol_action(table.protocol()?)["protocol"].to_string()),
+        ),
+    ]);
+    commit(table, vec![action], OPERATION_UPGRADE_PROTOCOL, parameters).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use deltalake::kernel::{Protocol, ReaderFeatures, WriterFeatures};
+
+    use super::{
+        locked_out, protocol_action, target, DeltaReader, DeltaTableProtocol, TableFeature,
+    };
+
+    fn spec(features: Vec<TableFeature>) -> DeltaTableProtocol {
+        DeltaTableProtocol {
+            features: Some(features),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_target_unchanged() {
+        let current = Protocol::new(1, 2);
+        assert_eq!(current, target(&current, &DeltaTableProtocol::default()));
+
+        // Never downgrades
+        let requested = DeltaTableProtocol {
+            min_reader_version: Some(1),
+            min_writer_version: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(current, target(&current, &requested));
+    }
+
+    #[test]
+    fn test_target_supported_feature() {
+        let current = Protocol::new(1, 4);
+        assert_eq!(
+            current,
+            target(&current, &spec(vec![TableFeature::ChangeDataFeed]))
+        );
+
+        let current = Protocol::new(3, 7)
+            .with_reader_features(vec![ReaderFeatures::DeletionVectors])
+            .with_writer_features(vec![WriterFeatures::DeletionVectors]);
+        assert_eq!(
+            current,
+            target(&current, &spec(vec![TableFeature::DeletionVectors]))
+        );
+    }
+
+    #[test]
+    fn test_target_versions() {
+        let requested = DeltaTableProtocol {
+            min_writer_version: Some(4),
+            ..Default::default()
+        };
+        assert_eq!(
+            Protocol::new(1, 4),
+            target(&Protocol::new(1, 2), &requested)
+        );
+    }
+
+    #[test]
+    fn test_target_writer_feature() {
+        let protocol = target(
+            &Protocol::new(1, 3),
+            &spec(vec![TableFeature::DomainMetadata]),
+        );
+        assert_eq!(1, protocol.min_reader_version);
+        assert_eq!(7, protocol.min_writer_version);
+        assert!(protocol.reader_features.is_none());
+        assert_eq!(
+            Some(HashSet::from([
+                WriterFeatures::AppendOnly,
+                WriterFeatures::Invariants,
+                WriterFeatures::CheckConstraints,
+                WriterFeatures::DomainMetadata,
+            ])),
+            protocol.writer_features
+        );
+    }
+
+    #[test]
+    fn test_target_reader_feature() {
+        let protocol = target(
+            &Protocol::new(2, 5),
+            &spec(vec![TableFeature::DeletionVectors]),
+        );
+        assert_eq!(3, protocol.min_reader_version);
+        assert_eq!(7, protocol.min_writer_version);
+        assert_eq!(
+            Some(HashSet::from([
+                ReaderFeatures::ColumnMapping,
+                ReaderFeatures::DeletionVectors,
+            ])),
+            protocol.reader_features
+        );
+        assert!(protocol
+            .writer_features
+            .unwrap()
+            .contains(&WriterFeatures::DeletionVectors));
+    }
+
+    #[test]
+    fn test_locked_out() {
+        let readers = vec![
+            DeltaReader {
+                name: "legacy".into(),
+                reader_version: 1,
+                reader_features: None,
+            },
+            DeltaReader {
+                name: "modern".into(),
+                reader_version: 3,
+                reader_features: Some(vec![
+                    TableFeature::DeletionVectors,
+                    TableFeature::TimestampNtz,
+                ]),
+            },
+            DeltaReader {
+                name: "partial".into(),
+                reader_version: 3,
+                reader_features: Some(vec![TableFeature::TimestampNtz]),
+            },
+        ];
+        let current = Protocol::new(1, 2);
+        let upgraded = target(&current, &spec(vec![TableFeature::DeletionVectors]));
+        assert_eq!(
+            vec!["legacy".to_owned(), "partial".to_owned()],
+            locked_out(&current, &upgraded, &readers)
+        );
+
+        let upgraded = target(&current, &spec(vec![TableFeature::ChangeDataFeed]));
+        assert!(locked_out(&current, &upgraded, &readers).is_empty());
+    }
+
+    #[test]
+    fn test_protocol_action() {
+        let protocol = target(
+            &Protocol::new(1, 2),
+            &spec(vec![TableFeature::TimestampNtz]),
+        );
+        assert_eq!(
+            serde_json::json!({"protocol": {
+                "minReaderVersion": 3,
+                "minWriterVersion": 7,
+                "readerFeatures": ["timestampNtz"],
+                "writerFeatures": ["appendOnly", "invariants", "timestampNtz"],
+            }}),
+            protocol_action(&protocol)
+        );
+        assert_eq!(
+            serde_json::json!({"protocol": {"minReaderVersion": 1, "minWriterVersion": 2}}),
+            protocol_action(&Protocol::new(1, 2))
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Tracing shared by the controller and the worker
+use std::collections::HashMap;
+
+use opentelemetry::{
+    propagation::{Extractor, Injector, TextMapPropagator},
+    trace::TraceError,
+    KeyValue,
+};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{propagation::TraceContextPropagator, runtime, trace, Resource};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{
+    filter::LevelFilter, fmt::format::FmtSpan, layer::SubscriberExt, util::SubscriberInitExt,
+    EnvFilter, Layer,
+};
+
+/// W3C trace context of the span that created a worker Job, so that the reconcile that
+/// decided on maintenance and the worker that ran it belong to one trace
+pub const ENV_TRACEPARENT: &str = "TRACEPARENT";
+/// Standard variable holding the OTLP endpoint, also passed on to workers
+pub const ENV_OTLP_ENDPOINT: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+const TRACEPARENT: &str = "traceparent";
+
+/// Installs the global subscriber for `service_name`. Spans are exported to the OTLP gRPC
+/// `otlp_endpoint` when it is set, and otherwise logged to stdout when they close.
+///
+/// Logs are filtered by `RUST_LOG`, `info` by default; exported spans are not.
+pub fn init(service_name: &'static str, otlp_endpoint: Option<&str>) -> Result<(), TraceError> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let fmt = tracing_subscriber::fmt::layer()
+        .with_target(false)
+        .compact();
+    let (fmt, otlp) = match otlp_endpoint {
+        Some(endpoint) => {
+            let tracer = opentelemetry_otlp::new_pipeline()
+                .tracing()
+                .with_exporter(
+                    opentelemetry_otlp::new_exporter()
+                        .tonic()
+                        .with_endpoint(endpoint),
+                )
+                .with_trace_config(trace::config().with_resource(Resource::new(vec![
+                    KeyValue::new("service.name", service_name),
+                ])))
+                .install_batch(runtime::Tokio)?;
+            let otlp = tracing_opentelemetry::layer()
+                .with_tracer(tracer)
+                .with_filter(LevelFilter::INFO);
+            (fmt.boxed(), Some(otlp))
+        }
+        None => (fmt.with_span_events(FmtSpan::CLOSE).boxed(), None),
+    };
+    tracing_subscriber::registry()
+        .with(fmt.with_filter(filter))
+        .with(otlp)
+        .init();
+    Ok(())
+}
+
+/// Exports the spans that have not been sent yet
+pub fn shutdown() {
+    opentelemetry::global::shutdown_tracer_provider();
+}
+
+/// The W3C `traceparent` of the current span, if it is part of a trace
+pub fn current_traceparent() -> Option<String> {
+    let mut carrier = Carrier::default();
+    TraceContextPropagator::new().inject_context(&tracing::Span::current().context(), &mut carrier);
+    carrier.0.remove(TRACEPARENT)
+}
+
+/// Continues the trace of `traceparent` in `span`
+pub fn set_parent(span: &tracing::Span, traceparent: &str) {
+    let carrier = Carrier(HashMap::from([(
+        TRACEPARENT.to_owned(),
+        traceparent.to_owned(),
+    )]));
+    span.set_parent(TraceContextPropagator::new().extract(&carrier));
+}
+
+#[derive(Default)]
+struct Carrier(HashMap<String, String>);
+
+impl Injector for Carrier {
+    fn set(&mut self, key: &str, value: String) {
+        self.0.insert(key.to_owned(), value);
+    }
+}
+
+impl Extractor for Carrier {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(String::as_str).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::{
+        propagation::TextMapPropagator,
+        trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState},
+        Context,
+    };
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+
+    use super::{current_traceparent, Carrier, TRACEPARENT};
+
+    const PARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn test_traceparent_round_trip() {
+        let carrier = Carrier([(TRACEPARENT.to_owned(), PARENT.to_owned())].into());
+        let context = TraceContextPropagator::new().extract(&carrier);
+        let span = context.span();
+        let span_context = span.span_context();
+        assert_eq!(
+            TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap(),
+            span_context.trace_id()
+        );
+        assert!(span_context.is_remote());
+
+        let context = Context::new().with_remote_span_context(SpanContext::new(
+            span_context.trace_id(),
+            SpanId::from_hex("00f067aa0ba902b7").unwrap(),
+            TraceFlags::SAMPLED,
+            true,
+            TraceState::default(),
+        ));
+        let mut carrier = Carrier::default();
+        TraceContextPropagator::new().inject_context(&context, &mut carrier);
+        assert_eq!(Some(PARENT), carrier.0.get(TRACEPARENT).map(String::as_str));
+    }
+
+    #[test]
+    fn test_no_traceparent_outside_trace() {
+        assert_eq!(None, current_traceparent());
+    }
+}
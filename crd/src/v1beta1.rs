@@ -0,0 +1,311 @@
+//! The `v1beta1` version of the [`DeltaTable`] resource
+use std::collections::{BTreeMap, HashMap};
+
+use kube::{core::Duration, CustomResource};
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    constraints::TableConstraint, failure::FailureClass, freshness::Severity, maintenance::Action,
+    protocol::TableFeature, ColumnMappingMode, ConstraintViolation, CreationPolicy, IsolationLevel,
+    MaintenanceCriteria, QueuedMaintenance, RunRequestStatus, StorageOptionReference,
+    TableCreation,
+};
+
+// The storage version. Unlike `v1alpha1`, fields are camelCase, the schema is structured
+// and the maintenance settings live in one `maintenance` block.
+#[derive(CustomResource, Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[kube(
+    kind = "DeltaTable",
+    group = "delta-operator.rs",
+    version = "v1beta1",
+    namespaced
+)]
+#[kube(status = "DeltaTableStatus", shortname = "dt", category = "delta")]
+#[kube(
+    printcolumn = r#"{"name":"URI", "type":"string", "jsonPath":".spec.tableUri"}"#,
+    printcolumn = r#"{"name":"Version", "type":"integer", "jsonPath":".status.version"}"#,
+    printcolumn = r#"{"name":"Ready", "type":"string", "jsonPath":".status.conditions[?(@.type==\"Ready\")].status"}"#,
+    printcolumn = r#"{"name":"Optimized", "type":"date", "jsonPath":".status.lastOptimizeTime"}"#,
+    printcolumn = r#"{"name":"Vacuumed", "type":"date", "jsonPath":".status.lastVacuumTime"}"#,
+    printcolumn = r#"{"name":"Files", "type":"integer", "jsonPath":".status.statistics.numFiles"}"#,
+    printcolumn = r#"{"name":"Age", "type":"date", "jsonPath":".metadata.creationTimestamp"}"#
+)]
+#[serde(rename_all = "camelCase")]
+pub struct DeltaTableSpec {
+    /// Name of the table
+    pub name: String,
+    /// Location of the table
+    pub table_uri: String,
+    /// Allow http uris
+    pub allow_http: Option<bool>,
+    /// Columns of the table and whether the operator manages them
+    pub schema: TableSchema,
+    /// Columns to use when partitioning the table
+    #[serde(default)]
+    pub partition_columns: Vec<String>,
+    /// When checkpoint, optimize and vacuum run and the priority of their maintenance
+    pub maintenance: Option<MaintenanceSettings>,
+    /// Set options used to initialize storage backend
+    pub storage_options: Option<BTreeMap<String, String>>,
+    /// Like `storageOptions`, but can come from `ConfigMap` or `Secret` resources
+    pub storage_options_from: Option<Vec<StorageOptionReference>>,
+    /// Delta table configuration settings
+    pub configuration: Option<TableConfiguration>,
+    /// CHECK constraints every row of the table must satisfy
+    pub constraints: Option<Vec<TableConstraint>>,
+    /// Protocol versions and table features to upgrade the table to
+    pub protocol: Option<TableProtocol>,
+    /// Whether a table may be created at `tableUri` or an existing one adopted.
+    /// Defaults to `CreateIfMissing`.
+    pub creation_policy: Option<CreationPolicy>,
+    /// Stop creating the table, updating its schema and running maintenance while still
+    /// reporting its status
+    pub suspend: Option<bool>,
+    /// Count the files and rows of the table into `status.statistics`, which walks
+    /// every file of the snapshot whenever the table is loaded or the counts are older
+    /// than the resync interval
+    pub statistics: Option<bool>,
+    /// Poll the log for new commits and report each one
+    pub watch_commits: Option<CommitWatch>,
+    /// Report the table as stale when its latest commit is older than `maxAge`
+    pub freshness: Option<Freshness>,
+}
+
+/// How old the latest commit of a table may be
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Freshness {
+    pub max_age: Duration,
+    pub severity: Option<Severity>,
+}
+
+/// How new commits are polled and reported
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitWatch {
+    pub interval: Option<Duration>,
+    /// URL that receives a JSON `POST` for every new commit
+    pub callback_url: Option<String>,
+}
+
+/// Columns of a table
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TableSchema {
+    /// Add new columns and apply column settings to an existing table
+    pub manage: Option<bool>,
+    /// Top level fields in the Delta schema serialization format
+    pub fields: Option<Vec<SchemaField>>,
+    /// Comments, metadata and generation expressions of the columns in `fields`
+    pub columns: Option<Vec<ColumnSettings>>,
+}
+
+/// A field of the schema, see
+/// <https://github.com/delta-io/delta/blob/master/PROTOCOL.md#schema-serialization-format>
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+pub struct SchemaField {
+    pub name: String,
+    /// A primitive type name such as `long`, or a struct, array or map type object
+    #[serde(rename = "type")]
+    #[schemars(schema_with = "any_value")]
+    pub type_: serde_json::Value,
+    pub nullable: bool,
+    #[serde(default)]
+    #[schemars(schema_with = "any_object")]
+    pub metadata: BTreeMap<String, serde_json::Value>,
+}
+
+fn any_value(_: &mut SchemaGenerator) -> Schema {
+    serde_json::from_value(serde_json::json!({
+        "x-kubernetes-preserve-unknown-fields": true
+    }))
+    .unwrap()
+}
+
+fn any_object(_: &mut SchemaGenerator) -> Schema {
+    serde_json::from_value(serde_json::json!({
+        "type": "object",
+        "x-kubernetes-preserve-unknown-fields": true
+    }))
+    .unwrap()
+}
+
+/// Settings for a top level column of the schema
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnSettings {
+    pub name: String,
+    pub comment: Option<String>,
+    /// Custom metadata, such as PII tags or owners
+    pub metadata: Option<BTreeMap<String, String>>,
+    /// SQL expression the column is generated from, e.g. `CAST(timestamp AS DATE)`
+    pub generation_expression: Option<String>,
+}
+
+/// Maintenance settings of a table
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceSettings {
+    /// Order in which pending maintenance for this table is started relative to other
+    /// tables when the controller limits concurrent maintenance. Higher runs first.
+    pub priority: Option<i32>,
+    pub checkpoint: Option<MaintenanceActionSettings>,
+    pub optimize: Option<MaintenanceActionSettings>,
+    pub vacuum: Option<VacuumSettings>,
+}
+
+/// When a maintenance action runs
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceActionSettings {
+    pub time_interval: Option<Duration>,
+    pub commit_interval: Option<i32>,
+    pub disable: Option<bool>,
+    pub criteria: Option<MaintenanceCriteria>,
+    /// Retries, deadline and history of the worker jobs running the action
+    pub job: Option<JobLifecycle>,
+}
+
+/// How the job running a maintenance action is retried, stopped and kept
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JobLifecycle {
+    #[schemars(range(min = 0))]
+    pub backoff_limit: Option<i32>,
+    pub active_deadline: Option<Duration>,
+    pub ttl_after_finished: Option<Duration>,
+    #[schemars(range(min = 0))]
+    pub successful_jobs_history_limit: Option<i32>,
+    #[schemars(range(min = 0))]
+    pub failed_jobs_history_limit: Option<i32>,
+}
+
+/// When vacuum runs and which files it removes
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct VacuumSettings {
+    #[serde(flatten)]
+    pub action: MaintenanceActionSettings,
+    pub retention_period: Option<Duration>,
+}
+
+/// Table properties, see [`DeltaTableConfiguration`](crate::DeltaTableConfiguration)
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TableConfiguration {
+    pub enable_change_feed: Option<bool>,
+    pub append_only: Option<bool>,
+    pub log_retention_duration: Option<Duration>,
+    pub deleted_file_retention_duration: Option<Duration>,
+    #[schemars(range(min = 1))]
+    pub checkpoint_interval: Option<i32>,
+    #[schemars(range(min = -1))]
+    pub data_skipping_num_indexed_cols: Option<i32>,
+    pub data_skipping_stats_columns: Option<Vec<String>>,
+    #[schemars(range(min = 1))]
+    pub target_file_size: Option<i64>,
+    pub tune_file_sizes_for_rewrites: Option<bool>,
+    pub isolation_level: Option<IsolationLevel>,
+    pub column_mapping_mode: Option<ColumnMappingMode>,
+    pub additional_settings: Option<HashMap<String, String>>,
+}
+
+/// Protocol versions and table features requested for a table
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TableProtocol {
+    #[schemars(range(min = 1, max = 3))]
+    pub min_reader_version: Option<i32>,
+    #[schemars(range(min = 1, max = 7))]
+    pub min_writer_version: Option<i32>,
+    pub features: Option<Vec<TableFeature>>,
+    /// Apply upgrades. Upgrades cannot be undone.
+    pub allow_upgrade: Option<bool>,
+    pub readers: Option<Vec<Reader>>,
+}
+
+/// A consumer of the table and the protocol it supports
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Reader {
+    pub name: String,
+    pub reader_version: i32,
+    pub reader_features: Option<Vec<TableFeature>>,
+}
+
+/// The status object of `DeltaTable`
+#[derive(Deserialize, Serialize, Clone, Default, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DeltaTableStatus {
+    pub table_uri: String,
+    pub schema: String,
+    pub last_checkpoint_commit: Option<i64>,
+    pub last_checkpoint_timestamp: Option<i64>,
+    pub last_vacuum_commit: Option<i64>,
+    pub last_vacuum_timestamp: Option<i64>,
+    pub last_optimize_commit: Option<i64>,
+    pub last_optimize_timestamp: Option<i64>,
+    pub version: Option<i64>,
+    pub is_healthy: Option<bool>,
+    pub maintenance_queue: Option<QueuedMaintenance>,
+    pub last_run_request: Option<RunRequestStatus>,
+    pub suspended: Option<bool>,
+    pub creation: Option<TableCreation>,
+    pub creation_uri: Option<String>,
+    pub conditions: Option<Vec<Condition>>,
+    pub protocol: Option<ProtocolStatus>,
+    pub constraints: Option<Vec<TableConstraint>>,
+    pub constraint_violations: Option<Vec<ConstraintViolation>>,
+    pub last_optimize_time: Option<String>,
+    pub last_vacuum_time: Option<String>,
+    pub statistics: Option<Statistics>,
+    pub observed_generation: Option<i64>,
+    pub last_commit_timestamp: Option<i64>,
+    pub last_operation: Option<String>,
+    pub last_failure: Option<Failure>,
+}
+
+/// Failed maintenance
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Failure {
+    pub action: Option<Action>,
+    pub reason: FailureClass,
+    pub message: String,
+    pub exit_code: i32,
+    pub time: String,
+    pub pod: Option<String>,
+}
+
+/// Files of a table version, as reported in the status
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Statistics {
+    pub num_files: i64,
+    pub size_bytes: i64,
+    pub num_records: Option<i64>,
+    pub timestamp: Option<i64>,
+}
+
+/// An observation of a table's state, following Kubernetes condition conventions
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Condition {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub status: String,
+    pub reason: Option<String>,
+    pub message: Option<String>,
+    pub last_transition_time: Option<String>,
+}
+
+/// The protocol of a table as reported in its status
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProtocolStatus {
+    pub min_reader_version: i32,
+    pub min_writer_version: i32,
+    pub reader_features: Option<Vec<String>>,
+    pub writer_features: Option<Vec<String>>,
+}
@@ -1,5 +1,9 @@
 use clap::Parser;
-use delta_operator_crd::maintenance::Action;
+use delta_operator_crd::{
+    failure::TERMINATION_MESSAGE_PATH,
+    maintenance::{Action, ENV_WORKER_PUSHGATEWAY_URL},
+    telemetry::ENV_OTLP_ENDPOINT,
+};
 
 pub const DEFAULT_WORKER_NAME: &str = "delta-operator-worker";
 
@@ -13,11 +17,23 @@ pub struct Arguments {
     #[clap(long, required = true)]
     pub namespace: String,
 
+    /// Actions to run in order. Repeat the flag or separate actions with commas
     #[arg(value_enum)]
-    #[clap(long)]
-    pub action: Action,
+    #[clap(long, required = true, value_delimiter = ',')]
+    pub action: Vec<Action>,
     #[clap(long, env, default_value = DEFAULT_WORKER_NAME)]
     pub worker_name: Option<String>,
+    /// Prometheus Pushgateway that receives the metrics of the run before the worker exits
+    #[clap(long, env = ENV_WORKER_PUSHGATEWAY_URL)]
+    pub pushgateway_url: Option<String>,
+    /// OTLP gRPC endpoint that spans are exported to. Spans are logged to stdout when it
+    /// is not set.
+    #[clap(long, env = ENV_OTLP_ENDPOINT)]
+    pub otlp_endpoint: Option<String>,
+    /// File the failure is written to when maintenance fails, read by Kubernetes as the
+    /// termination message of the container
+    #[clap(long, default_value = TERMINATION_MESSAGE_PATH)]
+    pub termination_message_path: String,
 }
 
 #[cfg(test)]
@@ -40,11 +56,22 @@ mod tests {
             "optimize",
             "--worker-name",
             "test-worker",
+            "--pushgateway-url",
+            "http://pushgateway:9091",
+            "--otlp-endpoint",
+            "http://collector:4317",
+            "--termination-message-path",
+            "/tmp/termination-log",
         ]);
         assert_eq!("test-table", arguments.table);
-        assert!(matches!(arguments.action, Action::Optimize));
+        assert_eq!(vec![Action::Optimize], arguments.action);
+        assert_eq!(
+            "http://pushgateway:9091",
+            arguments.pushgateway_url.unwrap()
+        );
         assert_eq!("test-worker", arguments.worker_name.unwrap());
         assert_eq!("something", arguments.namespace);
+        assert_eq!("/tmp/termination-log", arguments.termination_message_path);
     }
 
     #[test]
@@ -67,9 +94,40 @@ mod tests {
             ]);
 
             assert_eq!("test-table", arguments.table);
-            assert!(arguments.action == v);
+            assert!(arguments.action == vec![v]);
             assert_eq!("test-worker", arguments.worker_name.unwrap());
             assert_eq!("something", arguments.namespace);
         }
     }
+
+    #[test]
+    fn test_pipeline_actions() {
+        let arguments = Arguments::parse_from(vec![
+            "worker",
+            "--table",
+            "test-table",
+            "--namespace",
+            "something",
+            "--action",
+            "optimize,checkpoint",
+            "--action",
+            "vacuum",
+        ]);
+        assert_eq!(
+            vec![Action::Optimize, Action::Checkpoint, Action::Vacuum],
+            arguments.action
+        );
+    }
+
+    #[test]
+    fn test_action_required() {
+        let result = Arguments::try_parse_from(vec![
+            "worker",
+            "--table",
+            "test-table",
+            "--namespace",
+            "something",
+        ]);
+        assert!(result.is_err());
+    }
 }
@@ -1,14 +1,13 @@
-use app::Arguments;
+use chrono::Utc;
 use clap::Parser;
-use delta_operator_crd::maintenance::{
-    checkpoint_table, optimize_table, vacuum_table, Action, ENV_WORKER_POD_NAME,
-};
-use delta_operator_crd::{DeltaLakeTable, DeltaTable, Error};
-use kube::api::{Patch, PatchParams};
+use delta_operator_crd::failure::{clear_failure, publish_failure, MaintenanceFailure};
+use delta_operator_crd::maintenance::{join_actions, perform_pipeline, ENV_WORKER_POD_NAME};
+use delta_operator_crd::{telemetry, DeltaTable, Error};
 use kube::runtime::events::{Recorder, Reporter};
 use kube::{Api, Client, Resource};
-use serde_json::json;
+use tracing::Instrument;
 mod app;
+mod push;
 
 #[tokio::main]
 async fn main() {
@@ -16,98 +15,101 @@ async fn main() {
     let pod_name =
         std::env::var(ENV_WORKER_POD_NAME).unwrap_or(app::DEFAULT_WORKER_NAME.to_owned());
     let args = app::Arguments::parse();
-    let client = match Client::try_default().await {
-        Ok(client) => client,
-        Err(err) => panic!("unable to initialize kubernetes client: {}", err),
-    };
-    let api: Api<DeltaTable> = Api::namespaced(client.clone(), &args.namespace);
-    let table = match api.get(&args.table).await {
-        Ok(table) => table,
-        Err(err) => panic!(
-            "unable to retrieve delta table resource `{}`: {}",
-            args.table, err
-        ),
-    };
+    if let Err(e) = telemetry::init("delta-operator-worker", args.otlp_endpoint.as_deref()) {
+        // Maintenance still runs without traces, logging spans to stdout instead
+        telemetry::init("delta-operator-worker", None).ok();
+        tracing::error!("failed to install the OTLP exporter: {}", e);
+    }
+    let metrics = args
+        .pushgateway_url
+        .as_ref()
+        .and_then(|url| match push::install() {
+            Ok(handle) => Some((url, handle)),
+            Err(e) => {
+                tracing::error!("failed to install the metrics recorder: {}", e);
+                None
+            }
+        });
 
-    let delta_lake_table = match table.delta_lake_table(client.clone()).await {
-        Ok(dt) => dt,
-        Err(dt) => panic!("unable to access delta table `{}`: {}", args.table, dt),
+    let result = run(&args, pod_name).await;
+    if let Some((url, handle)) = metrics {
+        let url = push::group_url(
+            url,
+            args.worker_name.as_ref().unwrap(),
+            &args.namespace,
+            &args.table,
+        );
+        if let Err(e) = push::push(&url, &handle).await {
+            tracing::error!("failed to push metrics to {}: {}", url, e);
+        }
+    }
+    let code = match result {
+        Ok(()) => 0,
+        Err(failure) => {
+            tracing::error!(
+                "failed to perform maintenance {} on delta table `{}`: {}",
+                join_actions(&args.action, ","),
+                args.table,
+                failure.message
+            );
+            if let Err(e) = std::fs::write(
+                &args.termination_message_path,
+                failure.termination_message(),
+            ) {
+                tracing::error!(
+                    "failed to write the termination message to {}: {}",
+                    args.termination_message_path,
+                    e
+                );
+            }
+            failure.exit_code
+        }
     };
-    let result = perform_maintenance(&table, delta_lake_table, &args.action).await;
+    telemetry::shutdown();
+    std::process::exit(code);
+}
+
+/// Runs the maintenance pipeline, recording a failure in the status of the table when
+/// it can be reached
+async fn run(args: &app::Arguments, pod_name: String) -> Result<(), MaintenanceFailure> {
+    let failure =
+        |error: Error| MaintenanceFailure::new(&error, Some(pod_name.clone()), Utc::now());
+    let client = Client::try_default().await.map_err(|e| failure(e.into()))?;
+    let api: Api<DeltaTable> = Api::namespaced(client.clone(), &args.namespace);
+    let table = api.get(&args.table).await.map_err(|e| failure(e.into()))?;
 
     let reporter = Reporter {
         controller: args.worker_name.as_ref().unwrap().to_owned(),
-        instance: Some(pod_name),
+        instance: Some(pod_name.clone()),
     };
+    let recorder = Recorder::new(client.clone(), reporter, table.object_ref(&()));
 
-    let recorder = Recorder::new(client, reporter, table.object_ref(&()));
-    match result {
-        Ok(patch_value) => publish_success(&api, &recorder, &args, patch_value).await,
-        Err(err) => {
-            panic!(
-                "failed to perform maintence {} on delta table `{}`: {}",
-                args.action, args.table, err
-            );
-        }
-    };
-}
-
-async fn perform_maintenance(
-    table: &DeltaTable,
-    delta_lake_table: DeltaLakeTable,
-    action: &Action,
-) -> Result<serde_json::Value, Error> {
-    match action {
-        Action::Checkpoint => checkpoint_table(table, delta_lake_table).await.map(|dt| {
-            json!({
-                "status": {
-                    "last_checkpoint_commit": dt.version(),
-                    "last_checkpoint_timestamp": chrono::Utc::now().timestamp(),
-                },
-            })
-        }),
-        Action::Optimize => optimize_table(table, delta_lake_table).await.map(|dt| {
-            json!({
-                "status": {
-                    "last_optimize_commit": dt.version(),
-                    "last_optimize_timestamp": chrono::Utc::now().timestamp(),
-                }
-            })
-        }),
-        Action::Vacuum => vacuum_table(table, delta_lake_table).await.map(|dt| {
-            json!({
-                "status": {
-                    "last_vacuum_commit": dt.version(),
-                    "last_vacuum_timestamp": chrono::Utc::now().timestamp(),
-                }
-            })
-        }),
+    let actions = join_actions(&args.action, ",");
+    let span = tracing::info_span!("worker", table = %args.table, actions = %actions);
+    if let Ok(traceparent) = std::env::var(telemetry::ENV_TRACEPARENT) {
+        telemetry::set_parent(&span, &traceparent);
     }
-}
-
-async fn publish_success(
-    api: &Api<DeltaTable>,
-    recorder: &Recorder,
-    args: &Arguments,
-    value: serde_json::Value,
-) {
-    let pp = PatchParams::default();
-    if let Err(e) = api
-        .patch_status(&args.table, &pp, &Patch::Merge(value))
-        .await
-    {
-        panic!("failed to patch delta table: {}", e);
+    let result = async {
+        let delta_lake_table = table.delta_lake_table(client).await?;
+        perform_pipeline(&api, &recorder, &table, delta_lake_table, &args.action).await
     }
-    if let Err(e) = recorder
-        .publish(kube::runtime::events::Event {
-            type_: kube::runtime::events::EventType::Normal,
-            reason: format!("{} requested by controller", args.action),
-            note: Some("Success".into()),
-            action: format!("{}", args.action),
-            secondary: None,
-        })
-        .await
-    {
-        tracing::error!("failed to record delta table changes: {}", e);
+    .instrument(span)
+    .await;
+    match result {
+        Ok(_) => {
+            if table
+                .status
+                .as_ref()
+                .is_some_and(|s| s.last_failure.is_some())
+            {
+                clear_failure(&api, &args.table).await;
+            }
+            Ok(())
+        }
+        Err(e) => {
+            let failure = failure(e);
+            publish_failure(&api, Some(&recorder), &args.table, &failure).await;
+            Err(failure)
+        }
     }
 }
@@ -0,0 +1,57 @@
+//! Pushes the metrics of a run to a Prometheus Pushgateway
+use delta_operator_crd::maintenance::METRIC_MAINTENANCE_DURATION;
+use metrics_exporter_prometheus::{BuildError, Matcher, PrometheusBuilder, PrometheusHandle};
+
+/// Buckets of [`METRIC_MAINTENANCE_DURATION`], from a second to six hours
+const DURATION_BUCKETS: [f64; 10] = [
+    1.0, 5.0, 15.0, 60.0, 300.0, 900.0, 1800.0, 3600.0, 7200.0, 21600.0,
+];
+
+/// Installs the recorder whose metrics are pushed on exit, since worker pods finish
+/// before they could be scraped
+pub(crate) fn install() -> Result<PrometheusHandle, BuildError> {
+    PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Full(METRIC_MAINTENANCE_DURATION.into()),
+            &DURATION_BUCKETS,
+        )?
+        .install_recorder()
+}
+
+/// URL of the group of the worker's metrics on the Pushgateway at `base`, keyed by
+/// table so that each table's last run replaces its previous one
+pub(crate) fn group_url(base: &str, job: &str, namespace: &str, table: &str) -> String {
+    format!(
+        "{}/metrics/job/{}/namespace/{}/table/{}",
+        base.trim_end_matches('/'),
+        job,
+        namespace,
+        table
+    )
+}
+
+/// Replaces the metrics of the group at `url` with those recorded by `handle`
+pub(crate) async fn push(url: &str, handle: &PrometheusHandle) -> Result<(), String> {
+    reqwest::Client::new()
+        .put(url)
+        .timeout(std::time::Duration::from_secs(10))
+        .body(handle.render())
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::group_url;
+
+    #[test]
+    fn test_group_url() {
+        assert_eq!(
+            "http://pushgateway:9091/metrics/job/worker/namespace/data/table/clowns",
+            group_url("http://pushgateway:9091/", "worker", "data", "clowns")
+        );
+    }
+}